@@ -54,14 +54,21 @@ def find_log_file():
 
 
 def load_entries(log_file):
-    """Load all entries from the JSONL file."""
+    """Load the check-ins from the JSONL file.
+
+    Lines with a record_type (recovery, Pomodoro phase, outcome and similar
+    records) describe the session rather than a check-in and are left out.
+    """
     entries = []
     with open(log_file, 'r') as f:
         for line in f:
             try:
-                entries.append(json.loads(line))
+                entry = json.loads(line)
             except json.JSONDecodeError as e:
                 print(f"Warning: Skipping invalid JSON line: {e}")
+                continue
+            if 'record_type' not in entry:
+                entries.append(entry)
     return entries
 
 
@@ -0,0 +1,191 @@
+//! Adaptive check-in scheduling.
+//!
+//! A run of "On Task" answers lengthens the check-in interval up to the
+//! maximum; a distraction shortens it down to the minimum. Breaks and skips
+//! leave it alone but end the run.
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats::{self, StatusKind};
+
+/// Minutes added or removed per adjustment.
+const STEP_MINUTES: u32 = 5;
+/// On-task answers in a row needed before the interval grows.
+const ON_TASK_RUN: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveInterval {
+    pub min_interval: u32,
+    pub max_interval: u32,
+    /// Minutes until the next check-in.
+    pub interval: u32,
+    pub on_task_streak: u32,
+    /// Why the interval last changed; `None` until it first does.
+    pub reason: Option<String>,
+}
+
+/// What one answer did to the interval, journaled with the check-in so
+/// analytics can tell adaptive sessions from fixed ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IntervalAdjustment {
+    /// The interval that led up to this check-in.
+    pub interval: u32,
+    pub next_interval: u32,
+    /// Set when `next_interval` differs from `interval`.
+    pub reason: Option<String>,
+}
+
+impl AdaptiveInterval {
+    /// Start at the configured interval, kept within the bounds.
+    pub fn new(min_interval: u32, max_interval: u32, interval: u32) -> Self {
+        Self {
+            min_interval,
+            max_interval,
+            // Not `clamp`, which panics on inverted bounds; `is_valid` reports those.
+            interval: interval.max(min_interval).min(max_interval),
+            on_task_streak: 0,
+            reason: None,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.min_interval > 0
+            && self.min_interval <= self.max_interval
+            && (self.min_interval..=self.max_interval).contains(&self.interval)
+    }
+
+    pub fn after_answer(&self, status: &str) -> (Self, IntervalAdjustment) {
+        let mut next = self.clone();
+        let mut reason = None;
+        let kind = stats::status_kind(status);
+        if kind == Some(StatusKind::OnTask) {
+            next.on_task_streak += 1;
+            if next.on_task_streak >= ON_TASK_RUN && next.interval < next.max_interval {
+                next.interval = (next.interval + STEP_MINUTES).min(next.max_interval);
+                reason = Some(format!("{} on-task answers in a row", next.on_task_streak));
+                next.on_task_streak = 0;
+            }
+        } else {
+            next.on_task_streak = 0;
+            if kind == Some(StatusKind::Distraction) && next.interval > next.min_interval {
+                next.interval = next
+                    .interval
+                    .saturating_sub(STEP_MINUTES)
+                    .max(next.min_interval);
+                reason = Some(format!("answered {status}"));
+            }
+        }
+        if reason.is_some() {
+            next.reason = reason.clone();
+        }
+        let adjustment = IntervalAdjustment {
+            interval: self.interval,
+            next_interval: next.interval,
+            reason,
+        };
+        (next, adjustment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The interval after each answer, checking every adjustment on the way.
+    fn intervals(mut adaptive: AdaptiveInterval, statuses: &[&str]) -> Vec<u32> {
+        let mut intervals = Vec::new();
+        for status in statuses {
+            let (next, adjustment) = adaptive.after_answer(status);
+            assert_eq!(adjustment.interval, adaptive.interval);
+            assert_eq!(adjustment.next_interval, next.interval);
+            assert_eq!(
+                adjustment.reason.is_some(),
+                next.interval != adaptive.interval
+            );
+            intervals.push(next.interval);
+            adaptive = next;
+        }
+        intervals
+    }
+
+    #[test]
+    fn on_task_runs_lengthen_the_interval_and_other_answers_end_the_run() {
+        let adaptive = AdaptiveInterval::new(10, 40, 20);
+        assert_eq!(
+            intervals(
+                adaptive.clone(),
+                &["On Task", "On Task", "On Task", "On Task"]
+            ),
+            [20, 25, 25, 30]
+        );
+        // A break or skip between on-task answers restarts the run without
+        // shortening the interval.
+        assert_eq!(
+            intervals(
+                adaptive.clone(),
+                &["On Task", "Taking a Break", "On Task", "Skip", "On Task"]
+            ),
+            [20, 20, 20, 20, 20]
+        );
+        let (next, _) = adaptive.after_answer("On Task");
+        let (next, _) = next.after_answer("On Task");
+        assert_eq!(next.reason.as_deref(), Some("2 on-task answers in a row"));
+        assert_eq!(next.on_task_streak, 0);
+    }
+
+    #[test]
+    fn distractions_shorten_the_interval() {
+        let adaptive = AdaptiveInterval::new(5, 40, 30);
+        assert_eq!(
+            intervals(
+                adaptive.clone(),
+                &["Social Media", "Email/Chat", "Other Distraction"]
+            ),
+            [25, 20, 15]
+        );
+        let (next, _) = adaptive.after_answer("Email/Chat");
+        assert_eq!(next.reason.as_deref(), Some("answered Email/Chat"));
+        // Statuses outside the known set are not treated as distractions.
+        assert_eq!(intervals(adaptive, &["Lunch"]), [30]);
+    }
+
+    #[test]
+    fn interval_stays_within_the_bounds() {
+        assert_eq!(
+            intervals(
+                AdaptiveInterval::new(10, 32, 30),
+                &["On Task", "On Task", "On Task", "On Task"]
+            ),
+            [30, 32, 32, 32]
+        );
+        assert_eq!(
+            intervals(
+                AdaptiveInterval::new(12, 30, 15),
+                &["Social Media", "Social Media"]
+            ),
+            [12, 12]
+        );
+        assert_eq!(AdaptiveInterval::new(10, 30, 45).interval, 30);
+        assert_eq!(AdaptiveInterval::new(10, 30, 5).interval, 10);
+        assert!(AdaptiveInterval::new(10, 30, 45).is_valid());
+    }
+
+    #[test]
+    fn an_empty_window_never_moves_and_inverted_bounds_are_invalid() {
+        let fixed = AdaptiveInterval::new(20, 20, 20);
+        assert!(fixed.is_valid());
+        assert_eq!(
+            intervals(
+                fixed.clone(),
+                &["On Task", "On Task", "On Task", "Social Media"]
+            ),
+            [20, 20, 20, 20]
+        );
+        let (next, _) = fixed.after_answer("Social Media");
+        assert_eq!(next.reason, None);
+
+        assert!(!AdaptiveInterval::new(30, 10, 20).is_valid());
+        assert!(!AdaptiveInterval::new(0, 10, 5).is_valid());
+    }
+}
@@ -0,0 +1,554 @@
+//! Billable-hours report: time per project and day over a date range, built
+//! from the journal's check-ins and Pomodoro break records.
+//!
+//! On-task check-ins credit the interval before them as billable time, break
+//! answers credit it as break time and other answers as distracted time.
+//! Skipped and auto-submitted prompts say nothing about the interval and
+//! credit nothing. Pauses stop the countdown, so paused time is never
+//! credited. Journal lines without a project are left out.
+
+use chrono::{NaiveDate, TimeZone};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    error::AppError,
+    logs::{LogEntry, POMODORO_PHASE_RECORD},
+    projects::Project,
+    stats::{self, StatusKind},
+};
+
+/// Largest rounding increment, in minutes.
+pub const MAX_ROUNDING_MINUTES: u32 = 60;
+
+const CSV_HEADER: &str = "date,project,project_name,sessions,on_task_minutes,\
+distracted_minutes,break_minutes,billable_minutes,billable_hours";
+
+/// One project's time on one day.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingLine {
+    pub date: NaiveDate,
+    pub project: String,
+    /// Sessions with a check-in for the project that day.
+    pub sessions: u32,
+    pub on_task_minutes: u64,
+    pub distracted_minutes: u64,
+    pub break_minutes: u64,
+    /// On-task minutes rounded up to the report's increment.
+    pub billable_minutes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTotals {
+    pub project: String,
+    pub sessions: u32,
+    pub on_task_minutes: u64,
+    pub distracted_minutes: u64,
+    pub break_minutes: u64,
+    /// Sum of the rounded daily lines.
+    pub billable_minutes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingReport {
+    pub from: NaiveDate,
+    /// Inclusive.
+    pub to: NaiveDate,
+    /// 0 bills exact minutes.
+    pub rounding_minutes: u32,
+    /// By date, then project id.
+    pub lines: Vec<BillingLine>,
+    /// By project id.
+    pub projects: Vec<ProjectTotals>,
+}
+
+/// Round `minutes` up to a multiple of `increment`; 0 leaves them as they are.
+pub fn round_up(minutes: u64, increment: u32) -> u64 {
+    match u64::from(increment) {
+        0 => minutes,
+        step => minutes.div_ceil(step) * step,
+    }
+}
+
+/// Build the report for the days `from` to `to` inclusive, as seen in `zone`.
+///
+/// Each project's daily on-task time is rounded up separately, the way time
+/// is usually billed per day worked.
+pub fn report<Tz: TimeZone>(
+    entries: &[LogEntry],
+    from: NaiveDate,
+    to: NaiveDate,
+    rounding_minutes: u32,
+    zone: &Tz,
+) -> Result<BillingReport, AppError> {
+    if from > to {
+        return Err(AppError::InvalidArgument(
+            "the report must not end before it starts",
+        ));
+    }
+    if rounding_minutes > MAX_ROUNDING_MINUTES {
+        return Err(AppError::InvalidArgument(
+            "the rounding increment must be at most 60 minutes",
+        ));
+    }
+
+    // Break time is kept in seconds until the end so short breaks add up.
+    let mut lines: BTreeMap<(NaiveDate, String), (BillingLine, u64)> = BTreeMap::new();
+    // The last check-in number seen per project; a number that does not
+    // follow it starts a new session.
+    let mut last_check_in: HashMap<String, u32> = HashMap::new();
+    for entry in entries {
+        let Some(project) = entry.project.as_deref().filter(|id| !id.is_empty()) else {
+            continue;
+        };
+        let Some(time) = entry.parsed_timestamp() else {
+            continue;
+        };
+        let date = time.with_timezone(zone).date_naive();
+        let in_range = (from..=to).contains(&date);
+        let line = || {
+            let line = BillingLine {
+                date,
+                project: project.to_string(),
+                ..BillingLine::default()
+            };
+            (line, 0)
+        };
+
+        if entry.record_type.as_deref() == Some(POMODORO_PHASE_RECORD) {
+            let Some(details) = entry.details.as_ref().filter(|_| in_range) else {
+                continue;
+            };
+            if matches!(details["phase"].as_str(), Some("shortBreak" | "longBreak")) {
+                let seconds = details["seconds"].as_u64().unwrap_or(0);
+                lines
+                    .entry((date, project.to_string()))
+                    .or_insert_with(line)
+                    .1 += seconds;
+            }
+            continue;
+        }
+        if !entry.is_check_in() {
+            continue;
+        }
+
+        let number = entry.check_in_number.unwrap_or(1);
+        let new_session = last_check_in
+            .insert(project.to_string(), number)
+            .is_none_or(|last| number <= last);
+        if !in_range {
+            continue;
+        }
+        let (line, break_seconds) = lines
+            .entry((date, project.to_string()))
+            .or_insert_with(line);
+        if new_session {
+            line.sessions += 1;
+        }
+        if !stats::credits_interval(entry) {
+            continue;
+        }
+        let minutes = stats::credited_minutes(entry);
+        match stats::status_kind(&entry.reported_status) {
+            Some(StatusKind::OnTask) => line.on_task_minutes += minutes,
+            Some(StatusKind::Break) => *break_seconds += minutes * 60,
+            Some(StatusKind::Skipped) => {}
+            Some(StatusKind::Distraction) | None => line.distracted_minutes += minutes,
+        }
+    }
+
+    let mut projects: BTreeMap<String, ProjectTotals> = BTreeMap::new();
+    let lines: Vec<BillingLine> = lines
+        .into_values()
+        .map(|(mut line, break_seconds)| {
+            line.break_minutes = break_seconds / 60;
+            line.billable_minutes = round_up(line.on_task_minutes, rounding_minutes);
+            let totals = projects
+                .entry(line.project.clone())
+                .or_insert_with(|| ProjectTotals {
+                    project: line.project.clone(),
+                    ..ProjectTotals::default()
+                });
+            totals.sessions += line.sessions;
+            totals.on_task_minutes += line.on_task_minutes;
+            totals.distracted_minutes += line.distracted_minutes;
+            totals.break_minutes += line.break_minutes;
+            totals.billable_minutes += line.billable_minutes;
+            line
+        })
+        .collect();
+
+    Ok(BillingReport {
+        from,
+        to,
+        rounding_minutes,
+        lines,
+        projects: projects.into_values().collect(),
+    })
+}
+
+/// The registry name for a project id, or the id for unregistered projects.
+pub fn project_name<'a>(registry: &'a [Project], id: &'a str) -> &'a str {
+    registry
+        .iter()
+        .find(|project| project.id == id)
+        .map_or(id, |project| project.name.as_str())
+}
+
+/// Billable minutes as decimal hours, e.g. 90 -> "1.50".
+pub fn hours(minutes: u64) -> String {
+    format!("{:.2}", minutes as f64 / 60.0)
+}
+
+/// Render the daily lines as RFC 4180 CSV with a header row.
+pub fn to_csv(report: &BillingReport, registry: &[Project]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+    for line in &report.lines {
+        let fields = [
+            line.date.to_string(),
+            line.project.clone(),
+            project_name(registry, &line.project).to_string(),
+            line.sessions.to_string(),
+            line.on_task_minutes.to_string(),
+            line.distracted_minutes.to_string(),
+            line.break_minutes.to_string(),
+            line.billable_minutes.to_string(),
+            hours(line.billable_minutes),
+        ];
+        let row: Vec<String> = fields
+            .iter()
+            .map(|field| crate::export::escape(field))
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Render a self-contained HTML page with per-project totals and the daily
+/// lines, styled to print on a single sheet where it fits.
+pub fn to_html(report: &BillingReport, registry: &[Project]) -> String {
+    let rounding = match report.rounding_minutes {
+        0 => "exact minutes".to_string(),
+        minutes => format!("rounded up to {minutes} minutes per project and day"),
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+<title>Billable hours {from} to {to}</title>\n<style>\n\
+body {{ font-family: -apple-system, BlinkMacSystemFont, \"Segoe UI\", sans-serif; margin: 2em; color: #222; }}\n\
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 2em; }}\n\
+th, td {{ border-bottom: 1px solid #ddd; padding: 0.3em 0.6em; text-align: left; }}\n\
+td.number, th.number {{ text-align: right; }}\n\
+tfoot td {{ font-weight: bold; border-top: 2px solid #222; }}\n\
+.swatch {{ display: inline-block; width: 0.8em; height: 0.8em; border-radius: 50%; margin-right: 0.4em; }}\n\
+@media print {{ body {{ margin: 0; }} tr {{ break-inside: avoid; }} }}\n\
+</style>\n</head>\n<body>\n<h1>Billable hours</h1>\n<p>{from} to {to}, {rounding}</p>\n",
+        from = report.from,
+        to = report.to,
+    );
+
+    html.push_str(
+        "<h2>By project</h2>\n<table>\n<thead><tr><th>Project</th><th class=\"number\">Sessions</th>\
+<th class=\"number\">On task</th><th class=\"number\">Distracted</th><th class=\"number\">Breaks</th>\
+<th class=\"number\">Billable</th><th class=\"number\">Hours</th></tr></thead>\n<tbody>\n",
+    );
+    for totals in &report.projects {
+        let color = registry
+            .iter()
+            .find(|project| project.id == totals.project)
+            .map_or("#888888", |project| project.color.as_str());
+        html.push_str(&format!(
+            "<tr><td><span class=\"swatch\" style=\"background: {}\"></span>{}</td>{}</tr>\n",
+            escape_html(color),
+            escape_html(project_name(registry, &totals.project)),
+            minute_cells(
+                totals.sessions,
+                totals.on_task_minutes,
+                totals.distracted_minutes,
+                totals.break_minutes,
+                totals.billable_minutes
+            )
+        ));
+    }
+    let billable: u64 = report
+        .projects
+        .iter()
+        .map(|totals| totals.billable_minutes)
+        .sum();
+    html.push_str(&format!(
+        "</tbody>\n<tfoot><tr><td colspan=\"5\">Total</td><td class=\"number\">{billable} min</td>\
+<td class=\"number\">{}</td></tr></tfoot>\n</table>\n",
+        hours(billable)
+    ));
+
+    html.push_str(
+        "<h2>By day</h2>\n<table>\n<thead><tr><th>Date</th><th>Project</th><th class=\"number\">Sessions</th>\
+<th class=\"number\">On task</th><th class=\"number\">Distracted</th><th class=\"number\">Breaks</th>\
+<th class=\"number\">Billable</th><th class=\"number\">Hours</th></tr></thead>\n<tbody>\n",
+    );
+    for line in &report.lines {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td>{}</tr>\n",
+            line.date,
+            escape_html(project_name(registry, &line.project)),
+            minute_cells(
+                line.sessions,
+                line.on_task_minutes,
+                line.distracted_minutes,
+                line.break_minutes,
+                line.billable_minutes
+            )
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+fn minute_cells(
+    sessions: u32,
+    on_task: u64,
+    distracted: u64,
+    breaks: u64,
+    billable: u64,
+) -> String {
+    format!(
+        "<td class=\"number\">{sessions}</td><td class=\"number\">{on_task} min</td>\
+<td class=\"number\">{distracted} min</td><td class=\"number\">{breaks} min</td>\
+<td class=\"number\">{billable} min</td><td class=\"number\">{}</td>",
+        hours(billable)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn line(timestamp: &str, project: &str, number: u32, status: &str) -> LogEntry {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "session_goal": "Client work",
+            "reported_status": status,
+            "check_in_interval_setting": 20,
+            "check_in_number": number,
+            "project": project,
+        }))
+        .unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn report_rounds_each_project_day_and_separates_distractions_and_breaks() {
+        let mut entries = vec![
+            line("2025-11-03T09:20:00Z", "acme", 1, "On Task"),
+            line("2025-11-03T09:40:00Z", "acme", 2, "Email/Chat"),
+            line("2025-11-03T10:00:00Z", "acme", 3, "Skip"),
+            line("2025-11-03T10:17:00Z", "acme", 4, "On Task"),
+            line("2025-11-04T09:20:00Z", "acme", 1, "On Task"),
+            line("2025-11-04T11:20:00Z", "globex", 1, "On Task"),
+            // Outside the range, but it numbers the session that follows.
+            line("2025-11-05T09:20:00Z", "acme", 1, "On Task"),
+        ];
+        entries[3].check_in_interval_setting = Some(17);
+        entries.push(
+            serde_json::from_value(serde_json::json!({
+                "timestamp": "2025-11-03T10:22:00Z",
+                "record_type": POMODORO_PHASE_RECORD,
+                "project": "acme",
+                "details": { "phase": "shortBreak", "seconds": 300 },
+            }))
+            .unwrap(),
+        );
+        let mut untracked = line("2025-11-03T09:20:00Z", "", 1, "On Task");
+        untracked.project = None;
+        entries.push(untracked);
+
+        let report = report(&entries, date("2025-11-03"), date("2025-11-04"), 15, &Utc).unwrap();
+        assert_eq!(
+            report.lines[0],
+            BillingLine {
+                date: date("2025-11-03"),
+                project: "acme".to_string(),
+                sessions: 1,
+                on_task_minutes: 37,
+                distracted_minutes: 20,
+                break_minutes: 5,
+                billable_minutes: 45,
+            }
+        );
+        assert_eq!(report.lines.len(), 3);
+        assert_eq!(report.projects[0].sessions, 2);
+        assert_eq!(report.projects[0].on_task_minutes, 57);
+        assert_eq!(report.projects[0].billable_minutes, 45 + 30);
+        assert_eq!(report.projects[1].project, "globex");
+
+        // Days follow the given time zone: at UTC-10 the check-ins before
+        // 10:00 UTC on 3 November fall on the 2nd, while 09:20 UTC on
+        // 4 November is still the 3rd.
+        let hawaii = FixedOffset::west_opt(10 * 3600).unwrap();
+        let shifted =
+            super::report(&entries, date("2025-11-03"), date("2025-11-03"), 0, &hawaii).unwrap();
+        assert_eq!(shifted.projects[0].on_task_minutes, 17 + 20);
+        assert_eq!(shifted.projects[0].distracted_minutes, 0);
+
+        let registry = vec![Project {
+            id: "acme".to_string(),
+            name: "Acme, Inc.".to_string(),
+            color: "#3366cc".to_string(),
+            archived: false,
+        }];
+        let csv = to_csv(&report, &registry);
+        let mut rows = csv.split("\r\n");
+        assert_eq!(rows.next(), Some(CSV_HEADER));
+        assert_eq!(
+            rows.next(),
+            Some("2025-11-03,acme,\"Acme, Inc.\",1,37,20,5,45,0.75")
+        );
+        let html = to_html(&report, &registry);
+        assert!(html.contains("Acme, Inc.</td>"));
+        assert!(html.contains("<td class=\"number\">75 min</td><td class=\"number\">1.25</td>"));
+
+        assert!(super::report(&entries, date("2025-11-04"), date("2025-11-03"), 0, &Utc).is_err());
+        assert!(super::report(&entries, date("2025-11-03"), date("2025-11-04"), 90, &Utc).is_err());
+    }
+
+    fn pomodoro_break(timestamp: &str, project: &str, seconds: u64) -> LogEntry {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "record_type": POMODORO_PHASE_RECORD,
+            "project": project,
+            "details": { "phase": "shortBreak", "seconds": seconds },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn break_answers_and_short_pomodoro_breaks_add_up_as_break_time() {
+        let entries = vec![
+            line("2025-11-03T09:20:00Z", "acme", 1, "On Task"),
+            line("2025-11-03T09:40:00Z", "acme", 2, "Taking a Break"),
+            pomodoro_break("2025-11-03T10:00:00Z", "acme", 50),
+            pomodoro_break("2025-11-03T10:30:00Z", "acme", 50),
+            pomodoro_break("2025-11-03T11:00:00Z", "acme", 50),
+        ];
+        let report = report(&entries, date("2025-11-03"), date("2025-11-03"), 0, &Utc).unwrap();
+        let acme = &report.projects[0];
+        assert_eq!(acme.on_task_minutes, 20);
+        assert_eq!(acme.distracted_minutes, 0);
+        // 20 minutes plus 150 seconds; each 50-second break alone rounds to nothing.
+        assert_eq!(acme.break_minutes, 22);
+    }
+
+    #[test]
+    fn skipped_and_auto_submitted_prompts_credit_nothing() {
+        let mut auto_skip = line("2025-11-03T09:40:00Z", "acme", 2, "Skip");
+        auto_skip.auto_submitted = Some(true);
+        let mut auto_answer = line("2025-11-03T10:00:00Z", "acme", 3, "Other Distraction");
+        auto_answer.prompt_outcome = Some(crate::logs::PromptOutcome::AutoSubmitted);
+        let entries = vec![
+            line("2025-11-03T09:20:00Z", "acme", 1, "On Task"),
+            auto_skip,
+            auto_answer,
+            line("2025-11-03T10:20:00Z", "acme", 4, "Skip"),
+        ];
+        let report = report(&entries, date("2025-11-03"), date("2025-11-03"), 0, &Utc).unwrap();
+        assert_eq!(
+            report.lines,
+            [BillingLine {
+                date: date("2025-11-03"),
+                project: "acme".to_string(),
+                sessions: 1,
+                on_task_minutes: 20,
+                distracted_minutes: 0,
+                break_minutes: 0,
+                billable_minutes: 20,
+            }]
+        );
+    }
+
+    #[test]
+    fn rounding_applies_to_each_project_and_day_separately() {
+        assert_eq!(round_up(0, 6), 0);
+        assert_eq!(round_up(37, 0), 37);
+        assert_eq!(round_up(37, 6), 42);
+        assert_eq!(round_up(42, 6), 42);
+        assert_eq!(round_up(1, 60), 60);
+
+        let mut short = line("2025-11-03T09:07:00Z", "acme", 2, "On Task");
+        short.check_in_interval_setting = Some(7);
+        let entries = vec![
+            line("2025-11-03T09:00:00Z", "acme", 1, "On Task"),
+            short,
+            line("2025-11-03T09:20:00Z", "globex", 1, "On Task"),
+            line("2025-11-04T09:20:00Z", "acme", 1, "On Task"),
+        ];
+        let billable = |rounding| -> Vec<(String, u64)> {
+            report(
+                &entries,
+                date("2025-11-03"),
+                date("2025-11-04"),
+                rounding,
+                &Utc,
+            )
+            .unwrap()
+            .projects
+            .into_iter()
+            .map(|totals| (totals.project, totals.billable_minutes))
+            .collect()
+        };
+        assert_eq!(
+            billable(0),
+            [("acme".to_string(), 47), ("globex".to_string(), 20)]
+        );
+        // acme: 27 rounds to 30 on the 3rd and 20 to 24 on the 4th.
+        assert_eq!(
+            billable(6),
+            [("acme".to_string(), 30 + 24), ("globex".to_string(), 24)]
+        );
+        assert_eq!(
+            billable(15),
+            [("acme".to_string(), 30 + 30), ("globex".to_string(), 30)]
+        );
+    }
+
+    #[test]
+    fn projects_get_their_own_lines_sessions_and_totals() {
+        let entries = vec![
+            line("2025-11-03T09:20:00Z", "acme", 1, "On Task"),
+            line("2025-11-03T09:40:00Z", "globex", 1, "Social Media"),
+            line("2025-11-03T10:00:00Z", "acme", 2, "On Task"),
+            line("2025-11-03T10:20:00Z", "globex", 2, "On Task"),
+            line("2025-11-03T13:20:00Z", "acme", 1, "On Task"),
+        ];
+        let report = report(&entries, date("2025-11-03"), date("2025-11-03"), 0, &Utc).unwrap();
+        let lines: Vec<(&str, u32, u64, u64)> = report
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.project.as_str(),
+                    line.sessions,
+                    line.on_task_minutes,
+                    line.distracted_minutes,
+                )
+            })
+            .collect();
+        assert_eq!(lines, [("acme", 2, 60, 0), ("globex", 1, 20, 20)]);
+        assert_eq!(report.projects.len(), 2);
+        assert_eq!(report.projects[1].billable_minutes, 20);
+    }
+}
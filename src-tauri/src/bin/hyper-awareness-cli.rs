@@ -0,0 +1,554 @@
+//! Terminal companion for Hyper Awareness.
+//!
+//! Reads and appends to the same `focus_log.jsonl` and `active_session.json`
+//! as the menu bar app, without starting a webview.
+
+use std::{io::Write, path::Path, process::ExitCode};
+
+use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
+use hyper_awareness::{
+    billing,
+    clock::SystemClock,
+    control, doctor, export, goals, logs,
+    projects::{self, Project},
+    session_state, settings, stats,
+    storage::Storage,
+    templates,
+};
+use serde_json::json;
+
+const DEFAULT_PROJECT_COLOR: &str = "#888888";
+
+const USAGE: &str = "\
+Usage: hyper-awareness-cli [--config-dir DIR] <command>
+
+Commands:
+  status                       Show the saved session
+  log [--limit N]              Show the most recent check-ins (default 20)
+  stats --week                 Summarize the last seven days
+  export --csv                 Write the whole journal as CSV to stdout
+  billing [--from DATE] [--to DATE] [--round MIN] [--csv|--html]
+                               Billable hours per project, this month by default
+  doctor [--repair]            Check every data file; --repair asks before each fix
+  templates                    List the saved session templates
+  goals [SEARCH]               List past goals, or the best matches for SEARCH
+  goals --hide|--show GOAL     Hide a goal from suggestions, or show it again
+  projects                     List the project registry
+  projects --add ID --name NAME [--color #RRGGBB]
+                               Add a project, or rename or recolor one
+  projects --archive|--restore ID
+                               Archive a project, or bring it back
+  start --template ID          Start a session from a template in the running app
+  checkin STATUS [--note TEXT] Record a check-in, e.g. checkin \"On Task\"; STATUS is
+                               On Task, Taking a Break, Skip, Social Media,
+                               Email/Chat or Other Distraction";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let storage = match take_option(&mut args, "--config-dir")? {
+        Some(dir) => Storage::open(dir)?,
+        None => Storage::open(Storage::default_root()?)?,
+    };
+    let log_path = storage.log_path();
+    let state_path = storage.state_path();
+
+    let Some(command) = (!args.is_empty()).then(|| args.remove(0)) else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+
+    match command.as_str() {
+        "status" => status(&state_path),
+        "log" => {
+            let limit = match take_option(&mut args, "--limit")? {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| format!("--limit expects a number, got {limit}"))?,
+                None => 20,
+            };
+            log(&log_path, limit)
+        }
+        "stats" => {
+            take_flag(&mut args, "--week");
+            week_stats(&storage)
+        }
+        "export" => {
+            if !take_flag(&mut args, "--csv") {
+                return Err("export requires --csv".to_string());
+            }
+            print!(
+                "{}",
+                export::entries_to_csv(&logs::read_entries_from_path(&log_path)?)
+            );
+            Ok(())
+        }
+        "billing" => {
+            let today = Local::now().date_naive();
+            let from = match take_option(&mut args, "--from")? {
+                Some(date) => parse_date(&date)?,
+                None => today.with_day(1).unwrap_or(today),
+            };
+            let to = match take_option(&mut args, "--to")? {
+                Some(date) => parse_date(&date)?,
+                None => today,
+            };
+            let rounding = match take_option(&mut args, "--round")? {
+                Some(minutes) => minutes
+                    .parse()
+                    .map_err(|_| format!("--round expects a number of minutes, got {minutes}"))?,
+                None => settings::load(&storage)?.billing_rounding_minutes,
+            };
+            let report = billing::report(
+                &logs::read_entries_from_path(&log_path)?,
+                from,
+                to,
+                rounding,
+                &Local,
+            )?;
+            let registry = projects::load(&storage)?;
+            match (
+                take_flag(&mut args, "--csv"),
+                take_flag(&mut args, "--html"),
+            ) {
+                (true, true) => return Err("use either --csv or --html".to_string()),
+                (true, false) => print!("{}", billing::to_csv(&report, &registry)),
+                (false, true) => print!("{}", billing::to_html(&report, &registry)),
+                (false, false) => print_billing(&report, &registry),
+            }
+            Ok(())
+        }
+        "doctor" => {
+            let repair = take_flag(&mut args, "--repair");
+            doctor(&storage, repair)
+        }
+        "templates" => list_templates(&storage),
+        "projects" => {
+            if let Some(id) = take_option(&mut args, "--add")? {
+                let name = take_option(&mut args, "--name")?
+                    .ok_or_else(|| "--add requires --name NAME".to_string())?;
+                let color = take_option(&mut args, "--color")?
+                    .unwrap_or_else(|| DEFAULT_PROJECT_COLOR.to_string());
+                let archived = false;
+                Ok(projects::save(
+                    &storage,
+                    Project {
+                        id,
+                        name,
+                        color,
+                        archived,
+                    },
+                )?)
+            } else if let Some(id) = take_option(&mut args, "--archive")? {
+                Ok(projects::set_archived(&storage, &id, true)?)
+            } else if let Some(id) = take_option(&mut args, "--restore")? {
+                Ok(projects::set_archived(&storage, &id, false)?)
+            } else {
+                list_projects(&storage)
+            }
+        }
+        "goals" => {
+            let hide = take_option(&mut args, "--hide")?;
+            let show = take_option(&mut args, "--show")?;
+            match (hide, show) {
+                (Some(goal), None) => Ok(goals::set_hidden(&storage, &goal, true)?),
+                (None, Some(goal)) => Ok(goals::set_hidden(&storage, &goal, false)?),
+                (None, None) => {
+                    let query = (!args.is_empty()).then(|| args.remove(0));
+                    list_goals(&storage, query.as_deref())
+                }
+                (Some(_), Some(_)) => Err("use either --hide or --show".to_string()),
+            }
+        }
+        "start" => {
+            let id = take_option(&mut args, "--template")?
+                .ok_or_else(|| "start requires --template ID".to_string())?;
+            start_from_template(&storage, &id)
+        }
+        "checkin" => {
+            let note = take_option(&mut args, "--note")?;
+            let status = args
+                .first()
+                .cloned()
+                .ok_or_else(|| "checkin requires a status, e.g. \"On Task\"".to_string())?;
+            args.remove(0);
+            check_in(&storage, &status, note.as_deref())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => Err(format!("Unknown command: {other}\n\n{USAGE}")),
+    }
+    .and_then(|_| match args.first() {
+        Some(extra) => Err(format!("Unexpected argument: {extra}")),
+        None => Ok(()),
+    })
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(format!("{name} expects a value"));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a date like 2025-11-03, got {value}"))
+}
+
+fn format_minutes(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn status(state_path: &Path) -> Result<(), String> {
+    let Some(state) = session_state::load_from_path(state_path)? else {
+        println!("No saved session");
+        return Ok(());
+    };
+
+    println!("Phase:         {:?}", state.phase);
+    println!("Goal:          {}", state.session_goal);
+    println!(
+        "Session left:  {}",
+        format_minutes(state.session_time_remaining)
+    );
+    println!(
+        "Next check-in: {}",
+        format_minutes(state.check_in_time_remaining)
+    );
+    println!(
+        "Check-ins:     {} ({} skipped)",
+        state.check_ins_completed, state.skipped_check_ins
+    );
+    if let Some(template_id) = state.template_id {
+        println!("Template:      {template_id}");
+    }
+    if let Some(project) = state.project {
+        println!("Project:       {project}");
+    }
+    if !state.tags.is_empty() {
+        println!("Tags:          {}", state.tags.join(", "));
+    }
+    for subtask in &state.subtasks {
+        let mark = if subtask.completed_at.is_some() {
+            'x'
+        } else {
+            ' '
+        };
+        println!("  [{mark}] {}", subtask.text);
+    }
+    if let Some(reason) = state.recovery_reason {
+        println!("Recovery:      {reason}");
+    }
+    Ok(())
+}
+
+fn list_goals(storage: &Storage, query: Option<&str>) -> Result<(), String> {
+    let history = goals::load(storage)?;
+    let shown = match query {
+        Some(query) => goals::search(&history, query, 20, Utc::now()),
+        None => history,
+    };
+    if shown.is_empty() {
+        println!("No matching goals");
+    }
+    for record in shown {
+        println!(
+            "{}  {:>3} sessions  {:>5} min  {}{}",
+            record.last_used.format("%Y-%m-%d"),
+            record.sessions,
+            record.focused_minutes,
+            record.goal,
+            if record.hidden { "  (hidden)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+fn list_projects(storage: &Storage) -> Result<(), String> {
+    let projects = projects::load(storage)?;
+    if projects.is_empty() {
+        println!("No projects in {}", storage.projects_path().display());
+    }
+    for project in projects {
+        println!(
+            "{:<20} {}  {}{}",
+            project.id,
+            project.color,
+            project.name,
+            if project.archived { "  (archived)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+fn list_templates(storage: &Storage) -> Result<(), String> {
+    let templates = templates::load(storage)?;
+    if templates.is_empty() {
+        println!(
+            "No session templates in {}",
+            storage.templates_path().display()
+        );
+    }
+    for template in templates {
+        println!(
+            "{:<16} {}m, check in every {}m — {}",
+            template.id,
+            template.session_duration,
+            template.check_in_interval,
+            template.goal_pattern
+        );
+    }
+    Ok(())
+}
+
+fn start_from_template(storage: &Storage, id: &str) -> Result<(), String> {
+    // Fail on a typo even when the app is not running.
+    let template = templates::find(storage, id)?;
+    control::call(
+        &storage.control_socket_path(),
+        "startTemplate",
+        Some(json!({ "id": template.id })),
+    )?;
+    println!("Started {} in the running app", template.id);
+    Ok(())
+}
+
+fn log(log_path: &Path, limit: usize) -> Result<(), String> {
+    let entries = logs::read_entries_from_path(log_path)?;
+    for entry in entries.iter().skip(entries.len().saturating_sub(limit)) {
+        let note = entry.notes.as_deref().unwrap_or_default();
+        println!(
+            "{}  {:<18} {}{}",
+            entry.timestamp,
+            entry
+                .record_type
+                .as_deref()
+                .unwrap_or(&entry.reported_status),
+            entry.session_goal.as_deref().unwrap_or_default(),
+            if note.is_empty() {
+                String::new()
+            } else {
+                format!(" — {note}")
+            }
+        );
+    }
+    Ok(())
+}
+
+fn week_stats(storage: &Storage) -> Result<(), String> {
+    let end = Utc::now();
+    let summary = stats::summarize(
+        &logs::read_entries_from_path(&storage.log_path())?,
+        end - Duration::days(7),
+        end,
+    );
+
+    println!("Last 7 days");
+    println!("Check-ins:       {}", summary.total_check_ins);
+    println!(
+        "On task:         {}{}",
+        summary.on_task_check_ins,
+        summary
+            .on_task_ratio()
+            .map(|ratio| format!(" ({:.0}%)", ratio * 100.0))
+            .unwrap_or_default()
+    );
+    println!("Auto-submitted:  {}", summary.auto_submitted_check_ins);
+    println!("Skipped:         {}", summary.skipped_check_ins);
+    println!("Deferred:        {}", summary.deferred_prompts);
+    if let Some(latency) = summary.average_response_latency_ms {
+        println!("Avg. response:   {:.1} s", latency as f64 / 1000.0);
+    }
+    println!("Focused minutes: {}", summary.focused_minutes);
+    if summary.pomodoro_work_seconds > 0 || summary.break_seconds > 0 {
+        println!(
+            "Pomodoro work:   {} min",
+            summary.pomodoro_work_seconds / 60
+        );
+        println!("Breaks:          {} min", summary.break_seconds / 60);
+    }
+    if let Some(ratio) = summary.goal_completion_ratio() {
+        println!(
+            "Goals:           {} achieved, {} partial, {} missed ({:.0}%)",
+            summary.achieved_goals,
+            summary.partial_goals,
+            summary.missed_goals,
+            ratio * 100.0
+        );
+    }
+    for (status, count) in &summary.status_counts {
+        println!("  {status:<18} {count}");
+    }
+    if !summary.projects.is_empty() {
+        let registry = projects::load(storage)?;
+        println!("By project");
+        for (id, project) in &summary.projects {
+            let name = registry
+                .iter()
+                .find(|registered| &registered.id == id)
+                .map_or(id.as_str(), |registered| registered.name.as_str());
+            println!(
+                "  {name:<18} {} check-ins, {} focused min",
+                project.check_ins, project.focused_minutes
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_billing(report: &billing::BillingReport, registry: &[Project]) {
+    println!("Billable hours {} to {}", report.from, report.to);
+    if report.rounding_minutes > 0 {
+        println!(
+            "Rounded up to {} minutes per project and day",
+            report.rounding_minutes
+        );
+    }
+    if report.projects.is_empty() {
+        println!("No check-ins with a project in this range.");
+        return;
+    }
+    for totals in &report.projects {
+        println!(
+            "  {:<18} {:>6} h  ({} on task, {} distracted, {} break min)",
+            billing::project_name(registry, &totals.project),
+            billing::hours(totals.billable_minutes),
+            totals.on_task_minutes,
+            totals.distracted_minutes,
+            totals.break_minutes
+        );
+    }
+    let billable = report
+        .projects
+        .iter()
+        .map(|totals| totals.billable_minutes)
+        .sum();
+    println!("  {:<18} {:>6} h", "Total", billing::hours(billable));
+}
+
+fn doctor(storage: &Storage, repair: bool) -> Result<(), String> {
+    let report = doctor::check(storage)?;
+    let journal = &report.journal;
+    println!(
+        "Journal:        {} valid, {} malformed{}",
+        journal.valid_records,
+        journal.malformed_records,
+        if journal.has_unterminated_tail {
+            ", unterminated tail"
+        } else {
+            ""
+        }
+    );
+    println!("Session state:  {}", describe_file(&report.session_state));
+    println!("Settings:       {}", describe_file(&report.settings));
+    for lock in &report.locks {
+        println!(
+            "Lock {:<10} {}{}",
+            format!("{}:", lock.name),
+            if lock.held { "held" } else { "free" },
+            lock.owner_pid
+                .map(|pid| format!(" by pid {pid}"))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(free) = report.free_disk_bytes {
+        println!("Free space:     {} MB", free / (1024 * 1024));
+    }
+
+    if report.is_healthy() {
+        println!("\nNo issues found");
+        return Ok(());
+    }
+    println!();
+    for issue in &report.issues {
+        println!("- {}", issue.summary);
+        let Some(action) = &issue.repair else {
+            continue;
+        };
+        if !repair {
+            println!("  Repair: {}", action.description());
+        } else if confirm(&format!("  {}?", action.description()))? {
+            doctor::apply(storage, action)?;
+            println!("  Done");
+        }
+    }
+    if !repair {
+        println!("\nRun `doctor --repair` to review and apply these repairs");
+    }
+    Ok(())
+}
+
+fn describe_file(status: &doctor::FileStatus) -> String {
+    match status {
+        doctor::FileStatus::Missing => "none".to_string(),
+        doctor::FileStatus::Ok {
+            version: Some(version),
+        } => format!("ok (version {version})"),
+        doctor::FileStatus::Ok { version: None } => "ok".to_string(),
+        doctor::FileStatus::Invalid { error } => format!("{} ({})", error, error.code()),
+    }
+}
+
+fn confirm(prompt: &str) -> Result<bool, String> {
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| e.to_string())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn check_in(storage: &Storage, status: &str, note: Option<&str>) -> Result<(), String> {
+    // Prefer the running app so its timers advance with the check-in. Only
+    // write directly when it could not be reached; once the request is sent,
+    // the app may already have logged it.
+    let socket = storage.control_socket_path();
+    if socket.exists() {
+        match control::call(
+            &socket,
+            "checkIn",
+            Some(json!({ "status": status, "note": note })),
+        ) {
+            Ok(_) => {
+                println!("Logged {status} via the running app");
+                return Ok(());
+            }
+            Err(control::CallError::Unreachable(_)) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    session_state::record_check_in(storage, status, note, &SystemClock)?;
+    println!("Logged {status}");
+    Ok(())
+}
@@ -3,6 +3,10 @@
 // new `unexpected_cfgs` lint on stable. Allow it on macOS builds to keep the
 // output clean without touching the macro crate.
 
+use crate::error::AppError;
+
+#[cfg(target_os = "macos")]
+use crate::logging;
 #[cfg(target_os = "macos")]
 use cocoa::base::{id, nil};
 #[cfg(target_os = "macos")]
@@ -15,25 +19,27 @@ use std::sync::mpsc::channel;
 use std::time::Duration;
 
 #[cfg(target_os = "macos")]
-pub fn get_current_calendar_event() -> Result<Option<String>, String> {
+pub fn get_current_calendar_event() -> Result<Option<String>, AppError> {
     unsafe {
         // Get the shared event store
         let event_store_class = class!(EKEventStore);
         let event_store: id = msg_send![event_store_class, new];
 
         if event_store == nil {
-            return Err("Failed to create EKEventStore".to_string());
+            return Err(AppError::CalendarUnavailable(
+                "failed to create EKEventStore",
+            ));
         }
 
         // Check authorization status
         let auth_status: i64 = msg_send![event_store_class, authorizationStatusForEntityType: 0];
 
-        println!("Calendar authorization status: {}", auth_status);
+        log::debug!("Calendar authorization status: {}", auth_status);
 
         // Status values: 0 = not determined, 1 = restricted, 2 = denied, 3 = authorized
         if auth_status == 0 {
             // Not determined - need to request access
-            println!("Requesting calendar access...");
+            log::info!("Requesting calendar access");
             let (tx, rx) = channel();
 
             let handler = block::ConcreteBlock::new(move |granted: bool| {
@@ -47,17 +53,17 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
             match rx.recv_timeout(Duration::from_secs(30)) {
                 Ok(granted) => {
                     if !granted {
-                        return Err("Calendar access denied. Please enable in System Settings > Privacy & Security > Calendars".to_string());
+                        return Err(AppError::CalendarDenied);
                     }
-                    println!("Calendar access granted!");
+                    log::info!("Calendar access granted");
                 }
                 Err(_) => {
-                    return Err("Calendar permission request timed out".to_string());
+                    return Err(AppError::CalendarTimedOut);
                 }
             }
         } else if auth_status == 1 || auth_status == 2 {
             // Restricted or denied
-            return Err("Calendar access denied. Please enable in System Settings > Privacy & Security > Calendars".to_string());
+            return Err(AppError::CalendarDenied);
         }
         // auth_status == 3 means already authorized, continue
 
@@ -72,7 +78,7 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
         // Get all calendars
         let calendars: id = msg_send![event_store, calendarsForEntityType: 0];
         let calendar_count: usize = msg_send![calendars, count];
-        println!("Found {} calendars", calendar_count);
+        log::debug!("Found {} calendars", calendar_count);
 
         // Create predicate for events in the time window
         let predicate: id = msg_send![event_store,
@@ -86,7 +92,7 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
 
         // Get event count
         let count: usize = msg_send![events, count];
-        println!("Found {} events in time window", count);
+        log::debug!("Found {} events in time window", count);
 
         if count == 0 {
             return Ok(None);
@@ -104,7 +110,7 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
                 if !utf8_ptr.is_null() {
                     let c_str = std::ffi::CStr::from_ptr(utf8_ptr);
                     let event_title = c_str.to_string_lossy().into_owned();
-                    println!("Event {}: {}", i, event_title);
+                    log::debug!("Event {}: {}", i, logging::private(&event_title));
                 }
             }
 
@@ -114,14 +120,16 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
             let end_comparison: i64 = msg_send![event_end, compare: now];
             let ends_after_now: bool = end_comparison != -1; // NSOrderedAscending = -1
 
-            println!(
-                "  Start comparison: {}, Ends after: {}",
-                start_comparison, ends_after_now
+            log::trace!(
+                "Event {}: start comparison {}, ends after now {}",
+                i,
+                start_comparison,
+                ends_after_now
             );
 
             if starts_before_now && ends_after_now {
                 // This event is happening now!
-                println!("  -> This event is happening NOW!");
+                log::debug!("Event {} is happening now", i);
                 let title: id = msg_send![event, title];
 
                 if title == nil {
@@ -146,18 +154,18 @@ pub fn get_current_calendar_event() -> Result<Option<String>, String> {
 }
 
 #[cfg(target_os = "macos")]
-pub fn request_calendar_access() -> Result<String, String> {
+pub fn request_calendar_access() -> Result<String, AppError> {
     // This function is no longer needed - just call get_current_calendar_event directly
     // which will trigger the permission dialog automatically
     Ok("Call get_current_event to trigger permission dialog".to_string())
 }
 
 #[cfg(not(target_os = "macos"))]
-pub fn request_calendar_access() -> Result<String, String> {
-    Err("Calendar integration is only supported on macOS".to_string())
+pub fn request_calendar_access() -> Result<String, AppError> {
+    Err(AppError::CalendarUnsupported)
 }
 
 #[cfg(not(target_os = "macos"))]
-pub fn get_current_calendar_event() -> Result<Option<String>, String> {
-    Err("Calendar integration is only supported on macOS".to_string())
+pub fn get_current_calendar_event() -> Result<Option<String>, AppError> {
+    Err(AppError::CalendarUnsupported)
 }
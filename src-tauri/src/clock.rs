@@ -0,0 +1,106 @@
+//! Time sources for session, recovery and logging code.
+//!
+//! Anything that reads the time takes a `&dyn Clock`, so tests can replace
+//! [`SystemClock`] with a [`SimulatedClock`] and replay hours of a session,
+//! including sleeps and clock changes, in milliseconds.
+
+use chrono::{DateTime, Local, Utc};
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub trait Clock: Send + Sync {
+    /// Time since an arbitrary origin that stops while the machine sleeps
+    /// and ignores wall-clock changes.
+    fn monotonic(&self) -> Duration;
+    fn now(&self) -> DateTime<Utc>;
+    /// Local offset from UTC, in seconds.
+    fn utc_offset_seconds(&self) -> i32;
+
+    fn system_time(&self) -> SystemTime {
+        self.now().into()
+    }
+}
+
+/// The real clocks. `Instant` uses `CLOCK_MONOTONIC` on Linux and
+/// `CLOCK_UPTIME_RAW` on macOS, neither of which advances during suspend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn monotonic(&self) -> Duration {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn utc_offset_seconds(&self) -> i32 {
+        Local::now().offset().local_minus_utc()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one handle while the code under test holds another.
+#[derive(Debug, Clone)]
+pub struct SimulatedClock {
+    time: Arc<Mutex<SimulatedTime>>,
+}
+
+#[derive(Debug)]
+struct SimulatedTime {
+    monotonic: Duration,
+    wall: DateTime<Utc>,
+    utc_offset_seconds: i32,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            time: Arc::new(Mutex::new(SimulatedTime {
+                monotonic: Duration::ZERO,
+                wall: start,
+                utc_offset_seconds: 0,
+            })),
+        }
+    }
+
+    /// Let time pass normally.
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self.time.lock().unwrap();
+        time.monotonic += duration;
+        time.wall += duration;
+    }
+
+    /// Let wall-clock time pass while monotonic time stands still, as while
+    /// the machine sleeps.
+    pub fn suspend(&self, duration: Duration) {
+        self.time.lock().unwrap().wall += duration;
+    }
+
+    /// Set the wall clock forward or back, as NTP or the user might.
+    pub fn jump(&self, by: chrono::Duration) {
+        self.time.lock().unwrap().wall += by;
+    }
+
+    pub fn set_utc_offset(&self, seconds: i32) {
+        self.time.lock().unwrap().utc_offset_seconds = seconds;
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn monotonic(&self) -> Duration {
+        self.time.lock().unwrap().monotonic
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.time.lock().unwrap().wall
+    }
+
+    fn utc_offset_seconds(&self) -> i32 {
+        self.time.lock().unwrap().utc_offset_seconds
+    }
+}
@@ -0,0 +1,574 @@
+//! Local control API for scripting the app from shells, editors and window
+//! manager keybindings.
+//!
+//! The server speaks newline-delimited JSON-RPC 2.0 on a Unix domain socket in
+//! a private `control` folder in the config directory. Access is limited to
+//! the user by that folder's and the socket's permissions; nothing listens on
+//! the network.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fmt, fs,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::error::AppError;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const HANDLER_ERROR: i64 = -32000;
+const SERVER_BUSY: i64 = -32001;
+
+/// Connections served at once; subscriptions hold theirs open.
+const MAX_CONNECTIONS: usize = 16;
+/// A client that sends nothing for this long is disconnected, so idle
+/// connections cannot hold every slot.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const METHODS: &[&str] = &[
+    "status",
+    "start",
+    "startTemplate",
+    "pause",
+    "resume",
+    "end",
+    "checkIn",
+    "subscribe",
+    "focus",
+];
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "camelCase")]
+pub enum ControlRequest {
+    Status,
+    Start {
+        goal: String,
+    },
+    StartTemplate {
+        id: String,
+    },
+    Pause,
+    Resume,
+    End,
+    CheckIn {
+        status: String,
+        #[serde(default)]
+        note: Option<String>,
+    },
+    Subscribe,
+    Focus,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// Why [`call`] got no result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    /// Nothing accepted the connection, so the request was never sent.
+    Unreachable(String),
+    /// The instance was reached, but the request failed or its answer was lost.
+    Failed(String),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable(message) | Self::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<CallError> for String {
+    fn from(error: CallError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Fan-out of app events to every subscribed control connection.
+#[derive(Debug, Default)]
+pub struct EventHub {
+    subscribers: Mutex<Vec<Sender<Value>>>,
+}
+
+impl EventHub {
+    /// Deliver an event to current subscribers, dropping any that disconnected.
+    pub fn publish(&self, event: Value) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn subscribe(&self) -> Receiver<Value> {
+        let (tx, rx) = channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+/// Start serving control requests on a background thread.
+///
+/// Must only be called while holding the instance lock, since it replaces any
+/// socket file left behind by a previous owner.
+#[cfg(unix)]
+pub fn serve<H>(socket: &Path, events: Arc<EventHub>, handler: H) -> Result<(), AppError>
+where
+    H: Fn(ControlRequest) -> Result<Value, AppError> + Send + Sync + 'static,
+{
+    use std::os::unix::{
+        fs::{DirBuilderExt, PermissionsExt},
+        net::UnixListener,
+    };
+
+    // The folder is private before the socket exists, so the socket is never
+    // reachable by other users, even before its own chmod.
+    if let Some(parent) = socket.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)
+            .and_then(|_| fs::set_permissions(parent, fs::Permissions::from_mode(0o700)))
+            .map_err(|e| AppError::io("Failed to create the control socket folder", e))?;
+    }
+    if socket.exists() {
+        fs::remove_file(socket).map_err(|e| AppError::io("Failed to remove stale socket", e))?;
+    }
+    let listener =
+        UnixListener::bind(socket).map_err(|e| AppError::io("Failed to bind control socket", e))?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))
+        .map_err(|e| AppError::io("Failed to restrict control socket permissions", e))?;
+
+    let handler = Arc::new(handler);
+    let open_connections = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if open_connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                open_connections.fetch_sub(1, Ordering::AcqRel);
+                let busy = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": SERVER_BUSY, "message": "Too many control connections" }
+                });
+                let _ = writeln!(stream, "{busy}");
+                continue;
+            }
+            let handler = Arc::clone(&handler);
+            let events = Arc::clone(&events);
+            let open_connections = Arc::clone(&open_connections);
+            std::thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(IDLE_TIMEOUT));
+                let _ = stream.set_write_timeout(Some(IDLE_TIMEOUT));
+                if let Ok(writer) = stream.try_clone() {
+                    handle_connection(BufReader::new(stream), writer, &events, handler.as_ref());
+                }
+                open_connections.fetch_sub(1, Ordering::AcqRel);
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve<H>(_socket: &Path, _events: Arc<EventHub>, _handler: H) -> Result<(), AppError>
+where
+    H: Fn(ControlRequest) -> Result<Value, AppError> + Send + Sync + 'static,
+{
+    Ok(())
+}
+
+/// Send one request to a running instance and return its result.
+#[cfg(unix)]
+pub fn call(socket: &Path, method: &str, params: Option<Value>) -> Result<Value, CallError> {
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        CallError::Unreachable(format!("Failed to reach the running instance: {e}"))
+    })?;
+    let mut request = json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+    if let Some(params) = params {
+        request["params"] = params;
+    }
+    // A busy server replies and hangs up without reading, which can fail the
+    // send; its reply is still waiting to be read.
+    let sent = stream.write_all(format!("{request}\n").as_bytes());
+
+    let mut line = String::new();
+    let read = BufReader::new(stream).read_line(&mut line);
+    if let Err(e) = sent {
+        if line.trim().is_empty() {
+            return Err(CallError::Failed(format!(
+                "Failed to send control request: {e}"
+            )));
+        }
+    }
+    read.map_err(|e| CallError::Failed(format!("Failed to read control response: {e}")))?;
+    let mut response: Value = serde_json::from_str(&line)
+        .map_err(|e| CallError::Failed(format!("Control response is not valid JSON: {e}")))?;
+    match response.get("error") {
+        Some(error) => Err(CallError::Failed(
+            error["message"]
+                .as_str()
+                .unwrap_or("Control request failed")
+                .to_string(),
+        )),
+        None => Ok(response["result"].take()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn call(_socket: &Path, _method: &str, _params: Option<Value>) -> Result<Value, CallError> {
+    Err(CallError::Unreachable(
+        "The control socket is only supported on Unix".to_string(),
+    ))
+}
+
+fn handle_connection<R, W, H>(reader: R, mut writer: W, events: &EventHub, handler: &H)
+where
+    R: BufRead,
+    W: Write,
+    H: Fn(ControlRequest) -> Result<Value, AppError>,
+{
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, outcome) = dispatch(&line, handler);
+        // Register before acknowledging so no event published after the
+        // acknowledgement can be missed.
+        let subscription = matches!(outcome, Ok(Dispatched::Subscribe)).then(|| events.subscribe());
+        let response = match outcome {
+            Ok(Dispatched::Result(result)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "result": result })
+            }
+            Ok(Dispatched::Subscribe) => {
+                json!({ "jsonrpc": "2.0", "id": id, "result": { "subscribed": true } })
+            }
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error.to_json() }),
+        };
+        if writeln!(writer, "{response}").is_err() {
+            return;
+        }
+
+        if let Some(subscription) = subscription {
+            // The connection now only carries notifications until the client hangs up.
+            for event in subscription {
+                let notification = json!({ "jsonrpc": "2.0", "method": "event", "params": event });
+                if writeln!(writer, "{notification}").is_err() {
+                    return;
+                }
+            }
+            return;
+        }
+    }
+}
+
+enum Dispatched {
+    Result(Value),
+    Subscribe,
+}
+
+/// A JSON-RPC error object. Handler errors carry the `AppError` code and
+/// metadata in `data`, the same fields the Tauri commands return.
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: String) -> Self {
+        Self {
+            code,
+            message,
+            data: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(error: AppError) -> Self {
+        Self {
+            code: HANDLER_ERROR,
+            message: error.to_string(),
+            data: Some(json!({ "code": error.code(), "metadata": error.metadata() })),
+        }
+    }
+}
+
+fn dispatch<H>(line: &str, handler: &H) -> (Value, Result<Dispatched, RpcError>)
+where
+    H: Fn(ControlRequest) -> Result<Value, AppError>,
+{
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return (
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, format!("Invalid request: {e}"))),
+            )
+        }
+    };
+    if !METHODS.contains(&request.method.as_str()) {
+        return (
+            request.id,
+            Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", request.method),
+            )),
+        );
+    }
+
+    let mut tagged = json!({ "method": request.method });
+    if let Some(params) = request.params {
+        tagged["params"] = params;
+    }
+    let control_request = match serde_json::from_value::<ControlRequest>(tagged) {
+        Ok(control_request) => control_request,
+        Err(e) => {
+            return (
+                request.id,
+                Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("Invalid params: {e}"),
+                )),
+            )
+        }
+    };
+
+    let outcome = match control_request {
+        ControlRequest::Subscribe => Ok(Dispatched::Subscribe),
+        other => handler(other)
+            .map(Dispatched::Result)
+            .map_err(RpcError::from),
+    };
+    (request.id, outcome)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn echo_handler(request: ControlRequest) -> Result<Value, AppError> {
+        match request {
+            ControlRequest::Status => Ok(json!({ "phase": "active" })),
+            ControlRequest::Start { goal } => Ok(json!({ "goal": goal })),
+            ControlRequest::CheckIn { status, note } => {
+                Ok(json!({ "status": status, "note": note }))
+            }
+            ControlRequest::End => Err(AppError::NoActiveSession),
+            _ => Ok(Value::Null),
+        }
+    }
+
+    #[test]
+    fn requests_are_routed_to_the_shared_handler() {
+        let dir = temporary_dir("control-dispatch");
+        let socket = dir.join("control").join("control.sock");
+        // A socket file left by a crashed owner is replaced.
+        fs::create_dir_all(dir.join("control")).unwrap();
+        fs::write(&socket, "").unwrap();
+        serve(&socket, Arc::new(EventHub::default()), echo_handler).unwrap();
+
+        assert_eq!(
+            call(&socket, "status", None).unwrap(),
+            json!({ "phase": "active" })
+        );
+        assert_eq!(
+            call(&socket, "start", Some(json!({ "goal": "Synthetic goal" }))).unwrap(),
+            json!({ "goal": "Synthetic goal" })
+        );
+        assert_eq!(
+            call(
+                &socket,
+                "checkIn",
+                Some(json!({ "status": "On Task", "note": "Synthetic note" }))
+            )
+            .unwrap(),
+            json!({ "status": "On Task", "note": "Synthetic note" })
+        );
+        assert_eq!(
+            call(&socket, "end", None).unwrap_err(),
+            CallError::Failed("No session is active".to_string())
+        );
+        // Handler errors keep their stable code for scripts to branch on.
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","id":3,"method":"end"}}"#).unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            response["error"],
+            json!({
+                "code": HANDLER_ERROR,
+                "message": "No session is active",
+                "data": { "code": "no_active_session", "metadata": {} }
+            })
+        );
+        // Only a refused connection means the request was never sent.
+        assert!(matches!(
+            call(&dir.join("missing.sock"), "status", None),
+            Err(CallError::Unreachable(_))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_socket_is_owner_only_and_extra_connections_are_turned_away() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temporary_dir("control-limits");
+        let socket = dir.join("control").join("control.sock");
+        let events = Arc::new(EventHub::default());
+        serve(&socket, Arc::clone(&events), echo_handler).unwrap();
+        assert_eq!(
+            fs::metadata(&socket).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(
+            fs::metadata(dir.join("control"))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o700
+        );
+
+        // Subscriptions hold their connections open.
+        let subscribers: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| {
+                let mut stream = UnixStream::connect(&socket).unwrap();
+                writeln!(stream, r#"{{"jsonrpc":"2.0","id":1,"method":"subscribe"}}"#).unwrap();
+                let mut reader = BufReader::new(stream);
+                reader.read_line(&mut String::new()).unwrap();
+                reader
+            })
+            .collect();
+        assert_eq!(
+            call(&socket, "status", None).unwrap_err(),
+            CallError::Failed("Too many control connections".to_string())
+        );
+
+        // Hung-up subscribers free their slots at the next event.
+        drop(subscribers);
+        let mut served = false;
+        for _ in 0..50 {
+            events.publish(json!({ "kind": "tick" }));
+            if call(&socket, "status", None).is_ok() {
+                served = true;
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(served);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_methods_and_bad_params_get_json_rpc_error_codes() {
+        let handler = |_: ControlRequest| -> Result<Value, AppError> { Ok(Value::Null) };
+
+        let (_, unknown) = dispatch(r#"{"id":1,"method":"delete"}"#, &handler);
+        assert!(matches!(
+            unknown,
+            Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                ..
+            })
+        ));
+
+        let (id, missing_goal) = dispatch(r#"{"id":7,"method":"start","params":{}}"#, &handler);
+        assert_eq!(id, json!(7));
+        assert!(matches!(
+            missing_goal,
+            Err(RpcError {
+                code: INVALID_PARAMS,
+                ..
+            })
+        ));
+
+        let (_, garbage) = dispatch("not json", &handler);
+        assert!(matches!(
+            garbage,
+            Err(RpcError {
+                code: PARSE_ERROR,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn subscribers_receive_published_events() {
+        let dir = temporary_dir("control-subscribe");
+        let socket = dir.join("control").join("control.sock");
+        let events = Arc::new(EventHub::default());
+        serve(&socket, Arc::clone(&events), echo_handler).unwrap();
+
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","id":1,"method":"subscribe"}}"#).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap()["result"],
+            json!({ "subscribed": true })
+        );
+
+        events.publish(json!({ "type": "sessionCleared" }));
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        let notification: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(notification["method"], "event");
+        assert_eq!(notification["params"], json!({ "type": "sessionCleared" }));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Errors returned to the frontend from every Tauri command.
+//!
+//! Each variant serializes to `{ code, message, metadata }`. Codes are stable
+//! strings the frontend can branch on; messages are for display. Neither
+//! carries journal content: parse failures report positions, never the text
+//! that failed to parse.
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::{fmt, io, path::PathBuf};
+
+use crate::logs::LogDiagnostics;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// A file operation failed; `operation` says which one.
+    Io {
+        operation: String,
+        kind: io::ErrorKind,
+        detail: String,
+    },
+    /// A check-in was rejected before reaching the journal.
+    InvalidCheckIn {
+        reason: &'static str,
+    },
+    StateCorrupt {
+        line: usize,
+        column: usize,
+    },
+    StateVersionUnsupported(u32),
+    StateInvalid {
+        reason: &'static str,
+    },
+    SettingsCorrupt {
+        line: usize,
+        column: usize,
+    },
+    SettingsInvalid {
+        reason: &'static str,
+    },
+    NoActiveSession,
+    /// A command argument from the frontend could not be used.
+    InvalidArgument(&'static str),
+    CalendarDenied,
+    CalendarTimedOut,
+    CalendarUnavailable(&'static str),
+    CalendarUnsupported,
+    DataDirectoryInvalid(&'static str),
+    DataDirectoryConflict(PathBuf),
+    DataMigrationMismatch {
+        before: LogDiagnostics,
+        after: LogDiagnostics,
+    },
+    /// The windowing runtime refused an operation.
+    Window(String),
+}
+
+impl AppError {
+    pub fn io(operation: impl Into<String>, error: io::Error) -> Self {
+        Self::Io {
+            operation: operation.into(),
+            kind: error.kind(),
+            detail: error.to_string(),
+        }
+    }
+
+    pub fn state_corrupt(error: serde_json::Error) -> Self {
+        Self::StateCorrupt {
+            line: error.line(),
+            column: error.column(),
+        }
+    }
+
+    pub fn settings_corrupt(error: serde_json::Error) -> Self {
+        Self::SettingsCorrupt {
+            line: error.line(),
+            column: error.column(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io { kind, .. } => match kind {
+                io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => "disk_full",
+                io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                    "permission_denied"
+                }
+                io::ErrorKind::NotFound => "not_found",
+                _ => "io_error",
+            },
+            Self::InvalidCheckIn { .. } => "invalid_check_in",
+            Self::StateCorrupt { .. } => "state_corrupt",
+            Self::StateVersionUnsupported(_) => "state_version_unsupported",
+            Self::StateInvalid { .. } => "state_invalid",
+            Self::SettingsCorrupt { .. } => "settings_corrupt",
+            Self::SettingsInvalid { .. } => "settings_invalid",
+            Self::NoActiveSession => "no_active_session",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::CalendarDenied => "calendar_denied",
+            Self::CalendarTimedOut => "calendar_timed_out",
+            Self::CalendarUnavailable(_) => "calendar_unavailable",
+            Self::CalendarUnsupported => "calendar_unsupported",
+            Self::DataDirectoryInvalid(_) => "data_directory_invalid",
+            Self::DataDirectoryConflict(_) => "data_directory_conflict",
+            Self::DataMigrationMismatch { .. } => "data_migration_mismatch",
+            Self::Window(_) => "window_error",
+        }
+    }
+
+    /// Structured details for diagnostics; file operations, positions and
+    /// counts only.
+    pub fn metadata(&self) -> Map<String, Value> {
+        let metadata = match self {
+            Self::Io {
+                operation, kind, ..
+            } => json!({ "operation": operation, "ioKind": format!("{kind:?}") }),
+            Self::StateCorrupt { line, column } | Self::SettingsCorrupt { line, column } => {
+                json!({ "line": line, "column": column })
+            }
+            Self::StateVersionUnsupported(version) => json!({ "version": version }),
+            Self::DataDirectoryConflict(path) => json!({ "path": path }),
+            Self::DataMigrationMismatch { before, after } => {
+                json!({ "before": before, "after": after })
+            }
+            _ => json!({}),
+        };
+        match metadata {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io {
+                operation, detail, ..
+            } => write!(f, "{operation}: {detail}"),
+            Self::InvalidCheckIn { reason } => write!(f, "Check-in rejected: {reason}"),
+            Self::StateCorrupt { line, column } => write!(
+                f,
+                "Failed to parse active session state (line {line}, column {column})"
+            ),
+            Self::StateVersionUnsupported(version) => {
+                write!(f, "Unsupported active session state version: {version}")
+            }
+            Self::StateInvalid { reason } => write!(f, "Active session state {reason}"),
+            Self::SettingsCorrupt { line, column } => {
+                write!(f, "Failed to parse settings (line {line}, column {column})")
+            }
+            Self::SettingsInvalid { reason } => write!(f, "Settings {reason}"),
+            Self::NoActiveSession => write!(f, "No session is active"),
+            Self::InvalidArgument(reason) => write!(f, "Invalid argument: {reason}"),
+            Self::CalendarDenied => write!(
+                f,
+                "Calendar access denied. Please enable in System Settings > Privacy & Security > Calendars"
+            ),
+            Self::CalendarTimedOut => write!(f, "Calendar permission request timed out"),
+            Self::CalendarUnavailable(reason) => write!(f, "Calendar unavailable: {reason}"),
+            Self::CalendarUnsupported => {
+                write!(f, "Calendar integration is only supported on macOS")
+            }
+            Self::DataDirectoryInvalid(reason) => write!(f, "Invalid data directory: {reason}"),
+            Self::DataDirectoryConflict(path) => write!(
+                f,
+                "{} already exists with different contents; refusing to overwrite it",
+                path.display()
+            ),
+            Self::DataMigrationMismatch { before, after } => write!(
+                f,
+                "Journal record counts differ after copying ({} valid, {} malformed before; {} valid, {} malformed after)",
+                before.valid_records,
+                before.malformed_records,
+                after.valid_records,
+                after.malformed_records
+            ),
+            Self::Window(detail) => write!(f, "Window operation failed: {detail}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("metadata", &self.metadata())?;
+        error.end()
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(error: tauri::Error) -> Self {
+        Self::Window(error.to_string())
+    }
+}
+
+/// The control socket and CLI report errors as plain text.
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_serialize_with_stable_codes_and_no_parsed_text() {
+        let disk_full = AppError::io(
+            "Failed to append check-in",
+            io::Error::from(io::ErrorKind::StorageFull),
+        );
+        let value = serde_json::to_value(&disk_full).unwrap();
+        assert_eq!(value["code"], "disk_full");
+        assert_eq!(value["metadata"]["operation"], "Failed to append check-in");
+
+        let parse_error = serde_json::from_str::<u32>(r#""Synthetic goal""#).unwrap_err();
+        let value = serde_json::to_value(AppError::state_corrupt(parse_error)).unwrap();
+        assert_eq!(value["code"], "state_corrupt");
+        assert!(!value.to_string().contains("Synthetic goal"));
+
+        let value = serde_json::to_value(AppError::StateVersionUnsupported(9)).unwrap();
+        assert_eq!(value["code"], "state_version_unsupported");
+        assert_eq!(value["metadata"], json!({ "version": 9 }));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::logs::LogEntry;
+
+const CSV_HEADER: &str = "timestamp,session_goal,reported_status,notes,check_in_number,\
+session_duration_setting,check_in_interval_setting,write_time_setting,auto_submitted,\
+focus_shield_active,project,tags";
+
+/// Render journal check-ins as RFC 4180 CSV with a header row.
+pub fn entries_to_csv(entries: &[LogEntry]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+
+    for entry in entries.iter().filter(|entry| entry.is_check_in()) {
+        let fields = [
+            entry.timestamp.clone(),
+            entry.session_goal.clone().unwrap_or_default(),
+            entry.reported_status.clone(),
+            entry.notes.clone().unwrap_or_default(),
+            optional(entry.check_in_number),
+            optional(entry.session_duration_setting),
+            optional(entry.check_in_interval_setting),
+            optional(entry.write_time_setting),
+            optional(entry.auto_submitted),
+            optional(entry.focus_shield_active),
+            entry.project.clone().unwrap_or_default(),
+            entry.tags.join(";"),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+pub(crate) fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_fields_with_separators_quotes_and_newlines() {
+        let entry = LogEntry {
+            timestamp: "2025-11-13T10:00:00Z".to_string(),
+            session_goal: Some("Draft, review".to_string()),
+            reported_status: "On Task".to_string(),
+            notes: Some("Said \"done\"\nthen stopped".to_string()),
+            session_duration_setting: Some(720),
+            check_in_interval_setting: Some(20),
+            write_time_setting: None,
+            check_in_number: Some(2),
+            auto_submitted: Some(false),
+            focus_shield_active: None,
+            record_type: None,
+            details: None,
+            template_id: None,
+            interval_adjustment: None,
+            sampled_delay_seconds: None,
+            prompt_outcome: None,
+            response_latency_ms: None,
+            project: None,
+            tags: Vec::new(),
+        };
+
+        let csv = entries_to_csv(&[entry]);
+        let mut lines = csv.split("\r\n");
+
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        assert_eq!(
+            lines.next(),
+            Some(
+                "2025-11-13T10:00:00Z,\"Draft, review\",On Task,\
+\"Said \"\"done\"\"\nthen stopped\",2,720,20,,false,,,"
+            )
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Goal history for autocomplete, derived from the journal and cached in
+//! `goal_history.json` with the user's hidden flags.
+//!
+//! The cache remembers the journal's length and modification time and is
+//! rebuilt whenever either changes, so the journal stays the only source of
+//! truth.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    error::AppError,
+    logs::{self, LogEntry},
+    stats::{self, StatusKind},
+    storage::Storage,
+};
+
+/// Days after which a goal's usage counts half as much in the ranking.
+const RANKING_HALF_LIFE_DAYS: f64 = 14.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalRecord {
+    /// Lowercased with whitespace collapsed; identifies the goal.
+    pub normalized: String,
+    /// The spelling used most recently.
+    pub goal: String,
+    /// Sessions with at least one check-in for the goal.
+    pub sessions: u32,
+    pub last_used: DateTime<Utc>,
+    pub focused_minutes: u64,
+    /// Left out of suggestions but kept in the history.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoalHistory {
+    /// Journal size the goals were derived from.
+    journal_len: u64,
+    /// Journal modification time, in nanoseconds since the epoch, so a
+    /// rewrite that keeps the size still rebuilds the goals.
+    #[serde(default)]
+    journal_modified: u128,
+    goals: Vec<GoalRecord>,
+}
+
+pub fn normalize(goal: &str) -> String {
+    goal.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Derive one record per distinct goal, most recently used first.
+///
+/// A check-in whose number does not follow the goal's previous one starts a
+/// new session.
+pub fn build(entries: &[LogEntry]) -> Vec<GoalRecord> {
+    let mut goals: HashMap<String, (GoalRecord, Option<u32>)> = HashMap::new();
+    for entry in entries {
+        let Some(goal) = entry.session_goal.as_deref().map(str::trim) else {
+            continue;
+        };
+        let Some(time) = entry.parsed_timestamp() else {
+            continue;
+        };
+        let normalized = normalize(goal);
+        if normalized.is_empty() {
+            continue;
+        }
+        let (record, last_check_in) = goals.entry(normalized.clone()).or_insert_with(|| {
+            let record = GoalRecord {
+                normalized,
+                goal: goal.to_string(),
+                sessions: 0,
+                last_used: time,
+                focused_minutes: 0,
+                hidden: false,
+            };
+            (record, None)
+        });
+        if time >= record.last_used {
+            record.last_used = time;
+            record.goal = goal.to_string();
+        }
+        if !entry.is_check_in() {
+            continue;
+        }
+        let number = entry.check_in_number.unwrap_or(1);
+        if last_check_in.is_none_or(|last| number <= last) {
+            record.sessions += 1;
+        }
+        *last_check_in = Some(number);
+        if stats::status_kind(&entry.reported_status) == Some(StatusKind::OnTask) {
+            record.focused_minutes += stats::credited_minutes(entry);
+        }
+    }
+
+    let mut records: Vec<GoalRecord> = goals.into_values().map(|(record, _)| record).collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.last_used));
+    records
+}
+
+/// The goal history, rebuilt from the journal when it changed.
+pub fn load(storage: &Storage) -> Result<Vec<GoalRecord>, AppError> {
+    let _lock = lock_history(storage)?;
+    load_locked(storage)
+}
+
+/// Hide a goal from suggestions, or show it again.
+pub fn set_hidden(storage: &Storage, goal: &str, hidden: bool) -> Result<(), AppError> {
+    let _lock = lock_history(storage)?;
+    let mut goals = load_locked(storage)?;
+    let normalized = normalize(goal);
+    let record = goals
+        .iter_mut()
+        .find(|record| record.normalized == normalized)
+        .ok_or(AppError::InvalidArgument("no goal in the history matches"))?;
+    record.hidden = hidden;
+    let cached = read(storage)?;
+    write(storage, &GoalHistory { goals, ..cached })
+}
+
+fn load_locked(storage: &Storage) -> Result<Vec<GoalRecord>, AppError> {
+    let (journal_len, journal_modified) = fs::metadata(storage.log_path())
+        .map(|metadata| {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_nanos());
+            (metadata.len(), modified)
+        })
+        .unwrap_or_default();
+    let cached = read(storage)?;
+    if cached.journal_len == journal_len
+        && cached.journal_modified == journal_modified
+        && journal_len > 0
+    {
+        return Ok(cached.goals);
+    }
+
+    let hidden: Vec<String> = cached
+        .goals
+        .into_iter()
+        .filter(|record| record.hidden)
+        .map(|record| record.normalized)
+        .collect();
+    let mut goals = build(&logs::read_entries_from_path(&storage.log_path())?);
+    for record in &mut goals {
+        record.hidden = hidden.contains(&record.normalized);
+    }
+    write(
+        storage,
+        &GoalHistory {
+            journal_len,
+            journal_modified,
+            goals: goals.clone(),
+        },
+    )?;
+    Ok(goals)
+}
+
+/// Visible goals matching `query`, best first.
+///
+/// Goals starting with the query rank first, then goals with a word starting
+/// with it, then goals containing it, then goals containing its letters in
+/// order. Within each group, goals used in more sessions and more recently
+/// rank higher. An empty query lists the most recent goals.
+pub fn search(
+    goals: &[GoalRecord],
+    query: &str,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> Vec<GoalRecord> {
+    let query = normalize(query);
+    let mut matches: Vec<(u8, f64, &GoalRecord)> = goals
+        .iter()
+        .filter(|record| !record.hidden)
+        .filter_map(|record| {
+            let group = match_group(&record.normalized, &query)?;
+            let days = (now - record.last_used).num_seconds().max(0) as f64 / 86_400.0;
+            let weight = if query.is_empty() {
+                -days
+            } else {
+                f64::from(record.sessions.max(1)) * 0.5f64.powf(days / RANKING_HALF_LIFE_DAYS)
+            };
+            Some((group, weight, record))
+        })
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, record)| record.clone())
+        .collect()
+}
+
+fn match_group(goal: &str, query: &str) -> Option<u8> {
+    if goal.starts_with(query) {
+        Some(0)
+    } else if goal.split(' ').any(|word| word.starts_with(query)) {
+        Some(1)
+    } else if goal.contains(query) {
+        Some(2)
+    } else {
+        let mut letters = goal.chars();
+        query
+            .chars()
+            .filter(|c| *c != ' ')
+            .all(|c| letters.any(|letter| letter == c))
+            .then_some(3)
+    }
+}
+
+fn read(storage: &Storage) -> Result<GoalHistory, AppError> {
+    let path = storage.goal_history_path();
+    if !path.exists() {
+        return Ok(GoalHistory::default());
+    }
+    let data = fs::read(path).map_err(|e| AppError::io("Failed to read goal history", e))?;
+    // Rebuilding over an unreadable cache would silently drop the hidden flags.
+    serde_json::from_slice(&data).map_err(AppError::settings_corrupt)
+}
+
+fn write(storage: &Storage, history: &GoalHistory) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(history).map_err(AppError::settings_corrupt)?;
+    fs::write(storage.goal_history_path(), data)
+        .map_err(|e| AppError::io("Failed to save goal history", e))
+}
+
+/// Serializes the read-modify-write of the cache between the app and the CLI.
+fn lock_history(storage: &Storage) -> Result<File, AppError> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(storage.goal_history_path().with_extension("lock"))
+        .map_err(|e| AppError::io("Failed to open goal history lock", e))?;
+    lock.lock()
+        .map_err(|e| AppError::io("Failed to lock goal history", e))?;
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn check_in(storage: &Storage, timestamp: &str, goal: &str, number: u32, status: &str) {
+        let line = serde_json::json!({
+            "timestamp": timestamp,
+            "session_goal": goal,
+            "reported_status": status,
+            "check_in_interval_setting": 20,
+            "check_in_number": number,
+        });
+        logs::append_entry(storage, &line.to_string()).unwrap();
+    }
+
+    fn time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().into()
+    }
+
+    #[test]
+    fn history_counts_sessions_and_minutes_per_normalized_goal() {
+        let dir = temporary_dir("goal-history");
+        let storage = Storage::open(&dir).unwrap();
+        check_in(
+            &storage,
+            "2025-11-01T09:20:00Z",
+            "Ship release notes",
+            1,
+            "On Task",
+        );
+        check_in(
+            &storage,
+            "2025-11-01T09:40:00Z",
+            "Ship release notes",
+            2,
+            "Email/Chat",
+        );
+        check_in(
+            &storage,
+            "2025-11-03T10:20:00Z",
+            "ship  Release notes ",
+            1,
+            "On Task",
+        );
+        check_in(
+            &storage,
+            "2025-11-02T10:20:00Z",
+            "Write grant report",
+            1,
+            "On Task",
+        );
+
+        let goals = load(&storage).unwrap();
+        assert_eq!(goals.len(), 2);
+        assert_eq!(goals[0].goal, "ship  Release notes");
+        assert_eq!(goals[0].normalized, "ship release notes");
+        assert_eq!(goals[0].sessions, 2);
+        assert_eq!(goals[0].focused_minutes, 40);
+        assert_eq!(goals[0].last_used, time("2025-11-03T10:20:00Z"));
+
+        set_hidden(&storage, "Write Grant Report", true).unwrap();
+        assert!(set_hidden(&storage, "Unknown goal", true).is_err());
+        // A new journal line rebuilds the history but keeps the hidden flag.
+        check_in(&storage, "2025-11-04T10:20:00Z", "Review PRs", 1, "On Task");
+        let goals = load(&storage).unwrap();
+        assert_eq!(goals.len(), 3);
+        assert!(goals
+            .iter()
+            .any(|record| record.hidden && record.goal == "Write grant report"));
+        let now = time("2025-11-05T00:00:00Z");
+        assert!(search(&goals, "write", 5, now).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_ranks_prefix_then_word_then_fuzzy_matches() {
+        let record = |goal: &str, sessions, last_used| GoalRecord {
+            normalized: normalize(goal),
+            goal: goal.to_string(),
+            sessions,
+            last_used: time(last_used),
+            focused_minutes: 0,
+            hidden: false,
+        };
+        let goals = vec![
+            record("Review PRs", 1, "2025-11-10T09:00:00Z"),
+            record("Code review", 9, "2025-11-09T09:00:00Z"),
+            record("Write release notes", 2, "2025-11-01T09:00:00Z"),
+            record("Research vendors", 5, "2025-11-08T09:00:00Z"),
+            record("Reading", 1, "2025-11-07T09:00:00Z"),
+        ];
+        let now = time("2025-11-10T12:00:00Z");
+        let ranked = |query| -> Vec<String> {
+            search(&goals, query, 10, now)
+                .into_iter()
+                .map(|record| record.goal)
+                .collect()
+        };
+
+        assert_eq!(
+            ranked("re"),
+            [
+                "Research vendors",
+                "Review PRs",
+                "Reading",
+                "Code review",
+                "Write release notes"
+            ]
+        );
+        assert_eq!(ranked("rvw"), ["Code review", "Review PRs"]);
+        assert_eq!(
+            ranked(""),
+            [
+                "Review PRs",
+                "Code review",
+                "Research vendors",
+                "Reading",
+                "Write release notes"
+            ]
+        );
+        assert_eq!(search(&goals, "", 2, now).len(), 2);
+    }
+
+    #[test]
+    fn a_same_size_rewrite_rebuilds_and_a_corrupt_cache_keeps_its_hidden_flags() {
+        let dir = temporary_dir("goal-history-cache");
+        let storage = Storage::open(&dir).unwrap();
+        check_in(&storage, "2025-11-01T09:20:00Z", "Draft memo", 1, "On Task");
+        assert_eq!(load(&storage).unwrap()[0].goal, "Draft memo");
+
+        // Same length, different goal, e.g. after a repair rewrote the journal.
+        let journal = fs::read_to_string(storage.log_path()).unwrap();
+        fs::write(
+            storage.log_path(),
+            journal.replace("Draft memo", "Draft plan"),
+        )
+        .unwrap();
+        File::options()
+            .write(true)
+            .open(storage.log_path())
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(load(&storage).unwrap()[0].goal, "Draft plan");
+
+        set_hidden(&storage, "Draft plan", true).unwrap();
+        fs::write(storage.goal_history_path(), "{").unwrap();
+        assert_eq!(load(&storage).unwrap_err().code(), "settings_corrupt");
+        assert_eq!(
+            set_hidden(&storage, "Draft plan", false)
+                .unwrap_err()
+                .code(),
+            "settings_corrupt"
+        );
+        assert_eq!(
+            fs::read_to_string(storage.goal_history_path()).unwrap(),
+            "{"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::error::AppError;
+
+/// Exclusive ownership of a config directory for the lifetime of the process.
+///
+/// The lock is advisory and held on the open file, so the OS releases it when
+/// the process dies; a lock file left behind by a crash never blocks startup.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+#[derive(Debug)]
+pub enum LockOutcome {
+    Acquired(InstanceLock),
+    /// Another process owns the directory; carries its PID when readable.
+    HeldBy(Option<u32>),
+}
+
+/// Take the instance lock and record this process's PID in it.
+pub fn acquire(path: &Path) -> Result<LockOutcome, AppError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create instance lock directory", e))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| AppError::io("Failed to open instance lock", e))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut contents = String::new();
+            let _ = file.read_to_string(&mut contents);
+            return Ok(LockOutcome::HeldBy(contents.trim().parse().ok()));
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(AppError::io("Failed to take instance lock", e));
+        }
+    }
+
+    file.set_len(0)
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| write!(file, "{}", std::process::id()))
+        .and_then(|_| file.sync_data())
+        .map_err(|e| AppError::io("Failed to record instance lock owner", e))?;
+
+    Ok(LockOutcome::Acquired(InstanceLock { _file: file }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn second_lock_reports_the_owning_pid_until_the_first_is_released() {
+        let dir = temporary_dir("instance-lock");
+        let path = dir.join("instance.lock");
+
+        let first = acquire(&path).unwrap();
+        assert!(matches!(first, LockOutcome::Acquired(_)));
+        assert!(matches!(
+            acquire(&path).unwrap(),
+            LockOutcome::HeldBy(Some(pid)) if pid == std::process::id()
+        ));
+
+        drop(first);
+        assert!(matches!(acquire(&path).unwrap(), LockOutcome::Acquired(_)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stale_lock_file_from_a_crashed_owner_does_not_block_startup() {
+        let dir = temporary_dir("stale-instance-lock");
+        let path = dir.join("instance.lock");
+        fs::write(&path, "999999").unwrap();
+
+        assert!(matches!(acquire(&path).unwrap(), LockOutcome::Acquired(_)));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+//! Shared backend for the menu bar app and the `hyper-awareness-cli` binary.
+
+pub mod adaptive;
+pub mod billing;
+pub mod calendar;
+pub mod clock;
+pub mod control;
+pub mod doctor;
+pub mod error;
+pub mod export;
+pub mod goals;
+pub mod instance;
+pub mod logging;
+pub mod logs;
+pub mod outcome;
+pub mod projects;
+pub mod session_state;
+pub mod settings;
+pub mod stats;
+pub mod storage;
+pub mod templates;
+pub mod watchdog;
+pub mod window_position;
+
+/// Bundle identifier from `tauri.conf.json`; names the app's config directory.
+pub const APP_IDENTIFIER: &str = "com.focustime.app";
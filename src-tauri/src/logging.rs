@@ -0,0 +1,316 @@
+//! Diagnostic logging for the app, separate from the check-in journal.
+//!
+//! Records go through the `log` facade into `hyper-awareness.log` in the app's
+//! log directory, one JSON object per line, rotated by size. Anything the user
+//! typed or synced (goals, notes, calendar titles) must be logged through
+//! [`private`], which redacts it.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::AppError,
+    logs, session_state, settings,
+    storage::Storage,
+};
+
+pub const LOG_FILE_NAME: &str = "hyper-awareness.log";
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const ROTATED_LOG_FILES: usize = 3;
+
+/// Parse the `log_level` setting; unknown values fall back to `info`.
+pub fn level_from_setting(setting: &str) -> LevelFilter {
+    match setting {
+        "off" => LevelFilter::Off,
+        "error" => LevelFilter::Error,
+        "warn" => LevelFilter::Warn,
+        "debug" => LevelFilter::Debug,
+        "trace" => LevelFilter::Trace,
+        _ => LevelFilter::Info,
+    }
+}
+
+/// Change verbosity at runtime, e.g. after the settings window saves.
+pub fn set_level(setting: &str) {
+    log::set_max_level(level_from_setting(setting));
+}
+
+/// Wrap private text so only its length reaches the log.
+pub fn private(value: &str) -> Private {
+    Private(value.chars().count())
+}
+
+pub struct Private(usize);
+
+impl fmt::Display for Private {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[redacted {} chars]", self.0)
+    }
+}
+
+/// Appends records to a log file and rotates it to `.1`, `.2`, … once it would
+/// grow past `max_bytes`.
+pub struct FileLogger {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<Option<File>>,
+    clock: Box<dyn Clock>,
+}
+
+impl FileLogger {
+    pub fn new(dir: &Path, max_bytes: u64, clock: Box<dyn Clock>) -> Result<Self, AppError> {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("Failed to create log directory", e))?;
+        Ok(Self {
+            path: dir.join(LOG_FILE_NAME),
+            max_bytes,
+            file: Mutex::new(None),
+            clock,
+        })
+    }
+
+    fn format(&self, record: &Record) -> serde_json::Value {
+        json!({
+            "timestamp": self.clock.now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        })
+    }
+
+    fn write_line(&self, line: &str) -> std::io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let current_len = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        if current_len > 0 && current_len + line.len() as u64 + 1 > self.max_bytes {
+            *file = None;
+            rotate(&self.path)?;
+        }
+        if file.is_none() {
+            *file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            );
+        }
+        let file = file.as_mut().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format(record);
+        if self.write_line(&line.to_string()).is_err() && record.level() == Level::Error {
+            // Last resort when the log directory is unwritable.
+            eprintln!("{line}");
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn rotate(path: &Path) -> std::io::Result<()> {
+    let rotated = |index: usize| PathBuf::from(format!("{}.{index}", path.display()));
+    let _ = fs::remove_file(rotated(ROTATED_LOG_FILES));
+    for index in (1..ROTATED_LOG_FILES).rev() {
+        if rotated(index).exists() {
+            fs::rename(rotated(index), rotated(index + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+/// Install the file logger as the global `log` backend.
+pub fn init(dir: &Path, level: &str) -> Result<(), AppError> {
+    let logger = FileLogger::new(dir, MAX_LOG_BYTES, Box::new(SystemClock))?;
+    log::set_boxed_logger(Box::new(logger))
+        .map_err(|_| AppError::InvalidArgument("logging is already initialized"))?;
+    set_level(level);
+    Ok(())
+}
+
+/// Collect the diagnostic logs and metadata-only health information into one
+/// text file for attaching to a bug report. Journal entries, goals and notes
+/// are never included.
+pub fn write_support_bundle(
+    log_dir: &Path,
+    storage: &Storage,
+    output_dir: &Path,
+    clock: &dyn Clock,
+) -> Result<PathBuf, AppError> {
+    let now = clock.now();
+    fs::create_dir_all(output_dir)
+        .map_err(|e| AppError::io("Failed to create support bundle directory", e))?;
+    let path = output_dir.join(format!(
+        "hyper-awareness-support-{}.txt",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+
+    let mut bundle = String::new();
+    bundle.push_str(&format!(
+        "Hyper Awareness {} on {} {}\nCreated {}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        now.to_rfc3339()
+    ));
+
+    bundle.push_str("== Settings ==\n");
+    match settings::load(storage) {
+        Ok(settings) => bundle.push_str(
+            &serde_json::to_string_pretty(&settings).map_err(AppError::settings_corrupt)?,
+        ),
+        Err(error) => bundle.push_str(&error.to_string()),
+    }
+
+    bundle.push_str("\n\n== Journal ==\n");
+    match logs::diagnostics(storage) {
+        Ok(diagnostics) => bundle.push_str(&format!(
+            "{} valid, {} malformed, unterminated tail: {}",
+            diagnostics.valid_records,
+            diagnostics.malformed_records,
+            diagnostics.has_unterminated_tail
+        )),
+        Err(error) => bundle.push_str(&error.to_string()),
+    }
+
+    bundle.push_str("\n\n== Session state ==\n");
+    match session_state::load(storage) {
+        Ok(Some(state)) => bundle.push_str(&format!(
+            "version {}, phase {:?}, {} check-ins",
+            state.version, state.phase, state.check_ins_completed
+        )),
+        Ok(None) => bundle.push_str("none"),
+        Err(error) => bundle.push_str(&error.to_string()),
+    }
+
+    let log_path = log_dir.join(LOG_FILE_NAME);
+    for index in (1..=ROTATED_LOG_FILES).rev() {
+        let rotated = PathBuf::from(format!("{}.{index}", log_path.display()));
+        append_log_section(&mut bundle, &rotated);
+    }
+    append_log_section(&mut bundle, &log_path);
+
+    fs::write(&path, bundle).map_err(|e| AppError::io("Failed to write support bundle", e))?;
+    Ok(path)
+}
+
+fn append_log_section(bundle: &mut String, path: &Path) {
+    if let Ok(contents) = fs::read_to_string(path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        bundle.push_str(&format!("\n\n== {name} ==\n{contents}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use chrono::DateTime;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn log_file_rotates_by_size_and_keeps_a_bounded_history() {
+        let dir = temporary_dir("logging-rotate");
+        let logger = FileLogger::new(&dir, 100, Box::new(SystemClock)).unwrap();
+        let line = "x".repeat(60);
+
+        for _ in 0..6 {
+            logger.write_line(&line).unwrap();
+        }
+
+        let log_path = dir.join(LOG_FILE_NAME);
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), format!("{line}\n"));
+        for index in 1..=ROTATED_LOG_FILES {
+            assert!(PathBuf::from(format!("{}.{index}", log_path.display())).exists());
+        }
+        assert!(!PathBuf::from(format!("{}.4", log_path.display())).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn private_values_are_redacted_to_their_length() {
+        let rendered = format!("Using event {}", private("Synthetic meeting"));
+        assert_eq!(rendered, "Using event [redacted 17 chars]");
+    }
+
+    #[test]
+    fn support_bundle_contains_logs_and_metadata_but_no_journal_text() {
+        let dir = temporary_dir("logging-bundle");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        logs::append_entry(
+            &storage,
+            &json!({
+                "timestamp": "2025-01-01T09:00:00Z",
+                "session_goal": "Synthetic goal",
+                "reported_status": "On Task",
+                "notes": "Synthetic note"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let log_dir = dir.join("logs");
+        let clock = SimulatedClock::new(
+            DateTime::parse_from_rfc3339("2025-01-01T09:30:00Z")
+                .unwrap()
+                .into(),
+        );
+        let logger = FileLogger::new(&log_dir, MAX_LOG_BYTES, Box::new(clock.clone())).unwrap();
+        let record = logger.format(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("Tray icon built"))
+                .build(),
+        );
+        logger.write_line(&record.to_string()).unwrap();
+
+        let path = write_support_bundle(&log_dir, &storage, &dir, &clock).unwrap();
+        let bundle = fs::read_to_string(&path).unwrap();
+
+        assert!(bundle.contains("1 valid, 0 malformed"));
+        assert!(bundle.contains("Tray icon built"));
+        assert!(bundle.contains(r#""timestamp":"2025-01-01T09:30:00+00:00""#));
+        assert!(path.ends_with("hyper-awareness-support-20250101-093000.txt"));
+        assert!(!bundle.contains("Synthetic goal"));
+        assert!(!bundle.contains("Synthetic note"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -3,26 +3,127 @@ use serde::{Deserialize, Serialize};
 use std::{
     fs::{File, OpenOptions},
     io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
-    path::{Path, PathBuf},
+    path::Path,
 };
-use tauri::{AppHandle, Manager};
+
+use crate::{adaptive::IntervalAdjustment, error::AppError, stats, storage::Storage};
 
 /// Internal log entry structure (matches JSONL format).
 ///
 /// The backend parses and serializes every entry so JSONL has a single trusted
 /// validation boundary before data reaches the append-only journal.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct LogEntry {
-    timestamp: String,
-    session_goal: Option<String>,
-    reported_status: String,
-    notes: Option<String>,
-    session_duration_setting: Option<u32>,
-    check_in_interval_setting: Option<u32>,
-    write_time_setting: Option<u32>,
-    check_in_number: Option<u32>,
-    auto_submitted: Option<bool>,
-    focus_shield_active: Option<bool>,
+pub struct LogEntry {
+    pub timestamp: String,
+    pub session_goal: Option<String>,
+    /// Required on new check-ins; left out of records that are not check-ins.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reported_status: String,
+    pub notes: Option<String>,
+    pub session_duration_setting: Option<u32>,
+    pub check_in_interval_setting: Option<u32>,
+    pub write_time_setting: Option<u32>,
+    pub check_in_number: Option<u32>,
+    pub auto_submitted: Option<bool>,
+    pub focus_shield_active: Option<bool>,
+    /// Absent on check-ins, which predate the field; names the event for
+    /// records the app writes about the session itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<String>,
+    /// Event-specific fields for records with a `record_type`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    /// The session template the session was started from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<String>,
+    /// Present on check-ins from sessions with an adaptive interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_adjustment: Option<IntervalAdjustment>,
+    /// The randomly drawn wait before this check-in, in sampling mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampled_delay_seconds: Option<u64>,
+    /// How the prompt for this check-in ended; see [`LogEntry::prompt_outcome`]
+    /// for entries written before the field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_outcome: Option<PromptOutcome>,
+    /// Time from showing the prompt to submitting it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_latency_ms: Option<u64>,
+    /// Id of the project in the registry the session was for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptOutcome {
+    Answered,
+    /// The write time ran out and the prompt submitted itself.
+    AutoSubmitted,
+    Skipped,
+    /// The focus shield postponed the prompt; journaled as a
+    /// [`PROMPT_DEFERRED_RECORD`] rather than a check-in.
+    Deferred,
+}
+
+impl PromptOutcome {
+    /// Whether the prompt went unanswered.
+    pub fn is_ignored(self) -> bool {
+        matches!(self, Self::AutoSubmitted | Self::Skipped)
+    }
+}
+
+pub const SESSION_RECOVERY_RECORD: &str = "session_recovery";
+/// A finished Pomodoro work or break phase; `details` holds its length.
+pub const POMODORO_PHASE_RECORD: &str = "pomodoro_phase";
+/// A check-in prompt the focus shield held back; `details` says until when.
+pub const PROMPT_DEFERRED_RECORD: &str = "prompt_deferred";
+/// A checked-off item of the session's subtask checklist.
+pub const SUBTASK_COMPLETED_RECORD: &str = "subtask_completed";
+/// The user's verdict on a finished session's goal, with computed totals.
+pub const SESSION_OUTCOME_RECORD: &str = "session_outcome";
+
+impl LogEntry {
+    pub fn is_check_in(&self) -> bool {
+        self.record_type.is_none()
+    }
+
+    /// The recorded outcome, or for older check-ins the one their other
+    /// fields imply.
+    pub fn prompt_outcome(&self) -> Option<PromptOutcome> {
+        if self.record_type.as_deref() == Some(PROMPT_DEFERRED_RECORD) {
+            return Some(PromptOutcome::Deferred);
+        }
+        if !self.is_check_in() {
+            return None;
+        }
+        Some(self.prompt_outcome.unwrap_or_else(|| {
+            if self.auto_submitted == Some(true) {
+                PromptOutcome::AutoSubmitted
+            } else if stats::status_kind(&self.reported_status) == Some(stats::StatusKind::Skipped)
+            {
+                PromptOutcome::Skipped
+            } else {
+                PromptOutcome::Answered
+            }
+        }))
+    }
+
+    /// Check the field serde cannot: an RFC3339 timestamp.
+    pub fn validate(&self) -> Result<(), AppError> {
+        DateTime::parse_from_rfc3339(&self.timestamp).map_err(|_| AppError::InvalidCheckIn {
+            reason: "timestamp is not RFC3339",
+        })?;
+        Ok(())
+    }
+
+    pub fn parsed_timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
 }
 
 /// Session entry returned to frontend (cleaned up).
@@ -33,10 +134,11 @@ pub struct SessionEntry {
     #[serde(rename = "statusLabel")]
     pub status_label: String,
     pub note: String,
+    pub outcome: PromptOutcome,
 }
 
 /// Metadata-only journal health information. No activity content is exposed.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LogDiagnostics {
     pub valid_records: usize,
@@ -58,32 +160,44 @@ impl SessionEntry {
     }
 
     fn from_log_entry(entry: LogEntry) -> Self {
+        let outcome = entry.prompt_outcome().unwrap_or(PromptOutcome::Answered);
         Self {
             timestamp: entry.timestamp,
             status: entry.reported_status.clone(),
             status_label: Self::status_to_label(&entry.reported_status),
+            outcome,
             note: entry.notes.unwrap_or_default(),
         }
     }
 }
 
 /// Append a frontend log payload only after validating and normalizing it.
-pub fn append_entry(app: &AppHandle, log_line: &str) -> Result<(), String> {
-    append_entry_to_path(&log_file_path(app)?, log_line)
+pub fn append_entry(storage: &Storage, log_line: &str) -> Result<(), AppError> {
+    append_entry_to_path(&storage.log_path(), log_line)
 }
 
 /// Append a serialized entry to a JSONL file without joining it to an interrupted tail.
-pub fn append_entry_to_path(path: &Path, log_line: &str) -> Result<(), String> {
-    let entry: LogEntry =
-        serde_json::from_str(log_line).map_err(|e| format!("Check-in is not valid JSON: {e}"))?;
-    DateTime::parse_from_rfc3339(&entry.timestamp)
-        .map_err(|e| format!("Check-in timestamp is not RFC3339: {e}"))?;
-    let serialized =
-        serde_json::to_string(&entry).map_err(|e| format!("Failed to serialize check-in: {e}"))?;
+///
+/// The append holds an exclusive advisory lock on the journal for its duration.
+pub fn append_entry_to_path(path: &Path, log_line: &str) -> Result<(), AppError> {
+    let entry: LogEntry = serde_json::from_str(log_line).map_err(|_| AppError::InvalidCheckIn {
+        reason: "not valid JSON",
+    })?;
+    entry.validate()?;
+    // Older journals hold check-ins with an empty status, which stay valid
+    // records; new check-ins must name one.
+    if entry.is_check_in() && entry.reported_status.is_empty() {
+        return Err(AppError::InvalidCheckIn {
+            reason: "check-in has no reported_status",
+        });
+    }
+    let serialized = serde_json::to_string(&entry).map_err(|_| AppError::InvalidCheckIn {
+        reason: "could not be serialized",
+    })?;
 
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create focus log directory: {e}"))?;
+            .map_err(|e| AppError::io("Failed to create focus log directory", e))?;
     }
 
     let mut file = OpenOptions::new()
@@ -91,38 +205,42 @@ pub fn append_entry_to_path(path: &Path, log_line: &str) -> Result<(), String> {
         .read(true)
         .append(true)
         .open(path)
-        .map_err(|e| format!("Failed to open focus log: {e}"))?;
+        .map_err(|e| AppError::io("Failed to open focus log", e))?;
+    // Advisory lock so external tools that also lock the journal cannot
+    // interleave partial lines with ours. Released when `file` is dropped.
+    file.lock()
+        .map_err(|e| AppError::io("Failed to lock focus log", e))?;
 
     if file
         .metadata()
-        .map_err(|e| format!("Failed to inspect focus log: {e}"))?
+        .map_err(|e| AppError::io("Failed to inspect focus log", e))?
         .len()
         > 0
     {
         file.seek(SeekFrom::End(-1))
-            .map_err(|e| format!("Failed to inspect focus log tail: {e}"))?;
+            .map_err(|e| AppError::io("Failed to inspect focus log tail", e))?;
         let mut tail = [0_u8; 1];
         file.read_exact(&mut tail)
-            .map_err(|e| format!("Failed to read focus log tail: {e}"))?;
+            .map_err(|e| AppError::io("Failed to read focus log tail", e))?;
         if tail[0] != b'\n' {
-            eprintln!("Focus log has an unterminated trailing record; restoring its line boundary before append");
+            log::warn!("Focus log has an unterminated trailing record; restoring its line boundary before append");
             file.write_all(b"\n")
-                .map_err(|e| format!("Failed to restore focus log line boundary: {e}"))?;
+                .map_err(|e| AppError::io("Failed to restore focus log line boundary", e))?;
         }
     }
 
     file.write_all(serialized.as_bytes())
         .and_then(|_| file.write_all(b"\n"))
         .and_then(|_| file.sync_data())
-        .map_err(|e| format!("Failed to append check-in: {e}"))
+        .map_err(|e| AppError::io("Failed to append check-in", e))
 }
 
 /// Return metadata-only health information without exposing journal contents.
-pub fn diagnostics(app: &AppHandle) -> Result<LogDiagnostics, String> {
-    diagnostics_for_path(&log_file_path(app)?)
+pub fn diagnostics(storage: &Storage) -> Result<LogDiagnostics, AppError> {
+    diagnostics_for_path(&storage.log_path())
 }
 
-pub fn diagnostics_for_path(path: &Path) -> Result<LogDiagnostics, String> {
+pub fn diagnostics_for_path(path: &Path) -> Result<LogDiagnostics, AppError> {
     if !path.exists() {
         return Ok(LogDiagnostics {
             valid_records: 0,
@@ -132,7 +250,7 @@ pub fn diagnostics_for_path(path: &Path) -> Result<LogDiagnostics, String> {
     }
 
     let data =
-        std::fs::read(path).map_err(|e| format!("Failed to read focus log diagnostics: {e}"))?;
+        std::fs::read(path).map_err(|e| AppError::io("Failed to read focus log diagnostics", e))?;
     let has_unterminated_tail = !data.is_empty() && !data.ends_with(b"\n");
     let mut valid_records = 0;
     let mut malformed_records = 0;
@@ -141,10 +259,7 @@ pub fn diagnostics_for_path(path: &Path) -> Result<LogDiagnostics, String> {
         .split(|byte| *byte == b'\n')
         .filter(|line| !line.is_empty())
     {
-        let valid_entry = serde_json::from_slice::<LogEntry>(line)
-            .ok()
-            .and_then(|entry| DateTime::parse_from_rfc3339(&entry.timestamp).ok());
-        if valid_entry.is_some() {
+        if is_valid_record(line) {
             valid_records += 1;
         } else {
             malformed_records += 1;
@@ -158,15 +273,31 @@ pub fn diagnostics_for_path(path: &Path) -> Result<LogDiagnostics, String> {
     })
 }
 
+/// Whether one journal line parses as an entry that passes validation.
+pub fn is_valid_record(line: &[u8]) -> bool {
+    serde_json::from_slice::<LogEntry>(line).is_ok_and(|entry| entry.validate().is_ok())
+}
+
 /// Read session entries since a given start time.
-pub fn read_since(app: &AppHandle, start: DateTime<Utc>) -> Result<Vec<SessionEntry>, String> {
-    let log_path = log_file_path(app)?;
+pub fn read_since(storage: &Storage, start: DateTime<Utc>) -> Result<Vec<SessionEntry>, AppError> {
+    let mut entries: Vec<SessionEntry> = read_entries_from_path(&storage.log_path())?
+        .into_iter()
+        .filter(|entry| entry.is_check_in())
+        .filter(|entry| entry.parsed_timestamp().is_some_and(|time| time >= start))
+        .map(SessionEntry::from_log_entry)
+        .collect();
+
+    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(entries)
+}
 
+/// Read every valid journal entry in file order, skipping malformed lines.
+pub fn read_entries_from_path(log_path: &Path) -> Result<Vec<LogEntry>, AppError> {
     if !log_path.exists() {
         return Ok(Vec::new());
     }
 
-    let file = File::open(&log_path).map_err(|e| format!("Failed to open log file: {e}"))?;
+    let file = File::open(log_path).map_err(|e| AppError::io("Failed to open log file", e))?;
     let reader = BufReader::new(file);
     let mut entries = Vec::new();
 
@@ -174,7 +305,7 @@ pub fn read_since(app: &AppHandle, start: DateTime<Utc>) -> Result<Vec<SessionEn
         let line = match line_result {
             Ok(line) => line,
             Err(error) => {
-                eprintln!("Failed to read focus log line {}: {error}", line_num + 1);
+                log::warn!("Failed to read focus log line {}: {error}", line_num + 1);
                 continue;
             }
         };
@@ -186,40 +317,32 @@ pub fn read_since(app: &AppHandle, start: DateTime<Utc>) -> Result<Vec<SessionEn
         let log_entry: LogEntry = match serde_json::from_str(&line) {
             Ok(entry) => entry,
             Err(error) => {
-                eprintln!("Failed to parse focus log line {}: {error}", line_num + 1);
-                continue;
-            }
-        };
-
-        let entry_time = match DateTime::parse_from_rfc3339(&log_entry.timestamp) {
-            Ok(time) => time.with_timezone(&Utc),
-            Err(error) => {
-                eprintln!(
-                    "Failed to parse focus log timestamp on line {}: {error}",
-                    line_num + 1
+                // serde messages can quote the offending value, so report
+                // only where parsing stopped.
+                log::warn!(
+                    "Skipping malformed focus log line {} (column {})",
+                    line_num + 1,
+                    error.column()
                 );
                 continue;
             }
         };
 
-        if entry_time >= start {
-            entries.push(SessionEntry::from_log_entry(log_entry));
+        if let Err(error) = log_entry.validate() {
+            log::warn!("Skipping focus log line {}: {error}", line_num + 1);
+            continue;
         }
+
+        entries.push(log_entry);
     }
 
-    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
     Ok(entries)
 }
 
-fn log_file_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let path = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-    Ok(path.join("focus_log.jsonl"))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -269,6 +392,15 @@ mod tests {
             check_in_number: Some(1),
             auto_submitted: None,
             focus_shield_active: None,
+            record_type: None,
+            details: None,
+            template_id: None,
+            interval_adjustment: None,
+            sampled_delay_seconds: None,
+            prompt_outcome: None,
+            response_latency_ms: None,
+            project: None,
+            tags: Vec::new(),
         };
 
         let session_entry = SessionEntry::from_log_entry(log_entry);
@@ -293,6 +425,47 @@ mod tests {
         std::fs::remove_file(path).unwrap();
     }
 
+    #[test]
+    fn new_check_ins_need_a_status_and_other_records_are_written_without_one() {
+        let path = temporary_path("status-required");
+        for line in [
+            r#"{"timestamp":"2025-11-13T10:00:00Z"}"#,
+            r#"{"timestamp":"2025-11-13T10:00:00Z","reported_status":""}"#,
+        ] {
+            assert!(append_entry_to_path(&path, line).is_err());
+        }
+
+        let record = serde_json::json!({
+            "timestamp": "2025-11-13T10:00:00Z",
+            "record_type": SESSION_RECOVERY_RECORD,
+            "details": { "decision": "interrupted" },
+        });
+        append_entry_to_path(&path, &record.to_string()).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert!(written.get("reported_status").is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn older_check_ins_with_an_empty_status_are_still_valid_records() {
+        let path = temporary_path("empty-status");
+        let older = r#"{"timestamp":"2025-11-12T09:00:00Z","session_goal":"Synthetic test goal","reported_status":"","notes":null,"session_duration_setting":720,"check_in_interval_setting":20,"write_time_setting":20,"check_in_number":1,"auto_submitted":true,"focus_shield_active":false}"#;
+        std::fs::write(&path, format!("{older}\n")).unwrap();
+        append_entry_to_path(&path, &entry("2025-11-13T10:00:00Z", "On Task")).unwrap();
+
+        assert!(is_valid_record(older.as_bytes()));
+        let diagnostics = diagnostics_for_path(&path).unwrap();
+        assert_eq!(diagnostics.valid_records, 2);
+        assert_eq!(diagnostics.malformed_records, 0);
+        let entries = read_entries_from_path(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].reported_status, "");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn append_after_unterminated_tail_keeps_prior_valid_records_and_new_record() {
         let path = temporary_path("malformed-tail");
@@ -319,4 +492,31 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn append_waits_for_an_external_journal_lock() {
+        use std::time::Duration;
+
+        let path = temporary_path("external-lock");
+        std::fs::write(
+            &path,
+            format!("{}\n", entry("2025-11-13T10:00:00Z", "On Task")),
+        )
+        .unwrap();
+        let external = File::open(&path).unwrap();
+        external.lock().unwrap();
+
+        let append_path = path.clone();
+        let appender = std::thread::spawn(move || {
+            append_entry_to_path(&append_path, &entry("2025-11-13T10:20:00Z", "On Task"))
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(diagnostics_for_path(&path).unwrap().valid_records, 1);
+
+        external.unlock().unwrap();
+        appender.join().unwrap().unwrap();
+        assert_eq!(diagnostics_for_path(&path).unwrap().valid_records, 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -1,92 +1,98 @@
 // Temporarily allow console in release mode for debugging
 // #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod calendar;
-mod logs;
-mod session_state;
-
-use std::{fs, path::PathBuf, sync::Mutex};
-
-use serde::{Deserialize, Serialize};
-use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
+    time::Duration,
+};
+
+use hyper_awareness::{
+    adaptive::{AdaptiveInterval, IntervalAdjustment},
+    billing, calendar,
+    clock::SystemClock,
+    control, doctor,
+    error::AppError,
+    goals::{self, GoalRecord},
+    instance, logging, logs,
+    outcome::{self, SessionOutcome},
+    projects::{self, Project},
+    session_state, settings,
+    settings::Settings,
+    storage::Storage,
+    templates::{self, SessionTemplate},
+    watchdog, window_position,
+};
+use serde_json::{json, Value};
+use tauri::menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 #[cfg(target_os = "macos")]
 use tauri::ActivationPolicy;
-use tauri::{AppHandle, Manager, PhysicalPosition};
-
-// Store the last known tray icon position
-struct TrayPosition {
-    x: f64,
-    y: f64,
-    _width: f64,
-    _height: f64,
-}
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition};
+use window_position::{
+    calculate_window_position, LogicalSize, MonitorRect, PlacementRequest, TrayRect, WindowBounds,
+    WindowPlacement, DEFAULT_TRAY_ICON_SIZE,
+};
 
 struct AppState {
-    tray_position: Mutex<Option<TrayPosition>>,
+    // Last known tray icon rectangle, in physical pixels
+    tray_rect: Mutex<Option<TrayRect>>,
     suppress_next_main_window_focus_hide: Mutex<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Settings {
-    session_duration: u32,
-    check_in_interval: u32,
-    write_time: u32,
-    window_position: String, // "auto" or "right-edge"
+/// The data directory in use; held for reading while a command touches its files
+/// so a data directory move cannot happen underneath it.
+fn storage(app: &AppHandle) -> RwLockReadGuard<'_, Storage> {
+    app.state::<RwLock<Storage>>().inner().read().unwrap()
 }
 
-impl Default for Settings {
-    fn default() -> Self {
-        Self {
-            session_duration: 720,
-            check_in_interval: 20,
-            write_time: 20,
-            window_position: "auto".to_string(),
-        }
-    }
-}
-
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut path = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-    path.push("settings.json");
-    Ok(path)
+#[tauri::command]
+fn get_settings(app: AppHandle) -> Result<Settings, AppError> {
+    settings::load(&storage(&app))
 }
 
-fn load_settings(app: &AppHandle) -> Result<Settings, String> {
-    let path = settings_path(app)?;
-    if path.exists() {
-        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let settings: Settings = serde_json::from_str(&data).map_err(|e| e.to_string())?;
-        Ok(settings)
-    } else {
-        Ok(Settings::default())
-    }
+#[tauri::command]
+fn save_settings(app: AppHandle, settings: Settings) -> Result<(), AppError> {
+    settings::save(&storage(&app), &settings)?;
+    logging::set_level(&settings.log_level);
+    Ok(())
 }
 
-fn save_settings_file(app: &AppHandle, settings: &Settings) -> Result<(), String> {
-    let path = settings_path(app)?;
-    let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
-    fs::write(path, data).map_err(|e| e.to_string())
+/// Write the diagnostic logs and metadata-only health information to a file in
+/// Downloads and return its path.
+#[tauri::command]
+fn create_support_bundle(app: AppHandle) -> Result<PathBuf, AppError> {
+    let log_dir = app.path().app_log_dir()?;
+    let output_dir = app
+        .path()
+        .download_dir()
+        .unwrap_or_else(|_| log_dir.clone());
+    let path = logging::write_support_bundle(&log_dir, &storage(&app), &output_dir, &SystemClock)?;
+    log::info!("Wrote support bundle to {}", path.display());
+    Ok(path)
 }
 
 #[tauri::command]
-fn get_settings(app: AppHandle) -> Result<Settings, String> {
-    load_settings(&app)
+fn get_data_directory(app: AppHandle) -> PathBuf {
+    storage(&app).root().to_path_buf()
 }
 
+/// Move the data files to a new directory and switch the running app to it.
 #[tauri::command]
-fn save_settings(app: AppHandle, settings: Settings) -> Result<(), String> {
-    save_settings_file(&app, &settings)
+fn set_data_directory(app: AppHandle, path: PathBuf) -> Result<PathBuf, AppError> {
+    let state = app.state::<RwLock<Storage>>();
+    let mut storage = state.write().unwrap();
+    *storage = storage.migrate_to(&path)?;
+    Ok(storage.root().to_path_buf())
 }
 
 #[tauri::command]
-fn open_settings(app: AppHandle) -> Result<(), String> {
+fn open_settings(app: AppHandle) -> Result<(), AppError> {
     suppress_main_window_focus_hide_if_focused(&app);
 
     if let Some(window) = app.get_webview_window("settings") {
-        window.show().map_err(|e| e.to_string())?;
-        window.set_focus().map_err(|e| e.to_string())?;
+        window.show()?;
+        window.set_focus()?;
         return Ok(());
     }
 
@@ -101,203 +107,527 @@ fn open_settings(app: AppHandle) -> Result<(), String> {
     .decorations(true)
     .visible(true)
     .always_on_top(true)
-    .build()
-    .map_err(|e| e.to_string())?;
+    .build()?;
 
     Ok(())
 }
 
 #[tauri::command]
-fn update_tray_timer(app: AppHandle, timer_text: String) -> Result<(), String> {
+fn update_tray_timer(app: AppHandle, timer_text: String) -> Result<(), AppError> {
     if let Some(tray) = app.tray_by_id("main") {
         // Update the menu bar title (shows next to icon in menu bar)
-        tray.set_title(Some(&timer_text))
-            .map_err(|e| e.to_string())?;
+        tray.set_title(Some(&timer_text))?;
     }
 
     Ok(())
 }
 
-fn calculate_window_position(
-    settings: &Settings,
-    monitor_size: &tauri::PhysicalSize<u32>,
-) -> (i32, i32) {
-    let window_width = 380;
-    let y = 50; // Just below menu bar with some padding
+/// Menu item ids for the tray's recent goals carry the goal after this prefix.
+const RECENT_GOAL_MENU_PREFIX: &str = "recent-goal:";
+const RECENT_GOALS_IN_TRAY: usize = 5;
+
+fn tray_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    // Starting a recent goal is only offered while no session is saved, so a
+    // click cannot rename a running or paused one.
+    let session_saved = storage(app).state_path().exists();
+    let recent = goals::load(&storage(app))
+        .map(|history| goals::search(&history, "", RECENT_GOALS_IN_TRAY, chrono::Utc::now()))
+        .unwrap_or_else(|error| {
+            log::warn!("Failed to load the goal history: {error}");
+            Vec::new()
+        });
+    let goal_items = recent
+        .iter()
+        .map(|record| {
+            let id = format!("{RECENT_GOAL_MENU_PREFIX}{}", record.goal);
+            MenuItem::with_id(app, id, &record.goal, !session_saved, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let goal_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = goal_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<tauri::Wry>)
+        .collect();
+    let recent_goals = Submenu::with_items(
+        app,
+        "Recent Goals",
+        !goal_refs.is_empty() && !session_saved,
+        &goal_refs,
+    )?;
+
+    let show_i = MenuItem::with_id(app, "show", "Show Timer", true, None::<&str>)?;
+    let settings_i = MenuItem::with_id(app, "settings", "Settings", true, None::<&str>)?;
+    let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    Menu::with_items(
+        app,
+        &[
+            &show_i,
+            &recent_goals,
+            &settings_i,
+            &PredefinedMenuItem::separator(app)?,
+            &quit_i,
+        ],
+    )
+}
+
+fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id("main") else {
+        return;
+    };
+    if let Err(error) = tray_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        log::warn!("Failed to refresh the tray menu: {error}");
+    }
+}
 
-    let x = match settings.window_position.as_str() {
-        "right-edge" => {
-            // Position with right edge near screen edge (with 20px padding for safety)
-            (monitor_size.width as i32) - window_width - 20
+fn monitor_rect(monitor: &tauri::Monitor) -> MonitorRect {
+    MonitorRect {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width,
+        height: monitor.size().height,
+        scale_factor: monitor.scale_factor(),
+    }
+}
+
+fn place_main_window(
+    app: &AppHandle,
+    placement: WindowPlacement,
+    use_tray: bool,
+) -> Result<(), AppError> {
+    let Some(window) = app.get_webview_window("main") else {
+        return Ok(());
+    };
+
+    let monitors: Vec<MonitorRect> = window
+        .available_monitors()?
+        .iter()
+        .map(monitor_rect)
+        .collect();
+    let current_monitor = window
+        .current_monitor()?
+        .map(|monitor| monitor_rect(&monitor));
+    let scale_factor = window.scale_factor()?;
+    let outer_size = window.outer_size()?;
+    let tray = if use_tray {
+        *app.state::<AppState>().tray_rect.lock().unwrap()
+    } else {
+        None
+    };
+    let mut window_size = LogicalSize {
+        width: outer_size.width as f64 / scale_factor,
+        height: outer_size.height as f64 / scale_factor,
+    };
+    let mut remembered = None;
+
+    if placement == WindowPlacement::RememberLast {
+        let saved = window_position::load_bounds_from_path(&storage(app).window_positions_path())
+            .unwrap_or_else(|error| {
+                log::warn!("Ignoring saved window positions: {error}");
+                Default::default()
+            });
+        if let Some(bounds) = saved.get(&monitors) {
+            window.set_size(tauri::Size::Logical(tauri::LogicalSize {
+                width: bounds.width,
+                height: bounds.height,
+            }))?;
+            window_size = bounds.size();
+            remembered = Some(bounds.position());
         }
-        _ => {
-            // Default "auto" - position closer to where menu bar icons typically are
-            // Menu bar icons are usually in the rightmost ~300px area
-            // Center the window around that area
-            let from_right = 250; // Distance from right edge to center of window
-            (monitor_size.width as i32) - from_right - (window_width / 2)
+    }
+
+    let request = PlacementRequest {
+        placement,
+        monitors: &monitors,
+        current_monitor,
+        tray,
+        window_size,
+        remembered,
+    };
+
+    match calculate_window_position(&request) {
+        Some(point) => window
+            .set_position(tauri::Position::Physical(PhysicalPosition {
+                x: point.x,
+                y: point.y,
+            }))
+            .map_err(AppError::from),
+        None => {
+            log::warn!("No monitor available to position the window on");
+            Ok(())
         }
+    }
+}
+
+/// Save where the main window currently sits for the connected monitor layout.
+fn remember_main_window_bounds(app: &AppHandle) -> Result<(), AppError> {
+    let Some(window) = app.get_webview_window("main") else {
+        return Ok(());
     };
+    if !window.is_visible()? {
+        return Ok(());
+    }
 
-    (x, y)
+    let monitors: Vec<MonitorRect> = window
+        .available_monitors()?
+        .iter()
+        .map(monitor_rect)
+        .collect();
+    let position = window.outer_position()?;
+    let size = window.outer_size()?;
+    let scale_factor = window.scale_factor()?;
+
+    let path = storage(app).window_positions_path();
+    let mut saved = window_position::load_bounds_from_path(&path).unwrap_or_default();
+    saved.remember(
+        &monitors,
+        WindowBounds {
+            x: position.x,
+            y: position.y,
+            width: size.width as f64 / scale_factor,
+            height: size.height as f64 / scale_factor,
+        },
+    );
+    window_position::save_bounds_to_path(&path, &saved)
 }
 
 #[tauri::command]
-fn position_window_at_top(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("main") {
-        // Try to get the stored tray position
-        let state = app.state::<AppState>();
-        let tray_pos = state.tray_position.lock().unwrap();
-
-        if let Some(pos) = tray_pos.as_ref() {
-            // Get the monitor where the tray icon is located
-            if let Ok(Some(monitor)) = window.current_monitor() {
-                let scale_factor = monitor.scale_factor();
-                let monitor_position = monitor.position();
-                let monitor_size = monitor.size();
-
-                eprintln!("🔍 Tray Position (physical): x={}, y={}", pos.x, pos.y);
-                eprintln!("🔍 Monitor scale: {}", scale_factor);
-                eprintln!(
-                    "🔍 Monitor position: ({}, {})",
-                    monitor_position.x, monitor_position.y
-                );
-                eprintln!(
-                    "🔍 Monitor size: ({}, {})",
-                    monitor_size.width, monitor_size.height
-                );
-
-                // Convert from physical pixels to logical pixels
-                let tray_x_logical = pos.x / scale_factor;
-                let tray_y_logical = pos.y / scale_factor;
-
-                eprintln!(
-                    "🔍 Tray Position (logical): x={}, y={}",
-                    tray_x_logical, tray_y_logical
-                );
-
-                // Use stored tray icon position (now in logical pixels)
-                let window_width = 380.0;
-                let icon_width = 22.0;
-                let icon_center_x = tray_x_logical + (icon_width / 2.0);
-                let mut window_x = (icon_center_x - (window_width / 2.0)).round() as i32;
-                let window_y = (tray_y_logical + icon_width + 5.0).round() as i32;
-
-                eprintln!("🔍 Icon center (logical): {}", icon_center_x);
-                eprintln!("🔍 Window width: {}", window_width);
-                eprintln!("🔍 Initial window_x (logical): {}", window_x);
-                eprintln!("🔍 Initial window_y (logical): {}", window_y);
-
-                // Convert monitor bounds to logical pixels
-                let monitor_right =
-                    monitor_position.x + (monitor_size.width as i32 / scale_factor as i32);
-                let monitor_left = monitor_position.x;
-
-                // Constrain window to monitor bounds (with 10px padding)
-                if window_x < monitor_left {
-                    eprintln!("⚠️ Window X too far left, constraining to monitor");
-                    window_x = monitor_left + 10;
-                } else if window_x + (window_width as i32) > monitor_right {
-                    eprintln!("⚠️ Window X too far right, constraining to monitor");
-                    window_x = monitor_right - (window_width as i32) - 10;
-                }
+fn position_window_at_top(app: AppHandle) -> Result<(), AppError> {
+    let settings = settings::load(&storage(&app)).unwrap_or_default();
+    place_main_window(
+        &app,
+        WindowPlacement::from_setting(&settings.window_position),
+        true,
+    )
+}
 
-                eprintln!("🔍 Final window_x (logical): {}", window_x);
+#[tauri::command]
+fn position_window_centered(app: AppHandle) -> Result<(), AppError> {
+    place_main_window(&app, WindowPlacement::Centered, false)
+}
 
-                window
-                    .set_position(tauri::Position::Logical(tauri::LogicalPosition {
-                        x: window_x as f64,
-                        y: window_y as f64,
-                    }))
-                    .map_err(|e| e.to_string())?;
-            } else {
-                eprintln!("⚠️ Could not get current monitor");
-            }
-        } else {
-            // Fallback to old positioning if we don't have tray position yet
-            eprintln!("⚠️ No tray position captured yet, using fallback");
-            let settings = load_settings(&app).unwrap_or_default();
-            if let Ok(Some(monitor)) = window.current_monitor() {
-                let monitor_size = monitor.size();
-                let (x, y) = calculate_window_position(&settings, monitor_size);
-                window
-                    .set_position(tauri::Position::Physical(PhysicalPosition { x, y }))
-                    .map_err(|e| e.to_string())?;
-            }
-        }
+#[tauri::command]
+fn log_check_in(app: AppHandle, log_line: String) -> Result<(), AppError> {
+    logs::append_entry(&storage(&app), &log_line)?;
+    let entry: Value = serde_json::from_str(&log_line).unwrap_or_default();
+    publish_control_event(
+        &app,
+        json!({
+            "type": "checkIn",
+            "timestamp": entry["timestamp"],
+            "status": entry["reported_status"],
+        }),
+    );
+    // A session's goal joins the recent goals with its first check-in.
+    if entry["check_in_number"] == 1 {
+        refresh_tray_menu(&app);
     }
     Ok(())
 }
 
+/// Apply a check-in answer to an adaptive interval; returns the new interval
+/// and the adjustment to journal with the check-in.
 #[tauri::command]
-fn position_window_centered(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("main") {
-        if let Ok(Some(monitor)) = window.current_monitor() {
-            let monitor_frame = monitor.position();
-            let monitor_size = monitor.size();
-            let scale_factor = monitor.scale_factor();
-
-            let window_width = 380.0;
-            let window_height = 500.0;
-
-            // Calculate center position in logical pixels
-            let center_x = monitor_frame.x as f64
-                + (monitor_size.width as f64 / scale_factor) / 2.0
-                - (window_width / 2.0);
-            let center_y = monitor_frame.y as f64
-                + (monitor_size.height as f64 / scale_factor) / 2.0
-                - (window_height / 2.0);
-
-            eprintln!("📍 Centering window at ({}, {})", center_x, center_y);
-
-            window
-                .set_position(tauri::Position::Logical(tauri::LogicalPosition {
-                    x: center_x,
-                    y: center_y,
-                }))
-                .map_err(|e| e.to_string())?;
-        }
-    }
+fn adapt_check_in_interval(
+    adaptive: AdaptiveInterval,
+    status: String,
+) -> (AdaptiveInterval, IntervalAdjustment) {
+    adaptive.after_answer(&status)
+}
+
+/// Journal a finished Pomodoro phase from the state it finished in.
+#[tauri::command]
+fn log_pomodoro_phase(
+    app: AppHandle,
+    state: session_state::ActiveSessionState,
+    finished: session_state::PomodoroPhase,
+    seconds: u64,
+) -> Result<(), AppError> {
+    state.validate()?;
+    let entry = state.pomodoro_phase_entry(finished, seconds, chrono::Utc::now())?;
+    logs::append_entry(&storage(&app), &entry.to_string())?;
+    publish_control_event(
+        &app,
+        json!({
+            "type": "pomodoroPhase",
+            "phase": finished,
+            "seconds": seconds,
+        }),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+fn log_deferred_prompt(
+    app: AppHandle,
+    state: session_state::ActiveSessionState,
+    deferred_until: i64,
+) -> Result<(), AppError> {
+    state.validate()?;
+    let entry = state.deferred_prompt_entry(deferred_until, chrono::Utc::now());
+    logs::append_entry(&storage(&app), &entry.to_string())?;
+    publish_control_event(
+        &app,
+        json!({
+            "type": "checkInDeferred",
+            "deferredUntil": deferred_until,
+        }),
+    );
     Ok(())
 }
 
 #[tauri::command]
-fn log_check_in(app: AppHandle, log_line: String) -> Result<(), String> {
-    logs::append_entry(&app, &log_line)
+fn log_subtask_completed(
+    app: AppHandle,
+    state: session_state::ActiveSessionState,
+    id: u32,
+) -> Result<(), AppError> {
+    state.validate()?;
+    let entry = state.subtask_completed_entry(id)?;
+    logs::append_entry(&storage(&app), &entry.to_string())?;
+    publish_control_event(
+        &app,
+        json!({
+            "type": "subtaskCompleted",
+            "details": entry["details"],
+        }),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+fn session_report(
+    app: AppHandle,
+    state: session_state::ActiveSessionState,
+) -> Result<session_state::SessionReport, AppError> {
+    state.validate()?;
+    session_state::report(&storage(&app), &state)
+}
+
+#[tauri::command]
+fn log_session_outcome(
+    app: AppHandle,
+    state: session_state::ActiveSessionState,
+    outcome: SessionOutcome,
+) -> Result<(), AppError> {
+    state.validate()?;
+    let entry = outcome::entry(&storage(&app), &state, &outcome, chrono::Utc::now())?;
+    logs::append_entry(&storage(&app), &entry.to_string())?;
+    publish_control_event(
+        &app,
+        json!({
+            "type": "sessionOutcome",
+            "ended": outcome.ended,
+            "rating": outcome.rating,
+        }),
+    );
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// Visible goals from the history matching `query`, best first.
+#[tauri::command]
+fn search_goals(app: AppHandle, query: String, limit: usize) -> Result<Vec<GoalRecord>, AppError> {
+    let history = goals::load(&storage(&app))?;
+    Ok(goals::search(&history, &query, limit, chrono::Utc::now()))
+}
+
+#[tauri::command]
+fn list_goal_history(app: AppHandle) -> Result<Vec<GoalRecord>, AppError> {
+    goals::load(&storage(&app))
+}
+
+#[tauri::command]
+fn set_goal_hidden(app: AppHandle, goal: String, hidden: bool) -> Result<(), AppError> {
+    goals::set_hidden(&storage(&app), &goal, hidden)?;
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn list_projects(app: AppHandle) -> Result<Vec<Project>, AppError> {
+    projects::load(&storage(&app))
+}
+
+#[tauri::command]
+fn save_project(app: AppHandle, project: Project) -> Result<(), AppError> {
+    projects::save(&storage(&app), project)
+}
+
+#[tauri::command]
+fn set_project_archived(app: AppHandle, id: String, archived: bool) -> Result<(), AppError> {
+    projects::set_archived(&storage(&app), &id, archived)
+}
+
+/// Write the billable-hours report for the days `from` to `to` to a file in
+/// Downloads, as "csv" or printable "html", and return its path.
+#[tauri::command]
+fn export_billing_report(
+    app: AppHandle,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    format: String,
+) -> Result<PathBuf, AppError> {
+    let storage = storage(&app);
+    let report = billing::report(
+        &logs::read_entries_from_path(&storage.log_path())?,
+        from,
+        to,
+        settings::load(&storage)?.billing_rounding_minutes,
+        &chrono::Local,
+    )?;
+    let registry = projects::load(&storage)?;
+    let contents = match format.as_str() {
+        "csv" => billing::to_csv(&report, &registry),
+        "html" => billing::to_html(&report, &registry),
+        _ => return Err(AppError::InvalidArgument("the format must be csv or html")),
+    };
+    let output_dir = app
+        .path()
+        .download_dir()
+        .unwrap_or_else(|_| storage.root().to_path_buf());
+    let path = output_dir.join(format!("billable-hours-{from}-to-{to}.{format}"));
+    std::fs::write(&path, contents)
+        .map_err(|e| AppError::io("Failed to save the billing report", e))?;
+    Ok(path)
+}
+
+#[tauri::command]
+fn list_session_templates(app: AppHandle) -> Result<Vec<SessionTemplate>, AppError> {
+    templates::load(&storage(&app))
+}
+
+#[tauri::command]
+fn save_session_template(app: AppHandle, template: SessionTemplate) -> Result<(), AppError> {
+    templates::save(&storage(&app), template)
+}
+
+#[tauri::command]
+fn delete_session_template(app: AppHandle, id: String) -> Result<(), AppError> {
+    templates::delete(&storage(&app), &id)
+}
+
+#[tauri::command]
+fn template_for_calendar_event(
+    app: AppHandle,
+    title: String,
+) -> Result<Option<SessionTemplate>, AppError> {
+    let templates = templates::load(&storage(&app))?;
+    Ok(templates::for_calendar_event(&templates, &title).cloned())
+}
+
+/// The session timer runs in the webview, so a template start is forwarded to
+/// it like a control socket `start`.
+fn template_start_command(template: &SessionTemplate) -> Value {
+    json!({
+        "command": "start",
+        "goal": template.goal_on(chrono::Local::now().date_naive()),
+        "template": template,
+    })
+}
+
+#[tauri::command]
+fn start_session_from_template(app: AppHandle, id: String) -> Result<(), AppError> {
+    let template = templates::find(&storage(&app), &id)?;
+    app.emit("control-command", template_start_command(&template))
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
 fn save_active_session(
     app: AppHandle,
     state: session_state::ActiveSessionState,
-) -> Result<(), String> {
-    session_state::save(&app, state)
+) -> Result<(), AppError> {
+    let event = json!({
+        "type": "sessionSaved",
+        "phase": state.phase,
+        "sessionTimeRemaining": state.session_time_remaining,
+        "checkInTimeRemaining": state.check_in_time_remaining,
+    });
+    let first_save = {
+        let storage = storage(&app);
+        let first_save = !storage.state_path().exists();
+        session_state::save(&storage, state, &SystemClock)?;
+        first_save
+    };
+    publish_control_event(&app, event);
+    if first_save {
+        refresh_tray_menu(&app);
+    }
+    Ok(())
 }
 
 #[tauri::command]
 fn recover_active_session(
     app: AppHandle,
-) -> Result<Option<session_state::ActiveSessionState>, String> {
-    session_state::recover(&app)
+) -> Result<Option<session_state::ActiveSessionState>, AppError> {
+    let storage = storage(&app);
+    let settings = settings::load(&storage).unwrap_or_default();
+    session_state::recover(&storage, (&settings).into(), &SystemClock)
+}
+
+#[tauri::command]
+fn list_session_snapshots(app: AppHandle) -> Result<Vec<session_state::SnapshotSummary>, AppError> {
+    session_state::snapshot_history(&storage(&app))
+}
+
+#[tauri::command]
+fn restore_session_snapshot(
+    app: AppHandle,
+    id: String,
+) -> Result<session_state::ActiveSessionState, AppError> {
+    session_state::restore_snapshot(&storage(&app), &id, &SystemClock)
 }
 
+/// Remove temporary snapshots left by a crash; returns one worth restoring.
 #[tauri::command]
-fn clear_active_session(app: AppHandle) -> Result<(), String> {
-    session_state::clear(&app)
+fn clean_up_session_state_files(
+    app: AppHandle,
+) -> Result<Option<session_state::OrphanedState>, AppError> {
+    session_state::clean_up_orphans(&storage(&app), &SystemClock)
 }
 
 #[tauri::command]
-fn get_persistence_diagnostics(app: AppHandle) -> Result<logs::LogDiagnostics, String> {
-    logs::diagnostics(&app)
+fn resolve_orphaned_session_state(
+    app: AppHandle,
+    file_name: String,
+    restore: bool,
+) -> Result<(), AppError> {
+    session_state::resolve_orphan(&storage(&app), &file_name, restore)?;
+    Ok(())
 }
 
 #[tauri::command]
-fn get_current_event() -> Result<Option<String>, String> {
+fn clear_active_session(app: AppHandle) -> Result<(), AppError> {
+    session_state::clear(&storage(&app))?;
+    publish_control_event(&app, json!({ "type": "sessionCleared" }));
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_persistence_diagnostics(app: AppHandle) -> Result<doctor::HealthReport, AppError> {
+    doctor::check(&storage(&app))
+}
+
+/// Apply only the repairs the user approved from a health report.
+#[tauri::command]
+fn repair_persistence(
+    app: AppHandle,
+    actions: Vec<doctor::RepairAction>,
+) -> Result<doctor::HealthReport, AppError> {
+    let storage = storage(&app);
+    for action in &actions {
+        doctor::apply(&storage, action)?;
+    }
+    doctor::check(&storage)
+}
+
+#[tauri::command]
+fn get_current_event() -> Result<Option<String>, AppError> {
     calendar::get_current_calendar_event()
 }
 
 #[tauri::command]
-fn request_calendar_permission() -> Result<String, String> {
+fn request_calendar_permission() -> Result<String, AppError> {
     calendar::request_calendar_access()
 }
 
@@ -305,35 +635,131 @@ fn request_calendar_permission() -> Result<String, String> {
 fn list_session_entries(
     app: AppHandle,
     start_time_iso: String,
-) -> Result<Vec<logs::SessionEntry>, String> {
+) -> Result<Vec<logs::SessionEntry>, AppError> {
     use chrono::DateTime;
 
     // Parse the ISO timestamp
     let start_time = DateTime::parse_from_rfc3339(&start_time_iso)
-        .map_err(|e| format!("Invalid timestamp format: {}", e))?
+        .map_err(|_| AppError::InvalidArgument("start time is not an RFC3339 timestamp"))?
         .with_timezone(&chrono::Utc);
 
     // Read entries since start time
-    logs::read_since(&app, start_time)
+    logs::read_since(&storage(&app), start_time)
 }
 
 #[tauri::command]
-fn hide_window(window: tauri::WebviewWindow) -> Result<(), String> {
-    window.hide().map_err(|e| e.to_string())
+fn hide_window(window: tauri::WebviewWindow) -> Result<(), AppError> {
+    window.hide().map_err(AppError::from)
 }
 
 #[tauri::command]
-fn minimize_main_window(app: AppHandle, window: tauri::WebviewWindow) -> Result<(), String> {
+fn minimize_main_window(app: AppHandle, window: tauri::WebviewWindow) -> Result<(), AppError> {
     suppress_next_main_window_focus_hide(&app);
-    window.minimize().map_err(|e| e.to_string())
+    window.minimize().map_err(AppError::from)
 }
 
 #[tauri::command]
-fn keep_app_alive() -> Result<(), String> {
+fn keep_app_alive() -> Result<(), AppError> {
     // This command ensures the frontend is communicating with the backend regularly
     Ok(())
 }
 
+fn publish_control_event(app: &AppHandle, event: Value) {
+    if let Some(events) = app.try_state::<Arc<control::EventHub>>() {
+        events.publish(event);
+    }
+}
+
+/// Reconcile the saved session with a sleep or clock change and tell the
+/// webview, which adopts the reconciled state, and control subscribers.
+fn handle_clock_change(app: &AppHandle, change: watchdog::ClockChange) {
+    log::info!("Clock change detected: {change:?}");
+    let state = watchdog::reconcile(&storage(app), &change, &SystemClock).unwrap_or_else(|error| {
+        log::warn!("Failed to reconcile the session with a clock change: {error}");
+        None
+    });
+    let message = change.description(state.as_ref());
+    let _ = app.emit(
+        "clock-change",
+        json!({ "change": change, "state": state, "message": message }),
+    );
+    publish_control_event(
+        app,
+        json!({ "type": "clockChange", "change": change, "message": message }),
+    );
+}
+
+/// Serve a control socket request through the same paths as the Tauri commands.
+///
+/// The session timer runs in the webview, so lifecycle requests are forwarded
+/// to it as `control-command` events rather than handled here.
+fn handle_control_request(
+    app: &AppHandle,
+    request: control::ControlRequest,
+) -> Result<Value, AppError> {
+    use control::ControlRequest;
+
+    let forward = |payload: Value| {
+        app.emit("control-command", payload)
+            .map(|_| json!({ "accepted": true }))
+            .map_err(AppError::from)
+    };
+
+    match request {
+        ControlRequest::Status => serde_json::to_value(session_state::load(&storage(app))?)
+            .map_err(AppError::state_corrupt),
+        ControlRequest::Start { goal } => forward(json!({ "command": "start", "goal": goal })),
+        ControlRequest::StartTemplate { id } => {
+            let template = templates::find(&storage(app), &id)?;
+            forward(template_start_command(&template))
+        }
+        ControlRequest::Pause => forward(json!({ "command": "pause" })),
+        ControlRequest::Resume => forward(json!({ "command": "resume" })),
+        ControlRequest::End => forward(json!({ "command": "end" })),
+        ControlRequest::CheckIn { status, note } => {
+            let state = session_state::load(&storage(app))?.ok_or(AppError::NoActiveSession)?;
+            state.accepts_check_in(&status)?;
+            let log_line = state.check_in_entry(&status, note.as_deref(), chrono::Utc::now());
+            log_check_in(app.clone(), log_line.to_string())?;
+            // The webview owns the timers; hand it the session as the answer
+            // leaves it, so it advances exactly as the CLI would.
+            let mut answered = state;
+            answered.apply_answer(&status);
+            forward(json!({
+                "command": "checkInLogged",
+                "status": status,
+                "answered": {
+                    "checkInsCompleted": answered.check_ins_completed,
+                    "checkInTimeRemaining": answered.check_in_time_remaining,
+                    "skippedCheckIns": answered.skipped_check_ins,
+                    "lastCheckInWasSkipped": answered.last_check_in_was_skipped,
+                    "adaptive": answered.adaptive,
+                    "sampling": answered.sampling,
+                },
+            }))
+        }
+        ControlRequest::Focus => {
+            let handle = app.clone();
+            app.run_on_main_thread(move || show_main_window(&handle))
+                .map(|_| Value::Null)
+                .map_err(AppError::from)
+        }
+        ControlRequest::Subscribe => Err(AppError::InvalidArgument(
+            "subscriptions are handled by the control server",
+        )),
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        #[cfg(target_os = "macos")]
+        let _ = app.show();
+    }
+}
+
 fn suppress_next_main_window_focus_hide(app: &AppHandle) {
     *app.state::<AppState>()
         .suppress_next_main_window_focus_hide
@@ -373,38 +799,46 @@ fn should_hide_main_window_on_focus_loss(
 fn main() {
     tauri::Builder::default()
         .manage(AppState {
-            tray_position: Mutex::new(None),
+            tray_rect: Mutex::new(None),
             suppress_next_main_window_focus_hide: Mutex::new(false),
         })
         .on_menu_event(|app, event| match event.id().as_ref() {
-            "show" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.unminimize();
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                    #[cfg(target_os = "macos")]
-                    let _ = app.show();
-                }
-            }
+            "show" => show_main_window(app),
             "settings" => {
                 let _ = open_settings(app.clone());
             }
             "quit" => {
+                let _ = remember_main_window_bounds(app);
                 app.exit(0);
             }
-            _ => {}
+            id => {
+                if let Some(goal) = id.strip_prefix(RECENT_GOAL_MENU_PREFIX) {
+                    let _ = app.emit(
+                        "control-command",
+                        json!({ "command": "start", "goal": goal }),
+                    );
+                }
+            }
         })
         // Keep the timer's webview alive when the main window is dismissed.
         .on_window_event(|window, event| match event {
             tauri::WindowEvent::CloseRequested { api, .. } => {
-                eprintln!("🏃 Window close requested, hiding window to keep app alive.");
+                log::debug!("Window close requested, hiding window to keep app alive");
+                if window.label() == "main" {
+                    let _ = remember_main_window_bounds(window.app_handle());
+                }
                 api.prevent_close();
                 let _ = window.hide();
             }
             tauri::WindowEvent::Focused(false) if window.label() == "main" => {
+                if let Err(error) = remember_main_window_bounds(window.app_handle()) {
+                    log::warn!("Failed to remember window position: {error}");
+                }
                 let suppress_hide = take_main_window_focus_hide_suppression(window.app_handle());
                 if should_hide_main_window_on_focus_loss("main", false, suppress_hide) {
-                    eprintln!("👋 Main window lost focus, hiding it while the session keeps running.");
+                    log::debug!(
+                        "Main window lost focus, hiding it while the session keeps running"
+                    );
                     let _ = window.hide();
                 }
             }
@@ -417,32 +851,53 @@ fn main() {
                 app.set_activation_policy(ActivationPolicy::Regular);
             }
 
-            let settings = load_settings(app.handle()).unwrap_or_default();
-            let initial_time = format!("{}:00", settings.check_in_interval);
+            let storage = Storage::open(app.path().app_config_dir()?)?;
+            // Only the owning instance writes and rotates the diagnostic log.
+            match instance::acquire(&storage.instance_lock_path())? {
+                instance::LockOutcome::Acquired(lock) => {
+                    app.manage(lock);
+                    app.manage(RwLock::new(storage.clone()));
+                }
+                instance::LockOutcome::HeldBy(pid) => {
+                    eprintln!(
+                        "Another instance (pid {}) owns {}; focusing it instead",
+                        pid.map_or_else(|| "unknown".to_string(), |pid| pid.to_string()),
+                        storage.root().display()
+                    );
+                    if let Err(error) = control::call(&storage.control_socket_path(), "focus", None)
+                    {
+                        eprintln!("Failed to focus the running instance: {error}");
+                    }
+                    std::process::exit(0);
+                }
+            }
+            let settings = settings::load(&storage).unwrap_or_default();
+            if let Err(error) = logging::init(&app.path().app_log_dir()?, &settings.log_level) {
+                eprintln!("Diagnostic logging unavailable: {error}");
+            }
 
-            // Create context menu items
-            let show_i = MenuItem::with_id(app, "show", "Show Timer", true, None::<&str>)?;
-            let settings_i = MenuItem::with_id(app, "settings", "Settings", true, None::<&str>)?;
-            let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+            let events = Arc::new(control::EventHub::default());
+            app.manage(Arc::clone(&events));
+            let control_handle = app.handle().clone();
+            control::serve(&storage.control_socket_path(), events, move |request| {
+                handle_control_request(&control_handle, request)
+            })?;
+
+            let watchdog_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                watchdog::run(SystemClock, Duration::from_secs(5), |change| {
+                    handle_clock_change(&watchdog_handle, change)
+                })
+            });
 
-            let _menu = Menu::with_items(
-                app,
-                &[
-                    &show_i,
-                    &settings_i,
-                    &PredefinedMenuItem::separator(app)?,
-                    &quit_i,
-                ],
-            )?;
+            let initial_time = format!("{}:00", settings.check_in_interval);
 
-            eprintln!("🛠️ Building tray icon...");
+            let _menu = tray_menu(app.handle())?;
 
             // Try using the default app icon first for testing
             let icon = app.default_window_icon().unwrap().clone();
-            eprintln!("✅ Icon loaded from default_window_icon");
 
             // Build tray with icon and menu attached
-            eprintln!("🔨 Building TrayIconBuilder...");
             let tray_result = TrayIconBuilder::with_id("main")
                 .icon(icon)
                 .icon_as_template(true) // Let macOS handle dark/light mode coloring
@@ -451,7 +906,7 @@ fn main() {
                 .menu(&_menu)
                 .show_menu_on_left_click(false) // Left click toggles window, right click shows menu
                 .on_tray_icon_event(move |tray, event| {
-                    eprintln!("🖱️ Tray event received: {:?}", event);
+                    log::trace!("Tray event received: {:?}", event);
                     let app = tray.app_handle();
                     let state = app.state::<AppState>();
 
@@ -459,6 +914,7 @@ fn main() {
                         button,
                         button_state,
                         position,
+                        rect,
                         ..
                     } = event
                     {
@@ -479,16 +935,28 @@ fn main() {
                         }
 
                         *state.suppress_next_main_window_focus_hide.lock().unwrap() = false;
-                        eprintln!("✅ Left click detected at position: {:?}", position);
-                        *state.tray_position.lock().unwrap() = Some(TrayPosition {
-                            x: position.x,
-                            y: position.y,
-                            _width: 22.0,
-                            _height: 22.0,
+                        log::debug!("Tray left click at {:?}", position);
+                        let origin = rect.position.to_physical::<f64>(1.0);
+                        let size = rect.size.to_physical::<f64>(1.0);
+                        *state.tray_rect.lock().unwrap() = Some(if size.width > 0.0 {
+                            TrayRect {
+                                x: origin.x,
+                                y: origin.y,
+                                width: size.width,
+                                height: size.height,
+                            }
+                        } else {
+                            TrayRect {
+                                x: position.x,
+                                y: position.y,
+                                width: DEFAULT_TRAY_ICON_SIZE,
+                                height: DEFAULT_TRAY_ICON_SIZE,
+                            }
                         });
 
                         if let Some(window) = app.get_webview_window("main") {
                             if window.is_visible().unwrap_or(false) {
+                                let _ = remember_main_window_bounds(app);
                                 let _ = window.hide();
                             } else {
                                 // CRITICAL: Unminimize and activate app for proper window focus in builds
@@ -507,11 +975,10 @@ fn main() {
 
             match tray_result {
                 Ok(_) => {
-                    eprintln!("✅ Tray icon BUILT successfully - should now be visible in menu bar");
-                    eprintln!("📍 If you don't see it, check: 1) App is still running  2) Menu bar is not full  3) System tray settings");
+                    log::info!("Tray icon built");
                 }
                 Err(e) => {
-                    eprintln!("❌ TRAY BUILD FAILED: {:?}", e);
+                    log::error!("Tray icon build failed: {:?}", e);
                     return Err(Box::new(e));
                 }
             }
@@ -522,6 +989,9 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             get_settings,
             save_settings,
+            get_data_directory,
+            set_data_directory,
+            create_support_bundle,
             open_settings,
             update_tray_timer,
             position_window_at_top,
@@ -529,10 +999,33 @@ fn main() {
             hide_window,
             minimize_main_window,
             log_check_in,
+            log_pomodoro_phase,
+            log_deferred_prompt,
+            log_subtask_completed,
+            session_report,
+            log_session_outcome,
+            search_goals,
+            list_goal_history,
+            set_goal_hidden,
+            list_projects,
+            save_project,
+            set_project_archived,
+            export_billing_report,
+            adapt_check_in_interval,
+            list_session_templates,
+            save_session_template,
+            delete_session_template,
+            template_for_calendar_event,
+            start_session_from_template,
             save_active_session,
             recover_active_session,
+            list_session_snapshots,
+            restore_session_snapshot,
+            clean_up_session_state_files,
+            resolve_orphaned_session_state,
             clear_active_session,
             get_persistence_diagnostics,
+            repair_persistence,
             get_current_event,
             request_calendar_permission,
             list_session_entries,
@@ -543,17 +1036,16 @@ fn main() {
         .run(|app_handle, event| {
             #[cfg(target_os = "macos")]
             if let tauri::RunEvent::Reopen { .. } = event {
-                eprintln!("🚀 Dock icon clicked (Reopen event)");
                 if let Some(window) = app_handle.get_webview_window("main") {
-                    if let Ok(visible) = window.is_visible() {
-                        eprintln!("📊 Window visible status: {}", visible);
-                    }
+                    log::debug!(
+                        "Dock icon clicked; main window visible: {:?}",
+                        window.is_visible()
+                    );
                     let _ = window.unminimize();
                     let _ = position_window_centered(app_handle.clone());
                     let _ = window.show();
                     let _ = window.set_focus();
                     let _ = app_handle.show();
-                    eprintln!("✅ Window should now be visible and focused");
                 }
             }
             #[cfg(not(target_os = "macos"))]
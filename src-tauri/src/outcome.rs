@@ -0,0 +1,308 @@
+//! End-of-session outcome records: the user's verdict on the session goal,
+//! journaled with totals computed from the session's own records.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    logs,
+    session_state::ActiveSessionState,
+    stats::{self, CheckInStats},
+    storage::Storage,
+};
+
+const MAX_REFLECTION_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalRating {
+    Achieved,
+    Partial,
+    Missed,
+}
+
+/// How the session came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEnd {
+    /// The session timer ran out.
+    Completed,
+    /// Ended before the timer ran out, e.g. from the command line.
+    EndedEarly,
+    Reset,
+}
+
+/// What the user reports when a session ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionOutcome {
+    pub ended: SessionEnd,
+    pub rating: GoalRating,
+    /// 1 to 10.
+    pub focus_score: u8,
+    #[serde(default)]
+    pub reflection: String,
+}
+
+/// Totals over the session's journal records, stored with the outcome so the
+/// record stands on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeTotals {
+    /// Session time that ran, excluding pauses.
+    pub elapsed_minutes: u64,
+    pub check_ins: usize,
+    pub on_task_check_ins: usize,
+    pub skipped_check_ins: usize,
+    pub auto_submitted_check_ins: usize,
+    pub deferred_prompts: usize,
+    pub focused_minutes: u64,
+    pub subtasks_finished: usize,
+    pub subtasks_total: usize,
+}
+
+impl SessionOutcome {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(1..=10).contains(&self.focus_score) {
+            return Err(AppError::InvalidArgument(
+                "the focus score must be between 1 and 10",
+            ));
+        }
+        if self.reflection.chars().count() > MAX_REFLECTION_CHARS {
+            return Err(AppError::InvalidArgument(
+                "the reflection must be at most 500 characters",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl OutcomeTotals {
+    fn new(state: &ActiveSessionState, summary: &CheckInStats) -> Self {
+        let elapsed_seconds =
+            (u64::from(state.session_duration) * 60).saturating_sub(state.session_time_remaining);
+        Self {
+            elapsed_minutes: elapsed_seconds / 60,
+            check_ins: summary.total_check_ins,
+            on_task_check_ins: summary.on_task_check_ins,
+            skipped_check_ins: summary.skipped_check_ins,
+            auto_submitted_check_ins: summary.auto_submitted_check_ins,
+            deferred_prompts: summary.deferred_prompts,
+            focused_minutes: summary.focused_minutes,
+            subtasks_finished: state
+                .subtasks
+                .iter()
+                .filter(|subtask| subtask.completed_at.is_some())
+                .count(),
+            subtasks_total: state.subtasks.len(),
+        }
+    }
+}
+
+/// Build the journal line for a session's outcome, totalling the records the
+/// session wrote up to `now`.
+pub fn entry(
+    storage: &Storage,
+    state: &ActiveSessionState,
+    outcome: &SessionOutcome,
+    now: DateTime<Utc>,
+) -> Result<serde_json::Value, AppError> {
+    outcome.validate()?;
+    let started_at = state
+        .session_started_at
+        .and_then(DateTime::from_timestamp_millis)
+        .ok_or(AppError::InvalidArgument("the session has not started"))?;
+    let journal = logs::read_entries_from_path(&storage.log_path())?;
+    let summary = stats::summarize(&journal, started_at, now + chrono::Duration::seconds(1));
+    Ok(serde_json::json!({
+        "timestamp": now.to_rfc3339(),
+        "record_type": logs::SESSION_OUTCOME_RECORD,
+        "session_goal": state.session_goal,
+        "template_id": state.template_id,
+        "project": state.project,
+        "tags": state.tags,
+        "session_duration_setting": state.session_duration,
+        "details": {
+            "ended": outcome.ended,
+            "rating": outcome.rating,
+            "focus_score": outcome.focus_score,
+            "reflection": outcome.reflection,
+            "session_started_at": started_at.to_rfc3339(),
+            "totals": OutcomeTotals::new(state, &summary),
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_state::Subtask;
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn outcome_record_totals_the_sessions_own_check_ins() {
+        let dir = temporary_dir("outcome");
+        let storage = Storage::open(&dir).unwrap();
+        let mut state: ActiveSessionState = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "phase": "active",
+            "sessionGoal": "Ship release notes",
+            "sessionStartedAt": 1_741_000_000_000i64,
+            "sessionDuration": 90,
+            "checkInInterval": 20,
+            "writeTime": 20,
+            "sessionTimeRemaining": 30 * 60,
+            "checkInTimeRemaining": 600,
+            "writeTimeRemaining": 0,
+            "checkInsCompleted": 0,
+            "skippedCheckIns": 0,
+            "lastCheckInWasSkipped": false,
+            "focusShieldActive": false,
+            "focusShieldUntil": null,
+            "recoveryReason": null
+        }))
+        .unwrap();
+        state.subtasks = vec![Subtask {
+            id: 1,
+            text: "Collect merged PRs".to_string(),
+            completed_at: Some(1_741_000_600_000),
+        }];
+        let started = DateTime::from_timestamp_millis(1_741_000_000_000).unwrap();
+        let minutes = |n| started + chrono::Duration::minutes(n);
+        // Written before this session started.
+        let earlier = state.check_in_entry("On Task", None, minutes(-30));
+        logs::append_entry(&storage, &earlier.to_string()).unwrap();
+        for (at, status) in [(20, "On Task"), (40, "Skip"), (60, "On Task")] {
+            let check_in = state.check_in_entry(status, None, minutes(at));
+            logs::append_entry(&storage, &check_in.to_string()).unwrap();
+        }
+
+        let outcome = SessionOutcome {
+            ended: SessionEnd::EndedEarly,
+            rating: GoalRating::Partial,
+            focus_score: 7,
+            reflection: "Notes drafted, review pending".to_string(),
+        };
+        let record = entry(&storage, &state, &outcome, minutes(60)).unwrap();
+        assert_eq!(record["record_type"], logs::SESSION_OUTCOME_RECORD);
+        assert_eq!(record["details"]["ended"], "ended_early");
+        assert_eq!(record["details"]["rating"], "partial");
+        let totals: OutcomeTotals =
+            serde_json::from_value(record["details"]["totals"].clone()).unwrap();
+        assert_eq!(
+            totals,
+            OutcomeTotals {
+                elapsed_minutes: 60,
+                check_ins: 3,
+                on_task_check_ins: 2,
+                skipped_check_ins: 1,
+                auto_submitted_check_ins: 0,
+                deferred_prompts: 0,
+                focused_minutes: 40,
+                subtasks_finished: 1,
+                subtasks_total: 1,
+            }
+        );
+
+        // Stats read the rating back from the journal.
+        logs::append_entry(&storage, &record.to_string()).unwrap();
+        let journal = logs::read_entries_from_path(&storage.log_path()).unwrap();
+        let summary = stats::summarize(&journal, started, minutes(61));
+        assert_eq!(summary.total_check_ins, 3);
+        assert_eq!(summary.partial_goals, 1);
+        assert_eq!(summary.goal_completion_ratio(), Some(0.0));
+
+        let unrated = SessionOutcome {
+            focus_score: 0,
+            ..outcome.clone()
+        };
+        assert!(entry(&storage, &state, &unrated, Utc::now()).is_err());
+        state.session_started_at = None;
+        assert!(entry(&storage, &state, &outcome, Utc::now()).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn outcomes_are_validated_and_elapsed_time_leaves_out_pauses() {
+        let outcome = |focus_score, reflection: String| SessionOutcome {
+            ended: SessionEnd::Completed,
+            rating: GoalRating::Achieved,
+            focus_score,
+            reflection,
+        };
+        for score in [1, 10] {
+            assert!(outcome(score, String::new()).validate().is_ok());
+        }
+        for score in [0, 11] {
+            assert_eq!(
+                outcome(score, String::new()).validate().unwrap_err(),
+                AppError::InvalidArgument("the focus score must be between 1 and 10")
+            );
+        }
+        // Counted in characters, not bytes.
+        assert!(outcome(5, "é".repeat(500)).validate().is_ok());
+        assert_eq!(
+            outcome(5, "a".repeat(501)).validate().unwrap_err(),
+            AppError::InvalidArgument("the reflection must be at most 500 characters")
+        );
+
+        let dir = temporary_dir("outcome-elapsed");
+        let storage = Storage::open(&dir).unwrap();
+        let mut state: ActiveSessionState = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "phase": "paused",
+            "sessionGoal": "Synthetic goal",
+            "sessionStartedAt": null,
+            "sessionDuration": 60,
+            "checkInInterval": 20,
+            "writeTime": 20,
+            "sessionTimeRemaining": 45 * 60 + 30,
+            "checkInTimeRemaining": 600,
+            "writeTimeRemaining": 0,
+            "checkInsCompleted": 0,
+            "skippedCheckIns": 0,
+            "lastCheckInWasSkipped": false,
+            "focusShieldActive": false,
+            "focusShieldUntil": null,
+            "recoveryReason": null
+        }))
+        .unwrap();
+        let finished = outcome(8, String::new());
+        assert_eq!(
+            entry(&storage, &state, &finished, Utc::now()).unwrap_err(),
+            AppError::InvalidArgument("the session has not started")
+        );
+
+        // Three hours of wall time, most of it paused: only the 14.5 minutes
+        // the session timer ran count.
+        let started = DateTime::from_timestamp_millis(1_741_000_000_000).unwrap();
+        state.session_started_at = Some(started.timestamp_millis());
+        let record = entry(
+            &storage,
+            &state,
+            &finished,
+            started + chrono::Duration::hours(3),
+        )
+        .unwrap();
+        assert_eq!(record["details"]["totals"]["elapsed_minutes"], 14);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! The project registry: the projects sessions and check-ins can be filed
+//! under, stored next to settings in `projects.json`.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+
+use crate::{
+    error::AppError,
+    storage::{self, Storage},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    /// Short stable name recorded in the journal, e.g. "acme-redesign".
+    pub id: String,
+    pub name: String,
+    /// `#rrggbb`, used to tell projects apart in the UI.
+    pub color: String,
+    /// Archived projects are no longer offered for new sessions but keep
+    /// their history and stats.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+impl Project {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a project id that is not letters, digits, '-' or '_'",
+            });
+        }
+        if self.name.trim().is_empty() {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a project without a name",
+            });
+        }
+        let hex = self.color.strip_prefix('#').unwrap_or_default();
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a project color that is not #rrggbb",
+            });
+        }
+        Ok(())
+    }
+}
+
+pub fn load(storage: &Storage) -> Result<Vec<Project>, AppError> {
+    let _lock = lock_projects(storage)?;
+    load_locked(storage)
+}
+
+fn load_locked(storage: &Storage) -> Result<Vec<Project>, AppError> {
+    let path = storage.projects_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read(path).map_err(|e| AppError::io("Failed to read projects", e))?;
+    serde_json::from_slice(&data).map_err(AppError::settings_corrupt)
+}
+
+/// Add a project, or replace the one with the same id.
+pub fn save(storage: &Storage, project: Project) -> Result<(), AppError> {
+    project.validate()?;
+    let _lock = lock_projects(storage)?;
+    let mut projects = load_locked(storage)?;
+    match projects.iter_mut().find(|saved| saved.id == project.id) {
+        Some(saved) => *saved = project,
+        None => projects.push(project),
+    }
+    write(storage, &projects)
+}
+
+pub fn set_archived(storage: &Storage, id: &str, archived: bool) -> Result<(), AppError> {
+    let _lock = lock_projects(storage)?;
+    let mut projects = load_locked(storage)?;
+    let project = projects
+        .iter_mut()
+        .find(|project| project.id == id)
+        .ok_or(AppError::InvalidArgument("no project has that id"))?;
+    project.archived = archived;
+    write(storage, &projects)
+}
+
+fn write(storage: &Storage, projects: &[Project]) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(projects).map_err(AppError::settings_corrupt)?;
+    storage::write_atomically(&storage.projects_path(), &data)
+}
+
+/// Serializes the read-modify-write of the registry between the app and the CLI.
+fn lock_projects(storage: &Storage) -> Result<File, AppError> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(storage.projects_path().with_extension("lock"))
+        .map_err(|e| AppError::io("Failed to open projects lock", e))?;
+    lock.lock()
+        .map_err(|e| AppError::io("Failed to lock projects", e))?;
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn projects_are_saved_archived_and_validated() {
+        let dir = temporary_dir("projects");
+        let storage = Storage::open(&dir).unwrap();
+        let acme = Project {
+            id: "acme".to_string(),
+            name: "Acme redesign".to_string(),
+            color: "#3366cc".to_string(),
+            archived: false,
+        };
+        save(&storage, acme.clone()).unwrap();
+        set_archived(&storage, "acme", true).unwrap();
+        assert_eq!(
+            load(&storage).unwrap(),
+            vec![Project {
+                archived: true,
+                ..acme.clone()
+            }]
+        );
+        assert!(set_archived(&storage, "globex", true).is_err());
+
+        for invalid in [
+            Project {
+                color: "blue".to_string(),
+                ..acme.clone()
+            },
+            Project {
+                name: " ".to_string(),
+                ..acme.clone()
+            },
+            Project {
+                id: "a/b".to_string(),
+                ..acme
+            },
+        ] {
+            assert!(save(&storage, invalid).is_err());
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validation_accepts_only_safe_ids_names_and_hex_colors() {
+        let project = |id: &str, name: &str, color: &str| Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: color.to_string(),
+            archived: false,
+        };
+        assert!(project("acme-redesign_2", "Acme", "#A0b1C2")
+            .validate()
+            .is_ok());
+        for invalid in [
+            project("", "Acme", "#a0b1c2"),
+            project("acme redesign", "Acme", "#a0b1c2"),
+            project("acmé", "Acme", "#a0b1c2"),
+            project("acme", "", "#a0b1c2"),
+            project("acme", "Acme", "a0b1c2"),
+            project("acme", "Acme", "#a0b1c"),
+            project("acme", "Acme", "#a0b1cg"),
+        ] {
+            assert_eq!(invalid.validate().unwrap_err().code(), "settings_invalid");
+        }
+    }
+
+    #[test]
+    fn saving_replaces_by_id_and_a_corrupt_registry_is_not_overwritten() {
+        let dir = temporary_dir("projects-replace");
+        let storage = Storage::open(&dir).unwrap();
+        let project = |id: &str, name: &str| Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: "#3366cc".to_string(),
+            archived: false,
+        };
+        save(&storage, project("acme", "Acme")).unwrap();
+        save(&storage, project("globex", "Globex")).unwrap();
+        save(&storage, project("acme", "Acme redesign")).unwrap();
+        assert_eq!(
+            load(&storage).unwrap(),
+            [
+                project("acme", "Acme redesign"),
+                project("globex", "Globex")
+            ]
+        );
+
+        let before = fs::read(storage.projects_path()).unwrap();
+        assert_eq!(
+            set_archived(&storage, "initech", true).unwrap_err(),
+            AppError::InvalidArgument("no project has that id")
+        );
+        assert_eq!(fs::read(storage.projects_path()).unwrap(), before);
+
+        fs::write(storage.projects_path(), "[{").unwrap();
+        assert_eq!(load(&storage).unwrap_err().code(), "settings_corrupt");
+        assert!(save(&storage, project("initech", "Initech")).is_err());
+        assert!(set_archived(&storage, "acme", true).is_err());
+        assert_eq!(fs::read_to_string(storage.projects_path()).unwrap(), "[{");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves_keep_every_project_and_leave_no_temporary_files() {
+        let dir = temporary_dir("projects-concurrent");
+        let storage = Storage::open(&dir).unwrap();
+        let writers: Vec<_> = (0..8)
+            .map(|index| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    save(
+                        &storage,
+                        Project {
+                            id: format!("client-{index}"),
+                            name: format!("Client {index}"),
+                            color: "#3366cc".to_string(),
+                            archived: false,
+                        },
+                    )
+                    .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(load(&storage).unwrap().len(), 8);
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().contains(".tmp-"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
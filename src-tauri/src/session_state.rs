@@ -1,14 +1,29 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     fs::{self, OpenOptions},
     io::Write,
-    path::{Path, PathBuf},
-    time::{SystemTime, UNIX_EPOCH},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    adaptive::AdaptiveInterval,
+    clock::Clock,
+    error::AppError,
+    logs::{self, PromptOutcome},
+    settings::Settings,
+    stats::{self, StatusKind},
+    storage::Storage,
 };
-use tauri::{AppHandle, Manager};
 
 const ACTIVE_SESSION_STATE_VERSION: u32 = 1;
-const ACTIVE_SESSION_FILE_NAME: &str = "active_session.json";
+/// Temporary snapshots younger than this may belong to a save in flight.
+pub(crate) const ORPHAN_MIN_AGE: Duration = Duration::from_secs(60);
+/// Prior snapshots kept in the history ring, at most one per spacing interval.
+const HISTORY_LIMIT: usize = 20;
+const HISTORY_SPACING: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -17,14 +32,117 @@ pub enum SessionPhase {
     Writing,
     Paused,
     Interrupted,
+    /// A running Pomodoro break; check-ins are suspended.
+    Break,
 }
 
 impl SessionPhase {
+    /// Timers are counting down: focus time, a pending prompt, or a break.
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Active | Self::Writing | Self::Break)
+    }
+
     fn needs_restart_recovery(&self) -> bool {
-        matches!(self, Self::Active | Self::Writing)
+        self.is_running()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn is_break(self) -> bool {
+        matches!(self, Self::ShortBreak | Self::LongBreak)
+    }
+}
+
+/// Where a Pomodoro session is in its work/break cycle. The lengths are
+/// copied from settings when the session starts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroProgress {
+    pub phase: PomodoroPhase,
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    pub cycles_before_long_break: u32,
+    pub auto_start: bool,
+    /// Work phases finished so far.
+    pub cycles_completed: u32,
+    pub phase_time_remaining: u64,
+    /// Break time taken so far, kept apart from work time.
+    pub break_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SamplingDistribution {
+    Uniform,
+    Exponential,
+}
+
+/// Randomized check-in times for experience sampling. The webview draws each
+/// delay, or [`CheckInSampling::draw_delay`] for answers recorded without it;
+/// the generator state is saved here so a resumed session continues the same
+/// schedule.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckInSampling {
+    pub distribution: SamplingDistribution,
+    pub min_gap_seconds: u64,
+    /// 32 bits, so it round-trips through JavaScript numbers exactly.
+    pub rng_state: u32,
+    /// The delay drawn for the pending check-in.
+    pub last_delay_seconds: u64,
+}
+
+impl CheckInSampling {
+    /// Draw the wait before the next check-in, exactly as the webview's
+    /// `sampleCheckInDelay` does, so either side can continue the schedule.
+    pub fn draw_delay(&mut self, mean_seconds: u64) -> u64 {
+        let (state, value) = next_random(self.rng_state);
+        let mean = mean_seconds as f64;
+        let gap = (self.min_gap_seconds as f64).min(mean);
+        let spread = mean - gap;
+        let delay = match self.distribution {
+            SamplingDistribution::Exponential => {
+                (gap - spread * (1.0 - value).ln()).min(4.0 * mean)
+            }
+            SamplingDistribution::Uniform => gap + value * 2.0 * spread,
+        };
+        // `Math.round`, which rounds halves up.
+        let delay = (delay + 0.5).floor() as u64;
+        self.rng_state = state;
+        self.last_delay_seconds = delay;
+        delay
     }
 }
 
+/// Mulberry32, matching `nextRandom` in the webview's timer-state module.
+fn next_random(state: u32) -> (u32, f64) {
+    let next = state.wrapping_add(0x6D2B_79F5);
+    let mut t = next;
+    t = (t ^ (t >> 15)).wrapping_mul(t | 1);
+    t ^= t.wrapping_add((t ^ (t >> 7)).wrapping_mul(t | 61));
+    (next, f64::from(t ^ (t >> 14)) / 4_294_967_296.0)
+}
+
+/// One step of the session goal's checklist.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Subtask {
+    /// Stable across reordering; the list order is the checklist order.
+    pub id: u32,
+    pub text: String,
+    /// Unix milliseconds when the item was checked off.
+    pub completed_at: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ActiveSessionState {
@@ -44,68 +162,587 @@ pub struct ActiveSessionState {
     pub focus_shield_active: bool,
     pub focus_shield_until: Option<i64>,
     pub recovery_reason: Option<String>,
+    /// When the running app last saved this state; restart recovery measures
+    /// downtime from it. Absent in states saved before it was recorded.
+    #[serde(default)]
+    pub last_heartbeat_at: Option<i64>,
+    /// Present when the session runs in Pomodoro mode.
+    #[serde(default)]
+    pub pomodoro: Option<PomodoroProgress>,
+    /// The session template the session was started from.
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Present when the check-in interval adapts to the answers; its
+    /// `interval` then replaces `check_in_interval`.
+    #[serde(default)]
+    pub adaptive: Option<AdaptiveInterval>,
+    /// Present when check-in times are randomized.
+    #[serde(default)]
+    pub sampling: Option<CheckInSampling>,
+    /// When the pending check-in prompt appeared, while `phase` is writing.
+    #[serde(default)]
+    pub prompt_shown_at: Option<i64>,
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+    /// Id of the project in the registry the session is for.
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl ActiveSessionState {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), AppError> {
         if self.version != ACTIVE_SESSION_STATE_VERSION {
-            return Err(format!(
-                "Unsupported active session state version: {}",
-                self.version
-            ));
+            return Err(AppError::StateVersionUnsupported(self.version));
         }
         if self.session_duration == 0 || self.check_in_interval == 0 || self.write_time == 0 {
-            return Err("Active session state contains an invalid timer setting".to_string());
+            return Err(AppError::StateInvalid {
+                reason: "contains an invalid timer setting",
+            });
+        }
+        if let Some(pomodoro) = &self.pomodoro {
+            if pomodoro.work_minutes == 0
+                || pomodoro.short_break_minutes == 0
+                || pomodoro.long_break_minutes == 0
+                || pomodoro.cycles_before_long_break == 0
+            {
+                return Err(AppError::StateInvalid {
+                    reason: "contains an invalid Pomodoro setting",
+                });
+            }
+        }
+        if self
+            .adaptive
+            .as_ref()
+            .is_some_and(|adaptive| !adaptive.is_valid())
+        {
+            return Err(AppError::StateInvalid {
+                reason: "contains an invalid adaptive check-in interval",
+            });
+        }
+        let mut ids = HashSet::new();
+        if !self
+            .subtasks
+            .iter()
+            .all(|subtask| !subtask.text.trim().is_empty() && ids.insert(subtask.id))
+        {
+            return Err(AppError::StateInvalid {
+                reason: "contains an invalid subtask checklist",
+            });
+        }
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(AppError::StateInvalid {
+                reason: "contains an empty tag",
+            });
+        }
+        if self.phase == SessionPhase::Break && !self.on_break() {
+            return Err(AppError::StateInvalid {
+                reason: "is on a break outside a Pomodoro break phase",
+            });
         }
         Ok(())
     }
+
+    /// Whether the session is in a Pomodoro break, running or paused.
+    pub fn on_break(&self) -> bool {
+        self.pomodoro
+            .as_ref()
+            .is_some_and(|pomodoro| pomodoro.phase.is_break())
+    }
+
+    /// The one rule for check-ins from the prompt, the control socket and
+    /// the CLI: a known answer, while the session is running outside a break.
+    pub fn accepts_check_in(&self, status: &str) -> Result<(), AppError> {
+        if stats::status_kind(status).is_none() {
+            return Err(AppError::InvalidCheckIn {
+                reason: "the status is not one of the check-in answers",
+            });
+        }
+        if self.on_break() {
+            return Err(AppError::InvalidCheckIn {
+                reason: "check-ins are suspended during breaks",
+            });
+        }
+        if !matches!(self.phase, SessionPhase::Active | SessionPhase::Writing) {
+            return Err(AppError::InvalidCheckIn {
+                reason: "the session is not running",
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ActiveSessionState {
+    /// Move every saved wall-clock timestamp by `shift_ms`, after the system
+    /// clock itself was moved by that much.
+    pub fn shift_timestamps(&mut self, shift_ms: i64) {
+        let shift = |at: &mut Option<i64>| *at = at.map(|at| at + shift_ms);
+        shift(&mut self.session_started_at);
+        shift(&mut self.focus_shield_until);
+        shift(&mut self.last_heartbeat_at);
+        shift(&mut self.prompt_shown_at);
+        for subtask in &mut self.subtasks {
+            shift(&mut subtask.completed_at);
+        }
+    }
+
+    /// Advance the session past an answered check-in, as the webview does
+    /// when its prompt is answered: the prompt closes, the interval adapts
+    /// and the countdown to the next check-in restarts. Build the journal
+    /// line with [`Self::check_in_entry`] first.
+    pub fn apply_answer(&mut self, status: &str) {
+        // A pending prompt already counted this check-in.
+        if self.phase == SessionPhase::Writing {
+            self.phase = SessionPhase::Active;
+        } else {
+            self.check_ins_completed += 1;
+        }
+        self.prompt_shown_at = None;
+        self.write_time_remaining = 0;
+        if stats::status_kind(status) == Some(StatusKind::Skipped) {
+            self.skipped_check_ins += 1;
+            self.last_check_in_was_skipped = true;
+        } else {
+            self.last_check_in_was_skipped = false;
+        }
+        if let Some(adaptive) = &self.adaptive {
+            self.adaptive = Some(adaptive.after_answer(status).0);
+        }
+        let interval = self
+            .adaptive
+            .as_ref()
+            .map_or(self.check_in_interval, |adaptive| adaptive.interval);
+        let mean_seconds = u64::from(interval) * 60;
+        self.check_in_time_remaining = match &mut self.sampling {
+            Some(sampling) => sampling.draw_delay(mean_seconds),
+            None => mean_seconds,
+        };
+    }
+
+    /// Build a journal line for a check-in answered outside the prompt, e.g.
+    /// from the control socket or the CLI.
+    pub fn check_in_entry(
+        &self,
+        status: &str,
+        note: Option<&str>,
+        timestamp: DateTime<Utc>,
+    ) -> serde_json::Value {
+        // A pending prompt already counted this check-in.
+        let check_in_number = if self.phase == SessionPhase::Writing {
+            self.check_ins_completed
+        } else {
+            self.check_ins_completed + 1
+        };
+        serde_json::json!({
+            "timestamp": timestamp.to_rfc3339(),
+            "session_goal": self.session_goal,
+            "reported_status": status,
+            "notes": note.unwrap_or_default(),
+            "session_duration_setting": self.session_duration,
+            "check_in_interval_setting": self.check_in_interval,
+            "write_time_setting": self.write_time,
+            "check_in_number": check_in_number,
+            "auto_submitted": false,
+            "focus_shield_active": self.focus_shield_active,
+            "template_id": self.template_id,
+            "project": self.project,
+            "tags": self.tags,
+            "interval_adjustment": self
+                .adaptive
+                .as_ref()
+                .map(|adaptive| adaptive.after_answer(status).1),
+            "sampled_delay_seconds": self
+                .sampling
+                .as_ref()
+                .map(|sampling| sampling.last_delay_seconds),
+            "prompt_outcome": if stats::status_kind(status) == Some(StatusKind::Skipped) {
+                PromptOutcome::Skipped
+            } else {
+                PromptOutcome::Answered
+            },
+            "response_latency_ms": self
+                .prompt_shown_at
+                .filter(|_| self.phase == SessionPhase::Writing)
+                .map(|shown_at| (timestamp.timestamp_millis() - shown_at).max(0)),
+        })
+    }
+
+    /// Build a journal line for a check-in prompt the focus shield held back.
+    pub fn deferred_prompt_entry(
+        &self,
+        deferred_until: i64,
+        timestamp: DateTime<Utc>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": timestamp.to_rfc3339(),
+            "record_type": logs::PROMPT_DEFERRED_RECORD,
+            "session_goal": self.session_goal,
+            "template_id": self.template_id,
+            "project": self.project,
+            "tags": self.tags,
+            "check_in_number": self.check_ins_completed + 1,
+            "focus_shield_active": self.focus_shield_active,
+            "prompt_outcome": PromptOutcome::Deferred,
+            "details": {
+                "deferred_until": deferred_until,
+                "reason": "focus_shield",
+            },
+        })
+    }
+
+    /// Build a journal line for a finished Pomodoro phase. Work and break
+    /// phases are separate records so break time never counts as work.
+    pub fn pomodoro_phase_entry(
+        &self,
+        finished: PomodoroPhase,
+        seconds: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<serde_json::Value, AppError> {
+        let pomodoro = self.pomodoro.as_ref().ok_or(AppError::InvalidArgument(
+            "the session is not in Pomodoro mode",
+        ))?;
+        Ok(serde_json::json!({
+            "timestamp": timestamp.to_rfc3339(),
+            "record_type": logs::POMODORO_PHASE_RECORD,
+            "session_goal": self.session_goal,
+            "template_id": self.template_id,
+            "project": self.project,
+            "tags": self.tags,
+            "details": {
+                "phase": finished,
+                "seconds": seconds,
+                "cycle": pomodoro.cycles_completed,
+                "break_seconds": pomodoro.break_seconds,
+            },
+        }))
+    }
+
+    /// Build a journal line for a checked-off subtask, stamped with the time
+    /// it was checked off.
+    pub fn subtask_completed_entry(&self, id: u32) -> Result<serde_json::Value, AppError> {
+        let position = self
+            .subtasks
+            .iter()
+            .position(|subtask| subtask.id == id)
+            .ok_or(AppError::InvalidArgument("no subtask has that id"))?;
+        let subtask = &self.subtasks[position];
+        let completed_at = subtask
+            .completed_at
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or(AppError::InvalidArgument("the subtask is not checked off"))?;
+        Ok(serde_json::json!({
+            "timestamp": completed_at.to_rfc3339(),
+            "record_type": logs::SUBTASK_COMPLETED_RECORD,
+            "session_goal": self.session_goal,
+            "template_id": self.template_id,
+            "project": self.project,
+            "tags": self.tags,
+            "details": {
+                "subtask_id": subtask.id,
+                "text": subtask.text,
+                "position": position + 1,
+                "completed": self.subtasks.iter().filter(|s| s.completed_at.is_some()).count(),
+                "total": self.subtasks.len(),
+            },
+        }))
+    }
+
+    fn recovery_entry(
+        &self,
+        previous_phase: &SessionPhase,
+        decision: RecoveryDecision,
+        downtime: Option<Duration>,
+        timestamp: DateTime<Utc>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": timestamp.to_rfc3339(),
+            "record_type": logs::SESSION_RECOVERY_RECORD,
+            "session_goal": self.session_goal,
+            "template_id": self.template_id,
+            "project": self.project,
+            "tags": self.tags,
+            "details": {
+                "decision": decision,
+                "previous_phase": previous_phase,
+                "downtime_seconds": downtime.map(|downtime| downtime.as_secs()),
+                "session_time_remaining": self.session_time_remaining,
+            },
+        })
+    }
+}
+
+/// How restart recovery treats a session the app was running when it stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Resume without asking after less downtime than this; zero always asks.
+    pub auto_resume_within: Duration,
+    pub subtract_downtime: bool,
+    /// Discard sessions whose last heartbeat is at least this old; zero keeps them.
+    pub discard_after: Duration,
+}
+
+impl RecoveryPolicy {
+    /// Always ask, and keep the session exactly as it was saved.
+    pub const MANUAL: Self = Self {
+        auto_resume_within: Duration::ZERO,
+        subtract_downtime: false,
+        discard_after: Duration::ZERO,
+    };
+}
+
+impl From<&Settings> for RecoveryPolicy {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            auto_resume_within: Duration::from_secs(u64::from(settings.auto_resume_minutes) * 60),
+            subtract_downtime: settings.subtract_downtime,
+            discard_after: Duration::from_secs(
+                u64::from(settings.discard_session_after_hours) * 3600,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecoveryDecision {
+    Resumed,
+    Interrupted,
+    Discarded,
+}
+
+/// The outcome of restart recovery: the state to continue with, if any, and
+/// the journal record describing the decision when one was made.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recovery {
+    pub state: Option<ActiveSessionState>,
+    pub journal_entry: Option<serde_json::Value>,
+}
+
+/// A temporary snapshot left behind by a crash between write and rename that
+/// holds a valid state newer than the main snapshot.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedState {
+    pub file_name: String,
+    pub written_at: DateTime<Utc>,
+    pub state: ActiveSessionState,
+}
+
+/// A prior snapshot from the history ring, summarized for the recovery UI.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSummary {
+    pub id: String,
+    pub saved_at: DateTime<Utc>,
+    pub phase: SessionPhase,
+    pub session_goal: String,
+    pub session_time_remaining: u64,
+    pub check_ins_completed: u32,
+}
+
+/// What a session got through, shown when it ends.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReport {
+    pub session_goal: String,
+    pub check_ins: Vec<logs::SessionEntry>,
+    /// In the order they were checked off.
+    pub finished_subtasks: Vec<Subtask>,
+    /// In checklist order.
+    pub open_subtasks: Vec<Subtask>,
+}
+
+/// The session's checklist alongside its check-in timeline.
+pub fn report(storage: &Storage, state: &ActiveSessionState) -> Result<SessionReport, AppError> {
+    let check_ins = match state
+        .session_started_at
+        .and_then(DateTime::from_timestamp_millis)
+    {
+        Some(start) => logs::read_since(storage, start)?,
+        None => Vec::new(),
+    };
+    let (mut finished_subtasks, open_subtasks): (Vec<_>, Vec<_>) = state
+        .subtasks
+        .iter()
+        .cloned()
+        .partition(|subtask| subtask.completed_at.is_some());
+    finished_subtasks.sort_by_key(|subtask| subtask.completed_at);
+    Ok(SessionReport {
+        session_goal: state.session_goal.clone(),
+        check_ins,
+        finished_subtasks,
+        open_subtasks,
+    })
+}
+
+/// Save the running app's state, stamping it as the latest heartbeat.
+pub fn save(
+    storage: &Storage,
+    mut state: ActiveSessionState,
+    clock: &dyn Clock,
+) -> Result<(), AppError> {
+    state.last_heartbeat_at = Some(clock.now().timestamp_millis());
+    save_to_path(&storage.state_path(), &state, clock)
+}
+
+/// Apply `policy` to the saved state and journal the decision.
+pub fn recover(
+    storage: &Storage,
+    policy: RecoveryPolicy,
+    clock: &dyn Clock,
+) -> Result<Option<ActiveSessionState>, AppError> {
+    let recovery = recover_from_path(&storage.state_path(), policy, clock)?;
+    if let Some(entry) = recovery.journal_entry {
+        if let Err(error) = logs::append_entry(storage, &entry.to_string()) {
+            log::warn!("Failed to journal the session recovery decision: {error}");
+        }
+    }
+    Ok(recovery.state)
 }
 
-pub fn state_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let path = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-    Ok(path.join(ACTIVE_SESSION_FILE_NAME))
+/// Read, change and write back the saved state under the state lock, so a
+/// concurrent save cannot be lost. `change` returns whether to write.
+pub fn update(
+    storage: &Storage,
+    clock: &dyn Clock,
+    change: impl FnOnce(&mut ActiveSessionState) -> Result<bool, AppError>,
+) -> Result<Option<ActiveSessionState>, AppError> {
+    let path = storage.state_path();
+    let _lock = lock_state(&path)?;
+    let Some(mut state) = load_from_path(&path)? else {
+        return Ok(None);
+    };
+    if !change(&mut state)? {
+        return Ok(None);
+    }
+    state.validate()?;
+    let serialized = serde_json::to_vec_pretty(&state).map_err(AppError::state_corrupt)?;
+    write_locked(&path, &serialized, clock)?;
+    Ok(Some(state))
 }
 
-pub fn save(app: &AppHandle, state: ActiveSessionState) -> Result<(), String> {
-    save_to_path(&state_path(app)?, &state)
+/// Journal a check-in while the app is not running and advance the saved
+/// session past it, as the app would.
+///
+/// The state is written first and put back if the journal append fails, so
+/// the two never disagree about the check-in.
+pub fn record_check_in(
+    storage: &Storage,
+    status: &str,
+    note: Option<&str>,
+    clock: &dyn Clock,
+) -> Result<(), AppError> {
+    let path = storage.state_path();
+    let _lock = lock_state(&path)?;
+    let original = load_from_path(&path)?.ok_or(AppError::NoActiveSession)?;
+    original.accepts_check_in(status)?;
+    let entry = original.check_in_entry(status, note, clock.now());
+    let mut answered = original.clone();
+    answered.apply_answer(status);
+    answered.validate()?;
+    let serialized = serde_json::to_vec_pretty(&answered).map_err(AppError::state_corrupt)?;
+    write_locked(&path, &serialized, clock)?;
+
+    if let Err(error) = logs::append_entry(storage, &entry.to_string()) {
+        let serialized = serde_json::to_vec_pretty(&original).map_err(AppError::state_corrupt)?;
+        if let Err(rollback) = write_locked(&path, &serialized, clock) {
+            log::warn!("Failed to restore the session after a failed check-in: {rollback}");
+        }
+        return Err(error);
+    }
+    Ok(())
 }
 
-pub fn recover(app: &AppHandle) -> Result<Option<ActiveSessionState>, String> {
-    recover_from_path(&state_path(app)?)
+pub fn load(storage: &Storage) -> Result<Option<ActiveSessionState>, AppError> {
+    load_from_path(&storage.state_path())
 }
 
-pub fn clear(app: &AppHandle) -> Result<(), String> {
-    let path = state_path(app)?;
+pub fn clear(storage: &Storage) -> Result<(), AppError> {
+    let path = storage.state_path();
+    // A check-in recorded from the CLI must not write the state back after it is gone.
+    let _lock = lock_state(&path)?;
     if path.exists() {
-        fs::remove_file(path).map_err(|e| format!("Failed to clear active session state: {e}"))?;
+        fs::remove_file(path)
+            .map_err(|e| AppError::io("Failed to clear active session state", e))?;
     }
     Ok(())
 }
 
-pub fn save_to_path(path: &Path, state: &ActiveSessionState) -> Result<(), String> {
+/// Valid prior snapshots, newest first.
+pub fn snapshot_history(storage: &Storage) -> Result<Vec<SnapshotSummary>, AppError> {
+    snapshot_history_at(&storage.state_path())
+}
+
+/// Replace the current state with a prior snapshot. The state being replaced
+/// goes into the history too, so a restore can itself be undone.
+pub fn restore_snapshot(
+    storage: &Storage,
+    id: &str,
+    clock: &dyn Clock,
+) -> Result<ActiveSessionState, AppError> {
+    restore_snapshot_at(&storage.state_path(), id, clock)
+}
+
+/// Startup housekeeping for `active_session.tmp-<nanos>` files.
+///
+/// Deletes every orphan older than a minute except the newest valid one that
+/// is newer than the main snapshot, which is returned so the user can choose
+/// to restore it with [`resolve_orphan`].
+pub fn clean_up_orphans(
+    storage: &Storage,
+    clock: &dyn Clock,
+) -> Result<Option<OrphanedState>, AppError> {
+    clean_up_orphans_at(&storage.state_path(), clock)
+}
+
+/// Restore the orphan over the main snapshot, or discard it.
+pub fn resolve_orphan(
+    storage: &Storage,
+    file_name: &str,
+    restore: bool,
+) -> Result<Option<ActiveSessionState>, AppError> {
+    resolve_orphan_at(&storage.state_path(), file_name, restore)
+}
+
+pub fn save_to_path(
+    path: &Path,
+    state: &ActiveSessionState,
+    clock: &dyn Clock,
+) -> Result<(), AppError> {
     state.validate()?;
-    let serialized = serde_json::to_vec_pretty(state)
-        .map_err(|e| format!("Failed to serialize active session state: {e}"))?;
-    let parent = path
-        .parent()
-        .ok_or_else(|| "Active session state path has no parent directory".to_string())?;
+    let serialized = serde_json::to_vec_pretty(state).map_err(AppError::state_corrupt)?;
+    let parent = path.parent().ok_or(AppError::StateInvalid {
+        reason: "path has no parent directory",
+    })?;
     fs::create_dir_all(parent)
-        .map_err(|e| format!("Failed to create active session state directory: {e}"))?;
+        .map_err(|e| AppError::io("Failed to create active session state directory", e))?;
+
+    let _lock = lock_state(path)?;
+    write_locked(path, &serialized, clock)
+}
 
-    let temporary_path = path.with_extension(format!("tmp-{}", unique_suffix()));
-    let result = (|| -> Result<(), String> {
+/// Replace the state file; the caller holds [`lock_state`].
+fn write_locked(path: &Path, serialized: &[u8], clock: &dyn Clock) -> Result<(), AppError> {
+    let parent = path.parent().ok_or(AppError::StateInvalid {
+        reason: "path has no parent directory",
+    })?;
+    if let Err(error) = archive_snapshot(path, false) {
+        log::warn!("Failed to archive prior active session state: {error}");
+    }
+    let temporary_path = path.with_extension(format!("tmp-{}", unique_suffix(clock)));
+    let result = (|| -> Result<(), AppError> {
         let mut file = OpenOptions::new()
             .write(true)
             .create_new(true)
             .open(&temporary_path)
-            .map_err(|e| format!("Failed to create active session state file: {e}"))?;
-        file.write_all(&serialized)
-            .map_err(|e| format!("Failed to write active session state: {e}"))?;
+            .map_err(|e| AppError::io("Failed to create active session state file", e))?;
+        file.write_all(serialized)
+            .map_err(|e| AppError::io("Failed to write active session state", e))?;
         file.sync_all()
-            .map_err(|e| format!("Failed to sync active session state: {e}"))?;
+            .map_err(|e| AppError::io("Failed to sync active session state", e))?;
         fs::rename(&temporary_path, path)
-            .map_err(|e| format!("Failed to finalize active session state: {e}"))?;
+            .map_err(|e| AppError::io("Failed to finalize active session state", e))?;
         sync_parent_directory(parent)?;
         Ok(())
     })();
@@ -116,48 +753,358 @@ pub fn save_to_path(path: &Path, state: &ActiveSessionState) -> Result<(), Strin
     result
 }
 
-pub fn recover_from_path(path: &Path) -> Result<Option<ActiveSessionState>, String> {
+/// Read the saved state without applying restart recovery.
+pub fn load_from_path(path: &Path) -> Result<Option<ActiveSessionState>, AppError> {
     if !path.exists() {
         return Ok(None);
     }
 
-    let data = fs::read(path).map_err(|e| format!("Failed to read active session state: {e}"))?;
-    let mut state: ActiveSessionState = serde_json::from_slice(&data)
-        .map_err(|e| format!("Failed to parse active session state: {e}"))?;
+    let data =
+        fs::read(path).map_err(|e| AppError::io("Failed to read active session state", e))?;
+    let state: ActiveSessionState =
+        serde_json::from_slice(&data).map_err(AppError::state_corrupt)?;
     state.validate()?;
+    Ok(Some(state))
+}
+
+pub fn recover_from_path(
+    path: &Path,
+    policy: RecoveryPolicy,
+    clock: &dyn Clock,
+) -> Result<Recovery, AppError> {
+    let now = clock.now();
+    let Some(mut state) = load_from_path(path)? else {
+        return Ok(Recovery::default());
+    };
 
-    if state.phase.needs_restart_recovery() {
-        state.phase = SessionPhase::Interrupted;
-        state.recovery_reason =
-            Some("Application restarted while this session was active".to_string());
-        save_to_path(path, &state)?;
+    // Without a heartbeat the downtime is unknown, so only the manual path applies.
+    let downtime = state
+        .last_heartbeat_at
+        .and_then(DateTime::from_timestamp_millis)
+        .map(|heartbeat| (now - heartbeat).to_std().unwrap_or_default());
+    let expired = downtime.is_some_and(|downtime| {
+        !policy.discard_after.is_zero() && downtime >= policy.discard_after
+    });
+    let decision = if state.phase == SessionPhase::Paused {
+        None
+    } else if expired {
+        Some(RecoveryDecision::Discarded)
+    } else if !state.phase.needs_restart_recovery() {
+        None
+    } else if downtime.is_some_and(|downtime| downtime < policy.auto_resume_within) {
+        Some(RecoveryDecision::Resumed)
+    } else {
+        Some(RecoveryDecision::Interrupted)
+    };
+    let Some(decision) = decision else {
+        return Ok(Recovery {
+            state: Some(state),
+            journal_entry: None,
+        });
+    };
+
+    let previous_phase = state.phase.clone();
+    let minutes = downtime.unwrap_or_default().as_secs() / 60;
+    match decision {
+        RecoveryDecision::Resumed => {
+            // The open prompt was not on screen while the app was down.
+            let downtime_ms = downtime.unwrap_or_default().as_millis() as i64;
+            state.prompt_shown_at = state.prompt_shown_at.map(|at| at + downtime_ms);
+            state.recovery_reason = Some(format!(
+                "Resumed automatically after {minutes} min of downtime"
+            ));
+        }
+        RecoveryDecision::Interrupted => {
+            state.phase = SessionPhase::Interrupted;
+            state.recovery_reason = match downtime {
+                Some(downtime) if policy.subtract_downtime => {
+                    state.session_time_remaining = state
+                        .session_time_remaining
+                        .saturating_sub(downtime.as_secs());
+                    Some(format!(
+                        "Application restarted while this session was active; {minutes} min of downtime subtracted"
+                    ))
+                }
+                _ => Some("Application restarted while this session was active".to_string()),
+            };
+            save_to_path(path, &state, clock)?;
+        }
+        RecoveryDecision::Discarded => {
+            fs::remove_file(path)
+                .map_err(|e| AppError::io("Failed to discard expired session state", e))?;
+        }
     }
+    log::info!("Restart recovery: {decision:?} after {minutes} min of downtime");
 
-    Ok(Some(state))
+    let journal_entry = Some(state.recovery_entry(&previous_phase, decision, downtime, now));
+    Ok(Recovery {
+        state: (decision != RecoveryDecision::Discarded).then_some(state),
+        journal_entry,
+    })
+}
+
+pub fn snapshot_history_at(path: &Path) -> Result<Vec<SnapshotSummary>, AppError> {
+    let dir = history_dir(path);
+    let mut snapshots = Vec::new();
+    for id in history_ids(&dir)?.into_iter().rev() {
+        // Snapshots are validated on listing so the UI never offers a bad one.
+        let Ok(Some(state)) = load_from_path(&dir.join(format!("{id}.json"))) else {
+            continue;
+        };
+        snapshots.push(SnapshotSummary {
+            id: id.to_string(),
+            saved_at: DateTime::from_timestamp_millis(id).unwrap_or_default(),
+            phase: state.phase,
+            session_goal: state.session_goal,
+            session_time_remaining: state.session_time_remaining,
+            check_ins_completed: state.check_ins_completed,
+        });
+    }
+    Ok(snapshots)
 }
 
-fn unique_suffix() -> u128 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_nanos())
-        .unwrap_or_default()
+pub fn restore_snapshot_at(
+    path: &Path,
+    id: &str,
+    clock: &dyn Clock,
+) -> Result<ActiveSessionState, AppError> {
+    let id: i64 = id
+        .parse()
+        .map_err(|_| AppError::InvalidArgument("not a snapshot id"))?;
+    let state = load_from_path(&history_dir(path).join(format!("{id}.json")))?
+        .ok_or(AppError::InvalidArgument("no snapshot with that id"))?;
+    save_to_path(path, &state, clock)?;
+    log::info!("Restored active session state from snapshot {id}");
+    Ok(state)
+}
+
+fn history_dir(path: &Path) -> std::path::PathBuf {
+    path.with_extension("history")
+}
+
+/// Snapshot ids are the millisecond timestamps they were written at, sorted
+/// oldest first.
+fn history_ids(dir: &Path) -> Result<Vec<i64>, AppError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(AppError::io("Failed to list session state history", error)),
+    };
+    let mut ids: Vec<i64> = entries
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// Copy the current snapshot into the history ring before it is replaced.
+/// Routine saves skip it while the newest entry is more recent than the
+/// spacing interval; `always` archives regardless. Called with the state
+/// lock held.
+fn archive_snapshot(path: &Path, always: bool) -> Result<(), AppError> {
+    if !matches!(load_from_path(path), Ok(Some(_))) {
+        return Ok(());
+    }
+    let Some(written_at) = modified_at(path) else {
+        return Ok(());
+    };
+    let id = DateTime::<Utc>::from(written_at).timestamp_millis();
+    let dir = history_dir(path);
+    let mut ids = history_ids(&dir)?;
+    if !always
+        && ids
+            .last()
+            .is_some_and(|last| id - last < HISTORY_SPACING.as_millis() as i64)
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io("Failed to create session state history", e))?;
+    fs::copy(path, dir.join(format!("{id}.json")))
+        .map_err(|e| AppError::io("Failed to archive active session state", e))?;
+    ids.push(id);
+    for old in &ids[..ids.len().saturating_sub(HISTORY_LIMIT)] {
+        let _ = fs::remove_file(dir.join(format!("{old}.json")));
+    }
+    Ok(())
+}
+
+pub fn clean_up_orphans_at(
+    path: &Path,
+    clock: &dyn Clock,
+) -> Result<Option<OrphanedState>, AppError> {
+    let now = clock.system_time();
+    let Some(parent) = path.parent().filter(|parent| parent.exists()) else {
+        return Ok(None);
+    };
+    let _lock = lock_state(path)?;
+    let main_written_at = match load_from_path(path) {
+        Ok(Some(_)) => modified_at(path),
+        _ => None,
+    };
+
+    let mut newest: Option<(SystemTime, OrphanedState)> = None;
+    let mut stale = Vec::new();
+    let entries = fs::read_dir(parent)
+        .map_err(|e| AppError::io("Failed to list active session state directory", e))?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !is_orphan_name(path, &file_name) {
+            continue;
+        }
+        let orphan_path = entry.path();
+        let Some(written_at) = modified_at(&orphan_path) else {
+            continue;
+        };
+        if now.duration_since(written_at).unwrap_or_default() < ORPHAN_MIN_AGE {
+            continue;
+        }
+
+        let newer_than_main = main_written_at.is_none_or(|main| written_at > main);
+        let candidate = match load_from_path(&orphan_path) {
+            Ok(Some(state)) if newer_than_main => state,
+            _ => {
+                stale.push(orphan_path);
+                continue;
+            }
+        };
+        if let Some((previous, _)) = &newest {
+            if *previous >= written_at {
+                stale.push(orphan_path);
+                continue;
+            }
+        }
+        if let Some((_, previous)) = newest.replace((
+            written_at,
+            OrphanedState {
+                file_name,
+                written_at: written_at.into(),
+                state: candidate,
+            },
+        )) {
+            stale.push(parent.join(previous.file_name));
+        }
+    }
+
+    for orphan_path in &stale {
+        fs::remove_file(orphan_path)
+            .map_err(|e| AppError::io("Failed to remove orphaned session state file", e))?;
+    }
+    if !stale.is_empty() {
+        log::info!("Removed {} orphaned session state file(s)", stale.len());
+    }
+    Ok(newest.map(|(_, orphan)| orphan))
+}
+
+pub fn resolve_orphan_at(
+    path: &Path,
+    file_name: &str,
+    restore: bool,
+) -> Result<Option<ActiveSessionState>, AppError> {
+    if !is_orphan_name(path, file_name) {
+        return Err(AppError::InvalidArgument(
+            "not an orphaned session state file",
+        ));
+    }
+    let parent = path.parent().ok_or(AppError::StateInvalid {
+        reason: "path has no parent directory",
+    })?;
+    let orphan_path = parent.join(file_name);
+    let _lock = lock_state(path)?;
+    if !restore {
+        fs::remove_file(&orphan_path)
+            .map_err(|e| AppError::io("Failed to remove orphaned session state file", e))?;
+        return Ok(None);
+    }
+
+    let state = load_from_path(&orphan_path)?;
+    // The state being replaced stays restorable from the history.
+    archive_snapshot(path, true)?;
+    fs::rename(&orphan_path, path)
+        .map_err(|e| AppError::io("Failed to restore orphaned session state", e))?;
+    sync_parent_directory(parent)?;
+    log::info!("Restored active session state from an orphaned snapshot");
+    Ok(state)
+}
+
+/// Matches the names [`save_to_path`] gives its temporary files; the suffix
+/// is negative under a clock before the epoch.
+pub(crate) fn is_orphan_name(path: &Path, file_name: &str) -> bool {
+    let Some(stem) = path.file_stem() else {
+        return false;
+    };
+    file_name
+        .strip_prefix(&format!("{}.tmp-", stem.to_string_lossy()))
+        .map(|suffix| suffix.strip_prefix('-').unwrap_or(suffix))
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether an orphan is one [`clean_up_orphans_at`] would offer to restore:
+/// a valid state written after the main state, or with no valid main state.
+pub(crate) fn is_recoverable_orphan(path: &Path, orphan_path: &Path) -> bool {
+    let main_written_at = match load_from_path(path) {
+        Ok(Some(_)) => modified_at(path),
+        _ => None,
+    };
+    let Some(written_at) = modified_at(orphan_path) else {
+        return false;
+    };
+    main_written_at.is_none_or(|main| written_at > main)
+        && matches!(load_from_path(orphan_path), Ok(Some(_)))
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Take an exclusive advisory lock on a sidecar file next to the state file.
+///
+/// The state file itself is replaced by rename, so locking it would not
+/// exclude writers that open the new inode. Released when the handle drops.
+pub(crate) fn lock_state(path: &Path) -> Result<fs::File, AppError> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))
+        .map_err(|e| AppError::io("Failed to open active session state lock", e))?;
+    lock.lock()
+        .map_err(|e| AppError::io("Failed to lock active session state", e))?;
+    Ok(lock)
+}
+
+fn unique_suffix(clock: &dyn Clock) -> i64 {
+    clock.now().timestamp_nanos_opt().unwrap_or_default()
 }
 
 #[cfg(unix)]
-fn sync_parent_directory(parent: &Path) -> Result<(), String> {
+fn sync_parent_directory(parent: &Path) -> Result<(), AppError> {
     fs::File::open(parent)
         .and_then(|directory| directory.sync_all())
-        .map_err(|e| format!("Failed to sync active session state directory: {e}"))
+        .map_err(|e| AppError::io("Failed to sync active session state directory", e))
 }
 
 #[cfg(not(unix))]
-fn sync_parent_directory(_parent: &Path) -> Result<(), String> {
+fn sync_parent_directory(_parent: &Path) -> Result<(), AppError> {
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{SimulatedClock, SystemClock};
+    use std::path::PathBuf;
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -170,6 +1117,22 @@ mod tests {
         ))
     }
 
+    fn remove_state_files(path: &Path) {
+        fs::remove_file(path).unwrap();
+        let _ = fs::remove_file(path.with_extension("lock"));
+        let _ = fs::remove_dir_all(history_dir(path));
+    }
+
+    /// Backdate the current snapshot so the next save archives it.
+    fn age_snapshot(path: &Path, age: Duration) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
     fn active_state() -> ActiveSessionState {
         ActiveSessionState {
             version: ACTIVE_SESSION_STATE_VERSION,
@@ -188,6 +1151,15 @@ mod tests {
             focus_shield_active: false,
             focus_shield_until: None,
             recovery_reason: None,
+            last_heartbeat_at: None,
+            pomodoro: None,
+            template_id: None,
+            adaptive: None,
+            sampling: None,
+            prompt_shown_at: None,
+            subtasks: Vec::new(),
+            project: None,
+            tags: Vec::new(),
         }
     }
 
@@ -195,9 +1167,12 @@ mod tests {
     fn restart_recovery_marks_active_session_interrupted_without_resetting_it() {
         let path = temporary_path("restart-recovery");
         let original = active_state();
-        save_to_path(&path, &original).unwrap();
+        save_to_path(&path, &original, &SystemClock).unwrap();
 
-        let recovered = recover_from_path(&path).unwrap().unwrap();
+        let recovered = recover_from_path(&path, RecoveryPolicy::MANUAL, &SystemClock)
+            .unwrap()
+            .state
+            .unwrap();
 
         assert_eq!(recovered.phase, SessionPhase::Interrupted);
         assert_eq!(recovered.session_goal, original.session_goal);
@@ -211,26 +1186,267 @@ mod tests {
         );
         assert_eq!(recovered.check_ins_completed, original.check_ins_completed);
         assert!(recovered.recovery_reason.is_some());
-        assert_eq!(recover_from_path(&path).unwrap().unwrap(), recovered);
+        assert_eq!(
+            recover_from_path(&path, RecoveryPolicy::MANUAL, &SystemClock)
+                .unwrap()
+                .state,
+            Some(recovered)
+        );
 
-        fs::remove_file(path).unwrap();
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn recovery_policy_resumes_interrupts_or_discards_by_downtime() {
+        let path = temporary_path("recovery-policy");
+        let heartbeat = Utc::now();
+        let clock = SimulatedClock::new(heartbeat);
+        let mut running = active_state();
+        running.last_heartbeat_at = Some(heartbeat.timestamp_millis());
+        running.phase = SessionPhase::Writing;
+        running.write_time_remaining = 20;
+        running.prompt_shown_at = Some(heartbeat.timestamp_millis() - 10_000);
+        let policy = RecoveryPolicy::from(&Settings {
+            auto_resume_minutes: 5,
+            subtract_downtime: true,
+            discard_session_after_hours: 24,
+            ..Settings::default()
+        });
+        let decision = |recovery: &Recovery| {
+            recovery.journal_entry.as_ref().unwrap()["details"]["decision"].clone()
+        };
+
+        save_to_path(&path, &running, &SystemClock).unwrap();
+        let resumed = {
+            clock.advance(Duration::from_secs(2 * 60));
+            recover_from_path(&path, policy, &clock).unwrap()
+        };
+        assert_eq!(decision(&resumed), "resumed");
+        let state = resumed.state.unwrap();
+        assert_eq!(state.phase, SessionPhase::Writing);
+        // Latency leaves out the two minutes the prompt was not on screen.
+        assert_eq!(
+            state.prompt_shown_at,
+            Some(heartbeat.timestamp_millis() - 10_000 + 2 * 60 * 1000)
+        );
+
+        let interrupted = {
+            clock.advance(Duration::from_secs(28 * 60));
+            recover_from_path(&path, policy, &clock).unwrap()
+        };
+        assert_eq!(decision(&interrupted), "interrupted");
+        let state = interrupted.state.unwrap();
+        assert_eq!(state.phase, SessionPhase::Interrupted);
+        assert_eq!(
+            state.session_time_remaining,
+            running.session_time_remaining - 30 * 60
+        );
+        assert_eq!(load_from_path(&path).unwrap(), Some(state));
+
+        let discarded = {
+            clock.advance(Duration::from_secs(25 * 3600));
+            recover_from_path(&path, policy, &clock).unwrap()
+        };
+        assert_eq!(decision(&discarded), "discarded");
+        assert_eq!(discarded.state, None);
+        assert!(!path.exists());
+
+        let _ = fs::remove_file(path.with_extension("lock"));
+        let _ = fs::remove_dir_all(history_dir(&path));
     }
 
     #[test]
     fn saving_session_state_replaces_the_prior_snapshot() {
         let path = temporary_path("atomic-replace");
         let original = active_state();
-        save_to_path(&path, &original).unwrap();
+        save_to_path(&path, &original, &SystemClock).unwrap();
 
         let mut replacement = original.clone();
         replacement.phase = SessionPhase::Paused;
         replacement.session_time_remaining = 41_000;
-        save_to_path(&path, &replacement).unwrap();
+        save_to_path(&path, &replacement, &SystemClock).unwrap();
 
         let saved: ActiveSessionState = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
         assert_eq!(saved, replacement);
 
-        fs::remove_file(path).unwrap();
+        remove_state_files(&path);
+    }
+
+    /// Leave a temporary snapshot behind as if the app died before renaming it.
+    fn crash_before_rename(path: &Path, contents: &[u8]) -> String {
+        let orphan = path.with_extension(format!("tmp-{}", unique_suffix(&SystemClock)));
+        fs::write(&orphan, contents).unwrap();
+        orphan.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    fn after_orphan_age() -> SimulatedClock {
+        SimulatedClock::new(Utc::now() + ORPHAN_MIN_AGE * 2)
+    }
+
+    #[test]
+    fn newer_orphaned_snapshot_is_offered_and_can_be_restored() {
+        let path = temporary_path("orphan-restore");
+        save_to_path(&path, &active_state(), &SystemClock).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let mut current = active_state();
+        current.check_ins_completed = 2;
+        save_to_path(&path, &current, &SystemClock).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let mut newer = active_state();
+        newer.check_ins_completed = 4;
+        let older = crash_before_rename(&path, b"{}");
+        let file_name = crash_before_rename(&path, &serde_json::to_vec(&newer).unwrap());
+
+        let orphan = clean_up_orphans_at(&path, &after_orphan_age())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(orphan.file_name, file_name);
+        assert_eq!(orphan.state, newer);
+        assert!(!path.with_file_name(older).exists());
+        assert_eq!(
+            resolve_orphan_at(&path, &file_name, true).unwrap(),
+            Some(newer.clone())
+        );
+        assert_eq!(load_from_path(&path).unwrap(), Some(newer));
+        assert!(!path.with_file_name(&file_name).exists());
+        // The replaced state was archived even though the last snapshot is recent.
+        let history = snapshot_history_at(&path).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].check_ins_completed, 2);
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn stale_truncated_and_older_orphans_are_deleted_but_recent_ones_are_kept() {
+        let path = temporary_path("orphan-cleanup");
+        let older = crash_before_rename(&path, &serde_json::to_vec(&active_state()).unwrap());
+        std::thread::sleep(Duration::from_millis(20));
+        save_to_path(&path, &active_state(), &SystemClock).unwrap();
+        let serialized = serde_json::to_vec(&active_state()).unwrap();
+        let truncated = crash_before_rename(&path, &serialized[..serialized.len() / 2]);
+        let empty = crash_before_rename(&path, b"");
+        // Written under a clock set before the epoch.
+        let pre_epoch = path.with_extension("tmp--1741000000000000000");
+        fs::write(&pre_epoch, b"").unwrap();
+        let pre_epoch = pre_epoch
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        assert_eq!(
+            clean_up_orphans_at(&path, &after_orphan_age()).unwrap(),
+            None
+        );
+        for file_name in [&older, &truncated, &empty, &pre_epoch] {
+            assert!(!path.with_file_name(file_name).exists());
+        }
+
+        // A save may still be writing a file this young.
+        let in_flight = crash_before_rename(&path, b"");
+        assert_eq!(clean_up_orphans_at(&path, &SystemClock).unwrap(), None);
+        assert!(path.with_file_name(&in_flight).exists());
+        resolve_orphan_at(&path, &in_flight, false).unwrap();
+        assert!(!path.with_file_name(&in_flight).exists());
+
+        let stem = path.file_stem().unwrap().to_string_lossy().into_owned();
+        for file_name in [
+            "settings.json".to_string(),
+            "../x.tmp-1".to_string(),
+            format!("{stem}.tmp-migrate-1"),
+            format!("{stem}.tmp--"),
+            format!("{stem}.tmp-1-2"),
+        ] {
+            assert!(resolve_orphan_at(&path, &file_name, false).is_err());
+        }
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn history_keeps_a_bounded_ring_spaced_by_interval() {
+        let path = temporary_path("history-ring");
+        let total = HISTORY_LIMIT as u64 + 5;
+        for remaining in 0..total {
+            let mut state = active_state();
+            state.session_time_remaining = remaining;
+            save_to_path(&path, &state, &SystemClock).unwrap();
+            age_snapshot(&path, HISTORY_SPACING * (total - remaining) as u32 * 2);
+        }
+        save_to_path(&path, &active_state(), &SystemClock).unwrap();
+
+        let history = snapshot_history_at(&path).unwrap();
+
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history[0].session_time_remaining, total - 1);
+        assert_eq!(
+            history[HISTORY_LIMIT - 1].session_time_remaining,
+            total - HISTORY_LIMIT as u64
+        );
+        assert!(history
+            .windows(2)
+            .all(|pair| pair[0].saved_at > pair[1].saved_at));
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn bad_write_can_be_rolled_back_to_a_prior_snapshot() {
+        let path = temporary_path("history-restore");
+        let good = active_state();
+        save_to_path(&path, &good, &SystemClock).unwrap();
+        age_snapshot(&path, Duration::from_secs(600));
+        let mut zeroed = good.clone();
+        zeroed.session_time_remaining = 0;
+        zeroed.check_in_time_remaining = 0;
+        save_to_path(&path, &zeroed, &SystemClock).unwrap();
+
+        let history = snapshot_history_at(&path).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            restore_snapshot_at(&path, &history[0].id, &SystemClock).unwrap(),
+            good
+        );
+        assert_eq!(load_from_path(&path).unwrap(), Some(good.clone()));
+
+        // The replaced state was archived, so the restore can be undone.
+        assert_eq!(snapshot_history_at(&path).unwrap().len(), 2);
+        // Too soon after the last archived snapshot to be archived again.
+        save_to_path(&path, &good, &SystemClock).unwrap();
+        assert_eq!(snapshot_history_at(&path).unwrap().len(), 2);
+        assert!(restore_snapshot_at(&path, "../settings", &SystemClock).is_err());
+        assert!(restore_snapshot_at(&path, "1", &SystemClock).is_err());
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn default_settings_neither_resume_nor_discard_an_interrupted_session() {
+        let path = temporary_path("default-recovery");
+        let heartbeat = Utc::now();
+        let mut running = active_state();
+        running.last_heartbeat_at = Some(heartbeat.timestamp_millis());
+        let policy = RecoveryPolicy::from(&Settings::default());
+        assert_eq!(policy, RecoveryPolicy::MANUAL);
+
+        for downtime in [Duration::from_secs(60), Duration::from_secs(3 * 86_400)] {
+            save_to_path(&path, &running, &SystemClock).unwrap();
+            let clock = SimulatedClock::new(heartbeat);
+            clock.advance(downtime);
+            let recovery = recover_from_path(&path, policy, &clock).unwrap();
+            assert_eq!(
+                recovery.journal_entry.unwrap()["details"]["decision"],
+                "interrupted"
+            );
+            let state = recovery.state.unwrap();
+            assert_eq!(state.phase, SessionPhase::Interrupted);
+            assert_eq!(state.session_time_remaining, running.session_time_remaining);
+            assert_eq!(load_from_path(&path).unwrap(), Some(state));
+        }
+
+        remove_state_files(&path);
     }
 
     #[test]
@@ -238,10 +1454,292 @@ mod tests {
         let path = temporary_path("paused-recovery");
         let mut paused = active_state();
         paused.phase = SessionPhase::Paused;
-        save_to_path(&path, &paused).unwrap();
+        save_to_path(&path, &paused, &SystemClock).unwrap();
 
-        assert_eq!(recover_from_path(&path).unwrap(), Some(paused));
+        let policy = RecoveryPolicy::from(&Settings {
+            discard_session_after_hours: 24,
+            ..Settings::default()
+        });
+        let recovery = {
+            let clock = SimulatedClock::new(Utc::now() + chrono::Duration::days(3));
+            recover_from_path(&path, policy, &clock).unwrap()
+        };
+        assert_eq!(recovery.state, Some(paused));
+        assert_eq!(recovery.journal_entry, None);
 
-        fs::remove_file(path).unwrap();
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn pomodoro_break_suspends_check_ins_and_journals_break_time_separately() {
+        let path = temporary_path("pomodoro-break");
+        let mut state = active_state();
+        state.phase = SessionPhase::Break;
+        state.pomodoro = Some(PomodoroProgress {
+            phase: PomodoroPhase::LongBreak,
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            cycles_before_long_break: 4,
+            auto_start: false,
+            cycles_completed: 4,
+            phase_time_remaining: 600,
+            break_seconds: 15 * 60,
+        });
+        save_to_path(&path, &state, &SystemClock).unwrap();
+        let loaded = load_from_path(&path).unwrap().unwrap();
+        assert_eq!(loaded.pomodoro, state.pomodoro);
+        assert_eq!(
+            loaded.accepts_check_in("On Task"),
+            Err(AppError::InvalidCheckIn {
+                reason: "check-ins are suspended during breaks"
+            })
+        );
+
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let entry = loaded
+            .pomodoro_phase_entry(PomodoroPhase::ShortBreak, 300, timestamp)
+            .unwrap();
+        assert_eq!(entry["record_type"], logs::POMODORO_PHASE_RECORD);
+        assert_eq!(
+            entry["details"],
+            serde_json::json!({
+                "phase": "shortBreak",
+                "seconds": 300,
+                "cycle": 4,
+                "break_seconds": 900,
+            })
+        );
+        assert!(active_state()
+            .pomodoro_phase_entry(PomodoroPhase::Work, 1500, timestamp)
+            .is_err());
+
+        // A break phase without Pomodoro progress is not a valid state.
+        state.pomodoro = None;
+        assert!(save_to_path(&path, &state, &SystemClock).is_err());
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn sampling_state_survives_a_save_and_check_ins_record_the_drawn_delay() {
+        let path = temporary_path("sampling");
+        let mut state = active_state();
+        state.sampling = Some(CheckInSampling {
+            distribution: SamplingDistribution::Exponential,
+            min_gap_seconds: 300,
+            rng_state: u32::MAX,
+            last_delay_seconds: 1_742,
+        });
+        save_to_path(&path, &state, &SystemClock).unwrap();
+        assert_eq!(load_from_path(&path).unwrap().unwrap(), state);
+
+        let entry = state.check_in_entry("On Task", None, Utc::now());
+        assert_eq!(entry["sampled_delay_seconds"], 1_742);
+        assert_eq!(
+            active_state().check_in_entry("On Task", None, Utc::now())["sampled_delay_seconds"],
+            serde_json::Value::Null
+        );
+
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn check_in_entries_record_the_prompt_outcome_and_latency() {
+        let timestamp = DateTime::from_timestamp(1_700_000_012, 500_000_000).unwrap();
+        let mut state = active_state();
+        state.prompt_shown_at = Some(1_700_000_000_000);
+
+        // Outside a pending prompt there is nothing to measure.
+        let entry = state.check_in_entry("On Task", None, timestamp);
+        assert_eq!(entry["prompt_outcome"], "answered");
+        assert_eq!(entry["response_latency_ms"], serde_json::Value::Null);
+
+        state.phase = SessionPhase::Writing;
+        let entry = state.check_in_entry("Skip", None, timestamp);
+        assert_eq!(entry["prompt_outcome"], "skipped");
+        assert_eq!(entry["response_latency_ms"], 12_500);
+
+        let deferred = state.deferred_prompt_entry(1_700_000_600_000, timestamp);
+        let deferred: logs::LogEntry = serde_json::from_value(deferred).unwrap();
+        assert!(!deferred.is_check_in());
+        assert_eq!(deferred.prompt_outcome(), Some(PromptOutcome::Deferred));
+    }
+
+    #[test]
+    fn subtask_checklist_survives_recovery_and_the_report_lists_finished_items() {
+        let path = temporary_path("subtasks");
+        let subtask = |id, text: &str, completed_at| Subtask {
+            id,
+            text: text.to_string(),
+            completed_at,
+        };
+        let mut state = active_state();
+        state.subtasks = vec![
+            subtask(3, "Collect merged PRs", Some(1_700_000_900_000)),
+            subtask(1, "Draft highlights", Some(1_700_000_600_000)),
+            subtask(2, "Send for review", None),
+        ];
+        save_to_path(&path, &state, &SystemClock).unwrap();
+        let recovered = recover_from_path(&path, RecoveryPolicy::MANUAL, &SystemClock)
+            .unwrap()
+            .state
+            .unwrap();
+        assert_eq!(recovered.subtasks, state.subtasks);
+
+        let entry = state.subtask_completed_entry(1).unwrap();
+        assert_eq!(
+            entry["timestamp"],
+            DateTime::from_timestamp_millis(1_700_000_600_000)
+                .unwrap()
+                .to_rfc3339()
+        );
+        assert_eq!(entry["details"]["position"], 2);
+        assert_eq!(entry["details"]["completed"], 2);
+        assert!(state.subtask_completed_entry(2).is_err());
+        assert!(state.subtask_completed_entry(9).is_err());
+
+        let dir = path.with_extension("");
+        let storage = Storage::open(&dir).unwrap();
+        let check_in = state.check_in_entry("On Task", None, Utc::now());
+        logs::append_entry(&storage, &check_in.to_string()).unwrap();
+        logs::append_entry(&storage, &entry.to_string()).unwrap();
+        let report = report(&storage, &state).unwrap();
+        assert_eq!(report.check_ins.len(), 1);
+        let finished: Vec<_> = report.finished_subtasks.iter().map(|s| s.id).collect();
+        assert_eq!(finished, [1, 3]);
+        assert_eq!(
+            report.open_subtasks,
+            vec![subtask(2, "Send for review", None)]
+        );
+
+        state.subtasks.push(subtask(2, "Duplicate id", None));
+        assert!(save_to_path(&path, &state, &SystemClock).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+        remove_state_files(&path);
+    }
+
+    #[test]
+    fn clearing_waits_for_a_locked_update_to_finish() {
+        let dir = temporary_path("clear-locked").with_extension("");
+        let storage = Storage::open(&dir).unwrap();
+        save_to_path(&storage.state_path(), &active_state(), &SystemClock).unwrap();
+
+        let lock = lock_state(&storage.state_path()).unwrap();
+        let clearing = {
+            let storage = storage.clone();
+            std::thread::spawn(move || clear(&storage))
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(storage.state_path().exists());
+        drop(lock);
+        clearing.join().unwrap().unwrap();
+        assert!(!storage.state_path().exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn offline_check_ins_advance_the_saved_count() {
+        let dir = temporary_path("offline-check-in").with_extension("");
+        let storage = Storage::open(&dir).unwrap();
+        assert_eq!(
+            record_check_in(&storage, "On Task", None, &SystemClock)
+                .unwrap_err()
+                .code(),
+            "no_active_session"
+        );
+
+        save(&storage, active_state(), &SystemClock).unwrap();
+        record_check_in(&storage, "On Task", None, &SystemClock).unwrap();
+        record_check_in(&storage, "Email/Chat", Some("Synthetic note"), &SystemClock).unwrap();
+        let numbers: Vec<_> = logs::read_entries_from_path(&storage.log_path())
+            .unwrap()
+            .iter()
+            .map(|entry| entry.check_in_number)
+            .collect();
+        assert_eq!(numbers, [Some(4), Some(5)]);
+        assert_eq!(load(&storage).unwrap().unwrap().check_ins_completed, 5);
+
+        // A pending prompt already counted the check-in it answers; the
+        // answer closes it so the app does not prompt for it again.
+        let mut writing = load(&storage).unwrap().unwrap();
+        writing.phase = SessionPhase::Writing;
+        writing.prompt_shown_at = Some(1_700_000_000_000);
+        writing.write_time_remaining = 12;
+        writing.check_in_time_remaining = 0;
+        save(&storage, writing, &SystemClock).unwrap();
+        record_check_in(&storage, "Skip", None, &SystemClock).unwrap();
+        let journal = logs::read_entries_from_path(&storage.log_path()).unwrap();
+        assert_eq!(journal[2].check_in_number, Some(5));
+        let answered = load(&storage).unwrap().unwrap();
+        assert_eq!(answered.phase, SessionPhase::Active);
+        assert_eq!(answered.check_ins_completed, 5);
+        assert_eq!(answered.prompt_shown_at, None);
+        assert_eq!(answered.write_time_remaining, 0);
+        assert_eq!(answered.check_in_time_remaining, 20 * 60);
+        assert_eq!(answered.skipped_check_ins, 2);
+        assert!(answered.last_check_in_was_skipped);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn answers_adapt_the_interval_and_draw_the_same_sampled_delays_as_the_webview() {
+        let mut state = active_state();
+        state.adaptive = Some(AdaptiveInterval {
+            min_interval: 10,
+            max_interval: 40,
+            interval: 20,
+            on_task_streak: 1,
+            reason: None,
+        });
+        let entry = state.check_in_entry("On Task", None, Utc::now());
+        state.apply_answer("On Task");
+        assert_eq!(entry["interval_adjustment"]["next_interval"], 25);
+        assert_eq!(state.adaptive.as_ref().unwrap().interval, 25);
+        assert_eq!(state.check_in_time_remaining, 25 * 60);
+        assert!(!state.last_check_in_was_skipped);
+
+        // Values from `sampleCheckInDelay` with the same seed and settings.
+        for (distribution, expected) in [
+            (SamplingDistribution::Uniform, [1382, 1107, 1834, 1506]),
+            (SamplingDistribution::Exponential, [1127, 835, 2022, 1297]),
+        ] {
+            let mut sampling = CheckInSampling {
+                distribution,
+                min_gap_seconds: 300,
+                rng_state: 42,
+                last_delay_seconds: 0,
+            };
+            let delays = expected.map(|_| sampling.draw_delay(1200));
+            assert_eq!(delays, expected);
+            assert_eq!(sampling.rng_state, 3_031_295_998);
+        }
+    }
+
+    #[test]
+    fn check_ins_need_a_known_answer_while_the_session_runs() {
+        let mut state = active_state();
+        for phase in [SessionPhase::Active, SessionPhase::Writing] {
+            state.phase = phase;
+            assert_eq!(state.accepts_check_in("Email/Chat"), Ok(()));
+        }
+        assert_eq!(
+            state.accepts_check_in("Sleeping"),
+            Err(AppError::InvalidCheckIn {
+                reason: "the status is not one of the check-in answers"
+            })
+        );
+        for phase in [SessionPhase::Paused, SessionPhase::Interrupted] {
+            state.phase = phase;
+            assert_eq!(
+                state.accepts_check_in("On Task"),
+                Err(AppError::InvalidCheckIn {
+                    reason: "the session is not running"
+                })
+            );
+        }
     }
 }
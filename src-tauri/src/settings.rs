@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::{billing, error::AppError, storage::Storage};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    pub session_duration: u32,
+    pub check_in_interval: u32,
+    pub write_time: u32,
+    pub window_position: String, // "auto", "right-edge", "centered" or "remember-last"
+    /// Diagnostic log verbosity: "error", "warn", "info", "debug" or "trace".
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Restart recovery resumes a running session without asking when the app
+    /// was down for less than this many minutes; 0 always asks.
+    #[serde(default)]
+    pub auto_resume_minutes: u32,
+    /// Subtract the downtime from a session that was interrupted instead.
+    #[serde(default)]
+    pub subtract_downtime: bool,
+    /// Discard a saved session whose last heartbeat is older than this many
+    /// hours; 0 keeps it indefinitely.
+    #[serde(default)]
+    pub discard_session_after_hours: u32,
+    /// Split the session into Pomodoro work phases and breaks; check-ins are
+    /// suspended during breaks.
+    #[serde(default)]
+    pub pomodoro_enabled: bool,
+    #[serde(default = "default_pomodoro_work_minutes")]
+    pub pomodoro_work_minutes: u32,
+    #[serde(default = "default_pomodoro_short_break_minutes")]
+    pub pomodoro_short_break_minutes: u32,
+    #[serde(default = "default_pomodoro_long_break_minutes")]
+    pub pomodoro_long_break_minutes: u32,
+    /// Every this many work phases, the break is a long one.
+    #[serde(default = "default_pomodoro_cycles_before_long_break")]
+    pub pomodoro_cycles_before_long_break: u32,
+    /// Start the next phase without waiting for the user.
+    #[serde(default)]
+    pub pomodoro_auto_start: bool,
+    /// Lengthen the check-in interval after on-task answers and shorten it
+    /// after distractions, within the bounds below (minutes).
+    #[serde(default)]
+    pub adaptive_check_ins: bool,
+    #[serde(default = "default_adaptive_min_interval")]
+    pub adaptive_min_interval: u32,
+    #[serde(default = "default_adaptive_max_interval")]
+    pub adaptive_max_interval: u32,
+    /// Draw each check-in time at random around the check-in interval:
+    /// "off", "uniform" or "exponential".
+    #[serde(default = "default_check_in_sampling")]
+    pub check_in_sampling: String,
+    /// Sampled check-ins are never closer together than this many minutes.
+    #[serde(default = "default_sampling_min_gap_minutes")]
+    pub sampling_min_gap_minutes: u32,
+    /// Billable-hours reports round each project's daily on-task time up to
+    /// this many minutes, e.g. 6 or 15; 0 bills exact minutes.
+    #[serde(default)]
+    pub billing_rounding_minutes: u32,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_pomodoro_work_minutes() -> u32 {
+    25
+}
+
+fn default_pomodoro_short_break_minutes() -> u32 {
+    5
+}
+
+fn default_pomodoro_long_break_minutes() -> u32 {
+    15
+}
+
+fn default_pomodoro_cycles_before_long_break() -> u32 {
+    4
+}
+
+fn default_adaptive_min_interval() -> u32 {
+    10
+}
+
+fn default_adaptive_max_interval() -> u32 {
+    40
+}
+
+fn default_check_in_sampling() -> String {
+    "off".to_string()
+}
+
+fn default_sampling_min_gap_minutes() -> u32 {
+    5
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            session_duration: 720,
+            check_in_interval: 20,
+            write_time: 20,
+            window_position: "auto".to_string(),
+            log_level: default_log_level(),
+            // Restart recovery asks and keeps the session unless opted in.
+            auto_resume_minutes: 0,
+            subtract_downtime: false,
+            discard_session_after_hours: 0,
+            pomodoro_enabled: false,
+            pomodoro_work_minutes: default_pomodoro_work_minutes(),
+            pomodoro_short_break_minutes: default_pomodoro_short_break_minutes(),
+            pomodoro_long_break_minutes: default_pomodoro_long_break_minutes(),
+            pomodoro_cycles_before_long_break: default_pomodoro_cycles_before_long_break(),
+            pomodoro_auto_start: false,
+            adaptive_check_ins: false,
+            adaptive_min_interval: default_adaptive_min_interval(),
+            adaptive_max_interval: default_adaptive_max_interval(),
+            check_in_sampling: default_check_in_sampling(),
+            sampling_min_gap_minutes: default_sampling_min_gap_minutes(),
+            billing_rounding_minutes: 0,
+        }
+    }
+}
+
+const WINDOW_POSITIONS: &[&str] = &["auto", "right-edge", "centered", "remember-last"];
+const LOG_LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+const SAMPLING_MODES: &[&str] = &["off", "uniform", "exponential"];
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.session_duration == 0 || self.check_in_interval == 0 || self.write_time == 0 {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a zero timer value",
+            });
+        }
+        if self.pomodoro_work_minutes == 0
+            || self.pomodoro_short_break_minutes == 0
+            || self.pomodoro_long_break_minutes == 0
+            || self.pomodoro_cycles_before_long_break == 0
+        {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a zero Pomodoro value",
+            });
+        }
+        if self.adaptive_min_interval == 0
+            || self.adaptive_min_interval > self.adaptive_max_interval
+        {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain adaptive interval bounds that are zero or reversed",
+            });
+        }
+        if !WINDOW_POSITIONS.contains(&self.window_position.as_str()) {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain an unknown window position",
+            });
+        }
+        if !LOG_LEVELS.contains(&self.log_level.as_str()) {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain an unknown log level",
+            });
+        }
+        if !SAMPLING_MODES.contains(&self.check_in_sampling.as_str()) {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain an unknown check-in sampling mode",
+            });
+        }
+        if self.sampling_min_gap_minutes == 0 {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a zero sampling gap",
+            });
+        }
+        if self.billing_rounding_minutes > billing::MAX_ROUNDING_MINUTES {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a billing rounding increment over 60 minutes",
+            });
+        }
+        Ok(())
+    }
+}
+
+pub fn load(storage: &Storage) -> Result<Settings, AppError> {
+    let path = storage.settings_path();
+    if path.exists() {
+        let data =
+            fs::read_to_string(path).map_err(|e| AppError::io("Failed to read settings", e))?;
+        let settings: Settings = serde_json::from_str(&data).map_err(AppError::settings_corrupt)?;
+        Ok(settings)
+    } else {
+        Ok(Settings::default())
+    }
+}
+
+pub fn save(storage: &Storage, settings: &Settings) -> Result<(), AppError> {
+    settings.validate()?;
+    let data = serde_json::to_string_pretty(settings).map_err(AppError::settings_corrupt)?;
+    fs::write(storage.settings_path(), data).map_err(|e| AppError::io("Failed to save settings", e))
+}
@@ -0,0 +1,426 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::logs::{LogEntry, PromptOutcome, POMODORO_PHASE_RECORD, SESSION_OUTCOME_RECORD};
+
+pub const ON_TASK_STATUS: &str = "On Task";
+pub const BREAK_STATUS: &str = "Taking a Break";
+pub const SKIP_STATUS: &str = "Skip";
+pub const DISTRACTION_STATUSES: &[&str] = &["Social Media", "Email/Chat", "Other Distraction"];
+
+/// What a check-in answer says about the interval before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+    OnTask,
+    Break,
+    Distraction,
+    /// The prompt was skipped; the answer says nothing about the interval.
+    Skipped,
+}
+
+/// Classify one of the check-in statuses; `None` for any other string.
+pub fn status_kind(status: &str) -> Option<StatusKind> {
+    match status {
+        ON_TASK_STATUS => Some(StatusKind::OnTask),
+        BREAK_STATUS => Some(StatusKind::Break),
+        SKIP_STATUS => Some(StatusKind::Skipped),
+        _ if DISTRACTION_STATUSES.contains(&status) => Some(StatusKind::Distraction),
+        _ => None,
+    }
+}
+
+/// Aggregate check-in counts for a time range. Contains no goals or notes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckInStats {
+    pub total_check_ins: usize,
+    pub on_task_check_ins: usize,
+    pub auto_submitted_check_ins: usize,
+    pub skipped_check_ins: usize,
+    /// Prompts the focus shield postponed; not check-ins themselves.
+    pub deferred_prompts: usize,
+    /// Over answered prompts that recorded a latency.
+    pub average_response_latency_ms: Option<u64>,
+    /// Each on-task answer credits the check-in interval that preceded it,
+    /// which in adaptive and sampling sessions is not the configured one.
+    pub focused_minutes: u64,
+    pub status_counts: BTreeMap<String, usize>,
+    /// Finished Pomodoro phases, journaled apart from check-ins.
+    pub pomodoro_work_seconds: u64,
+    pub break_seconds: u64,
+    /// Session outcome records by goal rating.
+    pub achieved_goals: usize,
+    pub partial_goals: usize,
+    pub missed_goals: usize,
+    /// Check-in totals per project id; check-ins without a project are left out.
+    pub projects: BTreeMap<String, ProjectStats>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub check_ins: usize,
+    pub on_task_check_ins: usize,
+    pub focused_minutes: u64,
+}
+
+impl CheckInStats {
+    pub fn on_task_ratio(&self) -> Option<f64> {
+        (self.total_check_ins > 0)
+            .then(|| self.on_task_check_ins as f64 / self.total_check_ins as f64)
+    }
+
+    /// Share of rated sessions whose goal was fully achieved.
+    pub fn goal_completion_ratio(&self) -> Option<f64> {
+        let rated = self.achieved_goals + self.partial_goals + self.missed_goals;
+        (rated > 0).then(|| self.achieved_goals as f64 / rated as f64)
+    }
+}
+
+/// Summarize entries whose timestamp falls in `[start, end)`.
+pub fn summarize(entries: &[LogEntry], start: DateTime<Utc>, end: DateTime<Utc>) -> CheckInStats {
+    let mut stats = CheckInStats::default();
+    let mut latencies = Vec::new();
+
+    for entry in entries {
+        let Some(time) = entry.parsed_timestamp() else {
+            continue;
+        };
+        if time < start || time >= end {
+            continue;
+        }
+        if entry.record_type.as_deref() == Some(POMODORO_PHASE_RECORD) {
+            count_pomodoro_phase(&mut stats, entry);
+            continue;
+        }
+        if entry.record_type.as_deref() == Some(SESSION_OUTCOME_RECORD) {
+            count_goal_rating(&mut stats, entry);
+            continue;
+        }
+        let outcome = entry.prompt_outcome();
+        if outcome == Some(PromptOutcome::Deferred) {
+            stats.deferred_prompts += 1;
+            continue;
+        }
+        if !entry.is_check_in() {
+            continue;
+        }
+
+        stats.total_check_ins += 1;
+        match outcome {
+            Some(PromptOutcome::AutoSubmitted) => stats.auto_submitted_check_ins += 1,
+            Some(PromptOutcome::Skipped) => stats.skipped_check_ins += 1,
+            Some(PromptOutcome::Answered) => latencies.extend(entry.response_latency_ms),
+            _ => {}
+        }
+        let mut project = entry
+            .project
+            .as_ref()
+            .map(|project| stats.projects.entry(project.clone()).or_default());
+        if let Some(project) = project.as_deref_mut() {
+            project.check_ins += 1;
+        }
+        if status_kind(&entry.reported_status) == Some(StatusKind::OnTask) {
+            let minutes = credited_minutes(entry);
+            stats.on_task_check_ins += 1;
+            stats.focused_minutes += minutes;
+            if let Some(project) = project {
+                project.on_task_check_ins += 1;
+                project.focused_minutes += minutes;
+            }
+        }
+        *stats
+            .status_counts
+            .entry(entry.reported_status.clone())
+            .or_default() += 1;
+    }
+
+    if !latencies.is_empty() {
+        stats.average_response_latency_ms =
+            Some(latencies.iter().sum::<u64>() / latencies.len() as u64);
+    }
+    stats
+}
+
+/// Whether a check-in's status says anything about the wait before it; an
+/// auto-submitted or skipped prompt credits nothing.
+pub fn credits_interval(entry: &LogEntry) -> bool {
+    !entry
+        .prompt_outcome()
+        .is_some_and(|outcome| outcome.is_ignored())
+}
+
+/// The minutes a check-in credits to its status: the wait that preceded it.
+pub fn credited_minutes(entry: &LogEntry) -> u64 {
+    if !credits_interval(entry) {
+        return 0;
+    }
+    let interval = match (entry.sampled_delay_seconds, &entry.interval_adjustment) {
+        (Some(seconds), _) => Some(((seconds + 30) / 60) as u32),
+        (None, Some(adjustment)) => Some(adjustment.interval),
+        (None, None) => entry.check_in_interval_setting,
+    };
+    u64::from(interval.unwrap_or(0))
+}
+
+fn count_pomodoro_phase(stats: &mut CheckInStats, entry: &LogEntry) {
+    let Some(details) = &entry.details else {
+        return;
+    };
+    let seconds = details["seconds"].as_u64().unwrap_or(0);
+    match details["phase"].as_str() {
+        Some("work") => stats.pomodoro_work_seconds += seconds,
+        Some("shortBreak" | "longBreak") => stats.break_seconds += seconds,
+        _ => {}
+    }
+}
+
+fn count_goal_rating(stats: &mut CheckInStats, entry: &LogEntry) {
+    let Some(details) = &entry.details else {
+        return;
+    };
+    match details["rating"].as_str() {
+        Some("achieved") => stats.achieved_goals += 1,
+        Some("partial") => stats.partial_goals += 1,
+        Some("missed") => stats.missed_goals += 1,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adaptive::IntervalAdjustment;
+
+    fn entry(timestamp: &str, status: &str, auto: bool) -> LogEntry {
+        LogEntry {
+            timestamp: timestamp.to_string(),
+            session_goal: Some("Synthetic test goal".to_string()),
+            reported_status: status.to_string(),
+            notes: None,
+            session_duration_setting: Some(720),
+            check_in_interval_setting: Some(20),
+            write_time_setting: Some(20),
+            check_in_number: Some(1),
+            auto_submitted: Some(auto),
+            focus_shield_active: None,
+            record_type: None,
+            details: None,
+            template_id: None,
+            interval_adjustment: None,
+            sampled_delay_seconds: None,
+            prompt_outcome: None,
+            response_latency_ms: None,
+            project: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn summary_counts_only_entries_inside_the_range() {
+        let entries = vec![
+            entry("2025-11-09T23:59:59Z", "On Task", false),
+            entry("2025-11-10T09:00:00Z", "On Task", false),
+            entry("2025-11-10T09:20:00Z", "Social Media", false),
+            entry("2025-11-12T14:00:00Z", "Skip", true),
+            entry("2025-11-13T10:00:00Z", "On Task", false),
+            entry("2025-11-17T00:00:00Z", "On Task", false),
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+
+        assert_eq!(stats.total_check_ins, 4);
+        assert_eq!(stats.on_task_check_ins, 2);
+        assert_eq!(stats.auto_submitted_check_ins, 1);
+        assert_eq!(stats.focused_minutes, 40);
+        assert_eq!(stats.status_counts.get("Social Media"), Some(&1));
+        assert_eq!(stats.on_task_ratio(), Some(0.5));
+    }
+
+    #[test]
+    fn prompt_outcomes_count_ignored_and_deferred_prompts_and_average_latency() {
+        let answered = |timestamp, latency| LogEntry {
+            prompt_outcome: Some(PromptOutcome::Answered),
+            response_latency_ms: Some(latency),
+            ..entry(timestamp, "On Task", false)
+        };
+        let deferred = LogEntry {
+            reported_status: String::new(),
+            record_type: Some(crate::logs::PROMPT_DEFERRED_RECORD.to_string()),
+            prompt_outcome: Some(PromptOutcome::Deferred),
+            ..entry("2025-11-10T09:40:00Z", "", false)
+        };
+        let entries = vec![
+            answered("2025-11-10T09:00:00Z", 4_000),
+            answered("2025-11-10T09:20:00Z", 9_000),
+            deferred,
+            // Written before outcomes were recorded.
+            entry("2025-11-10T10:00:00Z", "Skip", false),
+            entry("2025-11-10T10:20:00Z", "Skip", true),
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+
+        assert_eq!(stats.total_check_ins, 4);
+        assert_eq!(stats.skipped_check_ins, 1);
+        assert_eq!(stats.auto_submitted_check_ins, 1);
+        assert_eq!(stats.deferred_prompts, 1);
+        assert_eq!(stats.average_response_latency_ms, Some(6_500));
+    }
+
+    #[test]
+    fn pomodoro_phases_count_as_work_or_break_time_but_not_check_ins() {
+        let phase = |timestamp: &str, phase: &str, seconds: u64| LogEntry {
+            reported_status: String::new(),
+            check_in_interval_setting: None,
+            record_type: Some(POMODORO_PHASE_RECORD.to_string()),
+            details: Some(serde_json::json!({ "phase": phase, "seconds": seconds, "cycle": 1 })),
+            ..entry(timestamp, "", false)
+        };
+        let entries = vec![
+            phase("2025-11-10T09:25:00Z", "work", 1500),
+            entry("2025-11-10T09:26:00Z", "On Task", false),
+            phase("2025-11-10T09:30:00Z", "shortBreak", 300),
+            phase("2025-11-10T11:45:00Z", "longBreak", 900),
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+
+        assert_eq!(stats.total_check_ins, 1);
+        assert_eq!(stats.pomodoro_work_seconds, 1500);
+        assert_eq!(stats.break_seconds, 1200);
+    }
+
+    #[test]
+    fn adaptive_and_sampled_check_ins_credit_the_wait_they_actually_had() {
+        let adapted = LogEntry {
+            interval_adjustment: Some(IntervalAdjustment {
+                interval: 35,
+                next_interval: 40,
+                reason: Some("2 on-task answers in a row".to_string()),
+            }),
+            ..entry("2025-11-10T10:00:00Z", "On Task", false)
+        };
+        let sampled = LogEntry {
+            sampled_delay_seconds: Some(7 * 60 + 40),
+            ..entry("2025-11-10T10:08:00Z", "On Task", false)
+        };
+        let entries = vec![
+            entry("2025-11-10T09:00:00Z", "On Task", false),
+            adapted,
+            sampled,
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+
+        assert_eq!(stats.focused_minutes, 20 + 35 + 8);
+    }
+
+    #[test]
+    fn check_ins_are_totalled_per_project_and_older_records_have_none() {
+        let for_project = |timestamp, status, project: &str| LogEntry {
+            project: Some(project.to_string()),
+            tags: vec!["client".to_string()],
+            ..entry(timestamp, status, false)
+        };
+        // A line written before projects existed.
+        let older: LogEntry = serde_json::from_str(
+            r#"{"timestamp":"2025-11-10T08:00:00Z","session_goal":"Inbox","reported_status":"On Task","notes":null,"session_duration_setting":720,"check_in_interval_setting":20,"write_time_setting":20,"check_in_number":1,"auto_submitted":false,"focus_shield_active":false}"#,
+        )
+        .unwrap();
+        assert_eq!((older.project.as_deref(), older.tags.len()), (None, 0));
+        let entries = vec![
+            older,
+            for_project("2025-11-10T09:00:00Z", "On Task", "acme"),
+            for_project("2025-11-10T09:20:00Z", "Social Media", "acme"),
+            for_project("2025-11-10T09:40:00Z", "On Task", "globex"),
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+
+        assert_eq!(stats.total_check_ins, 4);
+        assert_eq!(stats.focused_minutes, 60);
+        assert_eq!(
+            stats.projects["acme"],
+            ProjectStats {
+                check_ins: 2,
+                on_task_check_ins: 1,
+                focused_minutes: 20,
+            }
+        );
+        assert_eq!(stats.projects["globex"].focused_minutes, 20);
+        assert_eq!(stats.projects.len(), 2);
+    }
+
+    #[test]
+    fn an_auto_submitted_on_task_answer_credits_the_same_minutes_everywhere() {
+        let line = |timestamp: &str, number: u32, outcome: &str| -> LogEntry {
+            serde_json::from_value(serde_json::json!({
+                "timestamp": timestamp,
+                "session_goal": "Synthetic test goal",
+                "reported_status": ON_TASK_STATUS,
+                "check_in_interval_setting": 20,
+                "check_in_number": number,
+                "prompt_outcome": outcome,
+                "project": "acme",
+            }))
+            .unwrap()
+        };
+        let entries = vec![
+            line("2025-11-10T09:20:00Z", 1, "answered"),
+            line("2025-11-10T09:40:00Z", 2, "auto_submitted"),
+        ];
+
+        let stats = summarize(
+            &entries,
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-11T00:00:00Z"),
+        );
+        let goals = crate::goals::build(&entries);
+        let day = "2025-11-10".parse().unwrap();
+        let billing = crate::billing::report(&entries, day, day, 0, &Utc).unwrap();
+
+        assert_eq!(stats.focused_minutes, 20);
+        assert_eq!(stats.projects["acme"].focused_minutes, 20);
+        assert_eq!(goals[0].focused_minutes, 20);
+        assert_eq!(billing.projects[0].on_task_minutes, 20);
+    }
+
+    #[test]
+    fn empty_range_has_no_on_task_ratio() {
+        let stats = summarize(
+            &[],
+            time("2025-11-10T00:00:00Z"),
+            time("2025-11-17T00:00:00Z"),
+        );
+        assert_eq!(stats, CheckInStats::default());
+        assert_eq!(stats.on_task_ratio(), None);
+    }
+}
@@ -0,0 +1,555 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::AppError, logs, APP_IDENTIFIER};
+
+const FOCUS_LOG_FILE_NAME: &str = "focus_log.jsonl";
+const ACTIVE_SESSION_FILE_NAME: &str = "active_session.json";
+/// Earlier session state snapshots, next to the state file.
+const SESSION_HISTORY_DIR_NAME: &str = "active_session.history";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const WINDOW_POSITIONS_FILE_NAME: &str = "window_positions.json";
+const TEMPLATES_FILE_NAME: &str = "session_templates.json";
+const GOAL_HISTORY_FILE_NAME: &str = "goal_history.json";
+const PROJECTS_FILE_NAME: &str = "projects.json";
+const INSTANCE_LOCK_FILE_NAME: &str = "instance.lock";
+const CONTROL_DIR_NAME: &str = "control";
+const CONTROL_SOCKET_FILE_NAME: &str = "control.sock";
+const DATA_LOCATION_FILE_NAME: &str = "data_location.json";
+
+/// Where the data files live when the user moved them out of the config
+/// directory. Stored in the config directory itself so it can always be found.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataLocation {
+    data_directory: PathBuf,
+}
+
+/// The data root and every file the app keeps in it.
+///
+/// The app manages one as Tauri state; tests and the CLI build their own over
+/// any directory, so nothing that reads or writes app data needs an `AppHandle`.
+///
+/// The instance lock, control socket and data location pointer always stay in
+/// the config directory; the journal, session state and settings follow the
+/// user's chosen data directory, which may be a synced or encrypted folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storage {
+    config_dir: PathBuf,
+    root: PathBuf,
+}
+
+impl Storage {
+    /// Use `config_dir` as the config directory, creating it if needed, and
+    /// follow its data location pointer if the user chose another data directory.
+    pub fn open(config_dir: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let config_dir = config_dir.into();
+        fs::create_dir_all(&config_dir)
+            .map_err(|e| AppError::io("Failed to create config directory", e))?;
+
+        let pointer = config_dir.join(DATA_LOCATION_FILE_NAME);
+        let root = if pointer.exists() {
+            let data =
+                fs::read(&pointer).map_err(|e| AppError::io("Failed to read data location", e))?;
+            let location: DataLocation =
+                serde_json::from_slice(&data).map_err(AppError::settings_corrupt)?;
+            location.data_directory
+        } else {
+            config_dir.clone()
+        };
+        fs::create_dir_all(&root)
+            .map_err(|e| AppError::io("Failed to create data directory", e))?;
+
+        Ok(Self { config_dir, root })
+    }
+
+    /// The directory the app uses when nothing else is configured; matches
+    /// Tauri's `app_config_dir` for this bundle identifier.
+    pub fn default_root() -> Result<PathBuf, AppError> {
+        dirs::config_dir()
+            .map(|dir| dir.join(APP_IDENTIFIER))
+            .ok_or(AppError::DataDirectoryInvalid(
+                "could not determine the config directory",
+            ))
+    }
+
+    /// The data directory holding the journal, session state and settings.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    pub fn log_path(&self) -> PathBuf {
+        self.root.join(FOCUS_LOG_FILE_NAME)
+    }
+
+    pub fn state_path(&self) -> PathBuf {
+        self.root.join(ACTIVE_SESSION_FILE_NAME)
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.root.join(SETTINGS_FILE_NAME)
+    }
+
+    pub fn window_positions_path(&self) -> PathBuf {
+        self.root.join(WINDOW_POSITIONS_FILE_NAME)
+    }
+
+    pub fn templates_path(&self) -> PathBuf {
+        self.root.join(TEMPLATES_FILE_NAME)
+    }
+
+    pub fn goal_history_path(&self) -> PathBuf {
+        self.root.join(GOAL_HISTORY_FILE_NAME)
+    }
+
+    pub fn projects_path(&self) -> PathBuf {
+        self.root.join(PROJECTS_FILE_NAME)
+    }
+
+    pub fn instance_lock_path(&self) -> PathBuf {
+        self.config_dir.join(INSTANCE_LOCK_FILE_NAME)
+    }
+
+    pub fn control_socket_path(&self) -> PathBuf {
+        self.config_dir
+            .join(CONTROL_DIR_NAME)
+            .join(CONTROL_SOCKET_FILE_NAME)
+    }
+
+    fn data_location_path(&self) -> PathBuf {
+        self.config_dir.join(DATA_LOCATION_FILE_NAME)
+    }
+
+    /// Every file that moves with the data directory.
+    fn data_file_names() -> [&'static str; 7] {
+        [
+            FOCUS_LOG_FILE_NAME,
+            ACTIVE_SESSION_FILE_NAME,
+            SETTINGS_FILE_NAME,
+            WINDOW_POSITIONS_FILE_NAME,
+            TEMPLATES_FILE_NAME,
+            GOAL_HISTORY_FILE_NAME,
+            PROJECTS_FILE_NAME,
+        ]
+    }
+
+    /// Matches the names `copy_atomically` and `write_atomically` give their
+    /// temporary files (`<stem>.tmp-migrate-<pid>`, `<stem>.tmp-save-<pid>`)
+    /// for any file that moves with the data.
+    pub(crate) fn is_atomic_write_temp_name(file_name: &str) -> bool {
+        Self::data_file_names().iter().any(|name| {
+            let stem = Path::new(name).file_stem().unwrap_or_default();
+            ["migrate", "save"].iter().any(|purpose| {
+                file_name
+                    .strip_prefix(&format!("{}.tmp-{purpose}-", stem.to_string_lossy()))
+                    .is_some_and(|pid| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+            })
+        })
+    }
+
+    /// The snapshot files in the session history directory, if it exists.
+    fn history_files(&self) -> Result<Vec<PathBuf>, AppError> {
+        let dir = self.root.join(SESSION_HISTORY_DIR_NAME);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in
+            fs::read_dir(&dir).map_err(|e| AppError::io("Failed to list session history", e))?
+        {
+            let path = entry
+                .map_err(|e| AppError::io("Failed to list session history", e))?
+                .path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Move the data files and session history to `target` and point the
+    /// config directory at it.
+    ///
+    /// Each file is copied next to its destination and renamed into place, so
+    /// the target never holds a partial file. Refuses to overwrite any file at
+    /// the target that differs from the current one; identical files are left
+    /// in place, and a file only the target has is adopted as it is. The
+    /// originals are only deleted once the copied journal reports the same
+    /// record counts and the new location is saved; failing to delete one is
+    /// logged rather than returned, since the app already uses the new
+    /// directory. A failed move removes only the files it created.
+    pub fn migrate_to(&self, target: &Path) -> Result<Storage, AppError> {
+        if !target.is_absolute() {
+            return Err(AppError::DataDirectoryInvalid("must be an absolute path"));
+        }
+        fs::create_dir_all(target)
+            .map_err(|e| AppError::io("Failed to create the new data directory", e))?;
+        let target = target
+            .canonicalize()
+            .map_err(|e| AppError::io("Failed to resolve the new data directory", e))?;
+        let current = self
+            .root
+            .canonicalize()
+            .map_err(|e| AppError::io("Failed to resolve the current data directory", e))?;
+        if target == current {
+            return Ok(self.clone());
+        }
+
+        let migrated = Storage {
+            config_dir: self.config_dir.clone(),
+            root: target,
+        };
+        let source_log = self.log_path();
+        let target_log = migrated.log_path();
+
+        // Hold the journal lock so no check-in lands in the old file mid-move.
+        let _journal_lock = match OpenOptions::new().read(true).open(&source_log) {
+            Ok(file) => {
+                file.lock()
+                    .map_err(|e| AppError::io("Failed to lock focus log for migration", e))?;
+                Some(file)
+            }
+            Err(_) => None,
+        };
+
+        let history_dir = migrated.root.join(SESSION_HISTORY_DIR_NAME);
+        let mut pending: Vec<(PathBuf, PathBuf)> = Self::data_file_names()
+            .into_iter()
+            .map(|name| (self.root.join(name), migrated.root.join(name)))
+            .filter(|(source, _)| source.exists())
+            .collect();
+        for source in self.history_files()? {
+            let destination = history_dir.join(source.file_name().unwrap_or_default());
+            pending.push((source, destination));
+        }
+        let mut copies = Vec::new();
+        for (source, destination) in pending {
+            if !destination.exists() {
+                copies.push((source, destination));
+            } else if !same_contents(&source, &destination)? {
+                return Err(AppError::DataDirectoryConflict(destination));
+            }
+        }
+
+        let created_history_dir = !history_dir.exists();
+        let mut created = Vec::new();
+        let result = (|| -> Result<(), AppError> {
+            for (source, destination) in &copies {
+                if destination.starts_with(&history_dir) {
+                    fs::create_dir_all(&history_dir)
+                        .map_err(|e| AppError::io("Failed to create the session history", e))?;
+                }
+                copy_atomically(source, destination)?;
+                created.push(destination.clone());
+            }
+            if history_dir.exists() {
+                sync_directory(&history_dir)?;
+            }
+            sync_directory(&migrated.root)?;
+
+            // Without a journal of its own, the app adopts the target's as is.
+            if source_log.exists() {
+                let before = logs::diagnostics_for_path(&source_log)?;
+                let after = logs::diagnostics_for_path(&target_log)?;
+                if before != after {
+                    return Err(AppError::DataMigrationMismatch { before, after });
+                }
+            }
+
+            migrated.write_data_location()
+        })();
+
+        if let Err(error) = result {
+            for path in created {
+                let _ = fs::remove_file(path);
+            }
+            if created_history_dir {
+                let _ = fs::remove_dir(&history_dir);
+            }
+            return Err(error);
+        }
+
+        for path in self.remove_originals() {
+            log::warn!(
+                "Moved data to {} but could not remove {}",
+                migrated.root.display(),
+                path.display()
+            );
+        }
+        Ok(migrated)
+    }
+
+    /// Delete the data files and session history after a move; returns the
+    /// paths that could not be removed.
+    fn remove_originals(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Self::data_file_names()
+            .into_iter()
+            .map(|name| self.root.join(name))
+            .filter(|path| path.exists())
+            .collect();
+        paths.extend(self.history_files().unwrap_or_default());
+        let mut leftovers: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| fs::remove_file(path).is_err())
+            .collect();
+        // Only the snapshots were moved; anything else keeps the directory.
+        let history_dir = self.root.join(SESSION_HISTORY_DIR_NAME);
+        if history_dir.exists() && fs::remove_dir(&history_dir).is_err() {
+            leftovers.push(history_dir);
+        }
+        let _ = fs::remove_file(self.state_path().with_extension("lock"));
+        leftovers
+    }
+
+    fn write_data_location(&self) -> Result<(), AppError> {
+        let pointer = self.data_location_path();
+        if self.config_dir.canonicalize().ok().as_ref() == Some(&self.root) {
+            if pointer.exists() {
+                fs::remove_file(&pointer)
+                    .map_err(|e| AppError::io("Failed to reset data location", e))?;
+            }
+            return Ok(());
+        }
+
+        let data = serde_json::to_vec_pretty(&DataLocation {
+            data_directory: self.root.clone(),
+        })
+        .map_err(AppError::settings_corrupt)?;
+        let temporary_path = pointer.with_extension("tmp");
+        fs::write(&temporary_path, data)
+            .and_then(|_| fs::rename(&temporary_path, &pointer))
+            .map_err(|e| AppError::io("Failed to save data location", e))
+    }
+}
+
+fn same_contents(left: &Path, right: &Path) -> Result<bool, AppError> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))
+    };
+    Ok(read(left)? == read(right)?)
+}
+
+fn copy_atomically(source: &Path, destination: &Path) -> Result<(), AppError> {
+    let data = fs::read(source)
+        .map_err(|e| AppError::io(format!("Failed to read {}", source.display()), e))?;
+    replace_file(destination, &data, "migrate")
+}
+
+/// Replace `destination` with `data` through a temporary file renamed into
+/// place, so a crash leaves either the old contents or the new ones. Callers
+/// serialize writers to the same file with their own lock.
+pub(crate) fn write_atomically(destination: &Path, data: &[u8]) -> Result<(), AppError> {
+    replace_file(destination, data, "save")
+}
+
+fn replace_file(destination: &Path, data: &[u8], purpose: &str) -> Result<(), AppError> {
+    let temporary_path =
+        destination.with_extension(format!("tmp-{purpose}-{}", std::process::id()));
+    let result = (|| -> Result<(), AppError> {
+        let mut file = File::create(&temporary_path).map_err(|e| {
+            AppError::io(format!("Failed to create {}", temporary_path.display()), e)
+        })?;
+        file.write_all(data)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| {
+                AppError::io(format!("Failed to write {}", temporary_path.display()), e)
+            })?;
+        fs::rename(&temporary_path, destination)
+            .map_err(|e| AppError::io(format!("Failed to finalize {}", destination.display()), e))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn sync_directory(dir: &Path) -> Result<(), AppError> {
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| AppError::io("Failed to sync the new data directory", e))
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_dir: &Path) -> Result<(), AppError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn synthetic_entry(timestamp: &str) -> String {
+        serde_json::json!({
+            "timestamp": timestamp,
+            "session_goal": "Synthetic goal",
+            "reported_status": "On Task",
+            "notes": "Synthetic note"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn migration_moves_every_data_file_and_is_followed_on_reopen() {
+        let dir = temporary_dir("storage-migrate");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        logs::append_entry(&storage, &synthetic_entry("2025-01-01T09:00:00Z")).unwrap();
+        logs::append_entry(&storage, &synthetic_entry("2025-01-01T09:20:00Z")).unwrap();
+        fs::write(storage.settings_path(), "{}").unwrap();
+        let history = storage.root().join(SESSION_HISTORY_DIR_NAME);
+        fs::create_dir_all(&history).unwrap();
+        fs::write(history.join("1735722000000.json"), "{}").unwrap();
+
+        let migrated = storage.migrate_to(&dir.join("synced")).unwrap();
+
+        assert!(!storage.log_path().exists());
+        assert!(!storage.settings_path().exists());
+        assert!(!history.exists());
+        assert_eq!(
+            fs::read_to_string(
+                migrated
+                    .root()
+                    .join(SESSION_HISTORY_DIR_NAME)
+                    .join("1735722000000.json")
+            )
+            .unwrap(),
+            "{}"
+        );
+        assert_eq!(fs::read_to_string(migrated.settings_path()).unwrap(), "{}");
+        assert_eq!(logs::diagnostics(&migrated).unwrap().valid_records, 2);
+        assert_eq!(
+            migrated.control_socket_path(),
+            storage.control_socket_path()
+        );
+        assert_eq!(Storage::open(dir.join("config")).unwrap(), migrated);
+
+        // Moving back to the config directory drops the pointer again.
+        let restored = migrated.migrate_to(&dir.join("config")).unwrap();
+        assert!(!restored.data_location_path().exists());
+        assert_eq!(logs::diagnostics(&restored).unwrap().valid_records, 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migration_finishes_when_an_original_cannot_be_removed() {
+        let dir = temporary_dir("storage-migrate-leftover");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        logs::append_entry(&storage, &synthetic_entry("2025-01-01T09:00:00Z")).unwrap();
+        let history = storage.root().join(SESSION_HISTORY_DIR_NAME);
+        fs::create_dir_all(history.join("stray")).unwrap();
+        fs::write(history.join("1735722000000.json"), "{}").unwrap();
+
+        let migrated = storage.migrate_to(&dir.join("synced")).unwrap();
+
+        // The stray folder keeps the old history directory, but everything
+        // that was moved is gone from it and the new location is in use.
+        assert_eq!(Storage::open(dir.join("config")).unwrap(), migrated);
+        assert!(!storage.log_path().exists());
+        assert!(!history.join("1735722000000.json").exists());
+        assert!(history.join("stray").exists());
+        assert_eq!(logs::diagnostics(&migrated).unwrap().valid_records, 1);
+        assert_eq!(migrated.history_files().unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migration_refuses_a_target_holding_a_different_journal() {
+        let dir = temporary_dir("storage-migrate-conflict");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        logs::append_entry(&storage, &synthetic_entry("2025-01-01T09:00:00Z")).unwrap();
+        let target = Storage::open(dir.join("other")).unwrap();
+        logs::append_entry(&target, &synthetic_entry("2025-02-01T09:00:00Z")).unwrap();
+
+        let error = storage.migrate_to(target.root()).unwrap_err();
+
+        assert_eq!(error.code(), "data_directory_conflict");
+        assert_eq!(logs::diagnostics(&storage).unwrap().valid_records, 1);
+        assert_eq!(Storage::open(dir.join("config")).unwrap(), storage);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migration_refuses_any_differing_file_and_rolls_back_only_what_it_created() {
+        let dir = temporary_dir("storage-migrate-files");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        logs::append_entry(&storage, &synthetic_entry("2025-01-01T09:00:00Z")).unwrap();
+        fs::write(storage.settings_path(), "{}").unwrap();
+        fs::write(storage.projects_path(), "[]").unwrap();
+        let history = storage.root().join(SESSION_HISTORY_DIR_NAME);
+        fs::create_dir_all(&history).unwrap();
+        fs::write(history.join("1735722000000.json"), "{}").unwrap();
+        let target = dir.join("synced");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join(SETTINGS_FILE_NAME), r#"{"theme":"dark"}"#).unwrap();
+
+        let error = storage.migrate_to(&target).unwrap_err();
+        assert_eq!(
+            error,
+            AppError::DataDirectoryConflict(
+                target.canonicalize().unwrap().join(SETTINGS_FILE_NAME)
+            )
+        );
+        assert_eq!(
+            fs::read_to_string(target.join(SETTINGS_FILE_NAME)).unwrap(),
+            r#"{"theme":"dark"}"#
+        );
+        assert!(!target.join(FOCUS_LOG_FILE_NAME).exists());
+
+        // Identical files are kept; a failure after copying removes only the
+        // copies, here because the history directory cannot be created.
+        fs::write(target.join(SETTINGS_FILE_NAME), "{}").unwrap();
+        fs::copy(storage.log_path(), target.join(FOCUS_LOG_FILE_NAME)).unwrap();
+        fs::write(target.join(SESSION_HISTORY_DIR_NAME), "").unwrap();
+        assert!(storage.migrate_to(&target).is_err());
+        assert!(target.join(SETTINGS_FILE_NAME).exists());
+        assert!(target.join(FOCUS_LOG_FILE_NAME).exists());
+        assert!(!target.join(PROJECTS_FILE_NAME).exists());
+        assert!(storage.projects_path().exists());
+        assert_eq!(Storage::open(dir.join("config")).unwrap(), storage);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migration_without_a_journal_adopts_the_targets() {
+        let dir = temporary_dir("storage-migrate-adopt");
+        let storage = Storage::open(dir.join("config")).unwrap();
+        fs::write(storage.settings_path(), "{}").unwrap();
+        let target = Storage::open(dir.join("synced")).unwrap();
+        logs::append_entry(&target, &synthetic_entry("2025-02-01T09:00:00Z")).unwrap();
+
+        let migrated = storage.migrate_to(target.root()).unwrap();
+
+        assert_eq!(logs::diagnostics(&migrated).unwrap().valid_records, 1);
+        assert_eq!(fs::read_to_string(migrated.settings_path()).unwrap(), "{}");
+        assert!(!storage.settings_path().exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! Session templates: reusable goal and timer setups, stored next to settings
+//! in `session_templates.json`.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+
+use crate::{
+    error::AppError,
+    stats,
+    storage::{self, Storage},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    /// Short stable name used by commands and recorded in the journal, e.g.
+    /// "code-review".
+    pub id: String,
+    /// Goal for sessions started from the template. `{date}` and `{weekday}`
+    /// expand to the local start date, e.g. "Writing — {weekday}".
+    pub goal_pattern: String,
+    pub session_duration: u32,
+    pub check_in_interval: u32,
+    pub write_time: u32,
+    /// Calendar events whose title contains this text start from the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_tag: Option<String>,
+    /// The check-in prompt's answers to offer during the session, by status
+    /// name; empty offers all of them. Skip is always offered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<String>,
+}
+
+impl SessionTemplate {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a template id that is not letters, digits, '-' or '_'",
+            });
+        }
+        if self.session_duration == 0 || self.check_in_interval == 0 || self.write_time == 0 {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a template with a zero timer value",
+            });
+        }
+        if self.check_in_interval > self.session_duration {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a template whose check-in interval is longer than its session",
+            });
+        }
+        if self.goal_pattern.trim().is_empty() {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a template without a goal",
+            });
+        }
+        if self
+            .statuses
+            .iter()
+            .any(|status| stats::status_kind(status).is_none())
+        {
+            return Err(AppError::SettingsInvalid {
+                reason: "contain a template with an unknown check-in status",
+            });
+        }
+        Ok(())
+    }
+
+    pub fn goal_on(&self, date: NaiveDate) -> String {
+        self.goal_pattern
+            .replace("{date}", &date.format("%Y-%m-%d").to_string())
+            .replace("{weekday}", &date.format("%A").to_string())
+    }
+}
+
+/// The template for a calendar event, matched case-insensitively on its tag.
+pub fn for_calendar_event<'a>(
+    templates: &'a [SessionTemplate],
+    event_title: &str,
+) -> Option<&'a SessionTemplate> {
+    let title = event_title.to_lowercase();
+    templates.iter().find(|template| {
+        template
+            .calendar_tag
+            .as_deref()
+            .is_some_and(|tag| !tag.is_empty() && title.contains(&tag.to_lowercase()))
+    })
+}
+
+pub fn load(storage: &Storage) -> Result<Vec<SessionTemplate>, AppError> {
+    let _lock = lock_templates(storage)?;
+    load_locked(storage)
+}
+
+fn load_locked(storage: &Storage) -> Result<Vec<SessionTemplate>, AppError> {
+    let path = storage.templates_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read(path).map_err(|e| AppError::io("Failed to read session templates", e))?;
+    serde_json::from_slice(&data).map_err(AppError::settings_corrupt)
+}
+
+pub fn find(storage: &Storage, id: &str) -> Result<SessionTemplate, AppError> {
+    load(storage)?
+        .into_iter()
+        .find(|template| template.id == id)
+        .ok_or(AppError::InvalidArgument("no session template has that id"))
+}
+
+/// Add a template, or replace the one with the same id.
+pub fn save(storage: &Storage, template: SessionTemplate) -> Result<(), AppError> {
+    template.validate()?;
+    let _lock = lock_templates(storage)?;
+    let mut templates = load_locked(storage)?;
+    match templates.iter_mut().find(|saved| saved.id == template.id) {
+        Some(saved) => *saved = template,
+        None => templates.push(template),
+    }
+    write(storage, &templates)
+}
+
+pub fn delete(storage: &Storage, id: &str) -> Result<(), AppError> {
+    let _lock = lock_templates(storage)?;
+    let mut templates = load_locked(storage)?;
+    let before = templates.len();
+    templates.retain(|template| template.id != id);
+    if templates.len() == before {
+        return Err(AppError::InvalidArgument("no session template has that id"));
+    }
+    write(storage, &templates)
+}
+
+fn write(storage: &Storage, templates: &[SessionTemplate]) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(templates).map_err(AppError::settings_corrupt)?;
+    storage::write_atomically(&storage.templates_path(), &data)
+}
+
+/// Serializes the read-modify-write of the templates between the app and the CLI.
+fn lock_templates(storage: &Storage) -> Result<File, AppError> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(storage.templates_path().with_extension("lock"))
+        .map_err(|e| AppError::io("Failed to open session templates lock", e))?;
+    lock.lock()
+        .map_err(|e| AppError::io("Failed to lock session templates", e))?;
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn code_review() -> SessionTemplate {
+        SessionTemplate {
+            id: "code-review".to_string(),
+            goal_pattern: "Code review block {date} ({weekday})".to_string(),
+            session_duration: 60,
+            check_in_interval: 15,
+            write_time: 20,
+            calendar_tag: Some("[review]".to_string()),
+            statuses: vec!["On Task".to_string(), "Email/Chat".to_string()],
+        }
+    }
+
+    #[test]
+    fn templates_are_saved_replaced_and_deleted_by_id() {
+        let dir = temporary_dir("templates");
+        let storage = Storage::open(&dir).unwrap();
+        assert_eq!(load(&storage).unwrap(), vec![]);
+
+        save(&storage, code_review()).unwrap();
+        let writing = SessionTemplate {
+            id: "writing".to_string(),
+            goal_pattern: "Writing".to_string(),
+            session_duration: 120,
+            check_in_interval: 30,
+            calendar_tag: None,
+            statuses: vec![],
+            ..code_review()
+        };
+        save(&storage, writing.clone()).unwrap();
+        let shorter = SessionTemplate {
+            session_duration: 45,
+            ..code_review()
+        };
+        save(&storage, shorter.clone()).unwrap();
+        assert_eq!(load(&storage).unwrap(), vec![shorter, writing.clone()]);
+
+        assert!(save(
+            &storage,
+            SessionTemplate {
+                id: "../settings".to_string(),
+                ..writing.clone()
+            }
+        )
+        .is_err());
+        delete(&storage, "code-review").unwrap();
+        assert!(delete(&storage, "code-review").is_err());
+        assert_eq!(find(&storage, "writing").unwrap(), writing);
+        assert!(find(&storage, "code-review").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn goal_pattern_expands_the_date_and_calendar_tags_pick_a_template() {
+        let template = code_review();
+        assert_eq!(
+            template.goal_on(NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()),
+            "Code review block 2025-03-03 (Monday)"
+        );
+
+        let templates = vec![code_review()];
+        assert_eq!(
+            for_calendar_event(&templates, "Team [Review] hour"),
+            Some(&templates[0])
+        );
+        assert_eq!(for_calendar_event(&templates, "Standup"), None);
+    }
+
+    #[test]
+    fn validation_needs_a_goal_known_statuses_and_an_interval_within_the_session() {
+        assert!(code_review().validate().is_ok());
+        assert!(SessionTemplate {
+            check_in_interval: 60,
+            statuses: vec!["Skip".to_string(), "Taking a Break".to_string()],
+            ..code_review()
+        }
+        .validate()
+        .is_ok());
+        for invalid in [
+            SessionTemplate {
+                goal_pattern: " ".to_string(),
+                ..code_review()
+            },
+            SessionTemplate {
+                statuses: vec!["On Task".to_string(), "Napping".to_string()],
+                ..code_review()
+            },
+            SessionTemplate {
+                check_in_interval: 61,
+                ..code_review()
+            },
+            SessionTemplate {
+                write_time: 0,
+                ..code_review()
+            },
+        ] {
+            assert_eq!(invalid.validate().unwrap_err().code(), "settings_invalid");
+        }
+    }
+
+    #[test]
+    fn saving_replaces_the_file_atomically_under_a_lock() {
+        let dir = temporary_dir("templates-atomic");
+        let storage = Storage::open(&dir).unwrap();
+        let writers: Vec<_> = (0..8)
+            .map(|index| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    save(
+                        &storage,
+                        SessionTemplate {
+                            id: format!("block-{index}"),
+                            ..code_review()
+                        },
+                    )
+                    .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(load(&storage).unwrap().len(), 8);
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().contains(".tmp-"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+//! Backend detection of sleep and clock changes.
+//!
+//! The webview's own sleep-gap check stops running while the machine sleeps
+//! and cannot tell a suspend from a clock change. The watchdog polls from a
+//! backend thread and compares how far monotonic time and wall-clock time
+//! moved between polls: monotonic time does not advance while the machine is
+//! suspended and is unaffected by NTP or manual clock changes, so any
+//! difference is one of those.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{thread, time::Duration};
+
+use crate::{
+    clock::Clock,
+    error::AppError,
+    session_state::{self, ActiveSessionState, SessionPhase},
+    storage::Storage,
+};
+
+/// Wall-clock drift below this is ordinary scheduling jitter.
+const DRIFT_TOLERANCE_MS: i64 = 2_000;
+/// A forward gap at least this long is treated as a suspend. A manual clock
+/// change of the same size looks identical; pausing is the safe reading.
+const SUSPEND_THRESHOLD_MS: i64 = 60_000;
+/// Matches the webview: shorter sleeps let the timers catch up instead.
+const PAUSE_AFTER_SLEEP_MS: i64 = 5 * 60_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ClockChange {
+    /// Wall-clock time moved on while monotonic time did not.
+    Suspended { slept_ms: i64 },
+    /// The wall clock was set forward or back, e.g. by NTP.
+    ClockJumped { shift_ms: i64 },
+    /// UTC is unaffected; only local display times change.
+    TimezoneChanged {
+        from_offset_seconds: i32,
+        to_offset_seconds: i32,
+    },
+}
+
+impl ClockChange {
+    /// A sentence for the status line explaining what happened.
+    pub fn description(&self, reconciled: Option<&ActiveSessionState>) -> String {
+        match self {
+            Self::Suspended { slept_ms } => {
+                let minutes = slept_ms / 60_000;
+                match reconciled {
+                    Some(state) if state.phase == SessionPhase::Paused => {
+                        format!("Paused: computer was asleep for {minutes} min")
+                    }
+                    _ => format!("Computer was asleep for {minutes} min"),
+                }
+            }
+            Self::ClockJumped { shift_ms } => format!(
+                "System clock moved {} by {} s; timers adjusted",
+                if *shift_ms > 0 { "forward" } else { "back" },
+                shift_ms.abs() / 1000
+            ),
+            Self::TimezoneChanged {
+                to_offset_seconds, ..
+            } => format!(
+                "Time zone changed to UTC{:+03}:{:02}",
+                to_offset_seconds / 3600,
+                (to_offset_seconds.abs() % 3600) / 60
+            ),
+        }
+    }
+}
+
+pub struct Watchdog<C: Clock> {
+    clock: C,
+    last_monotonic: Duration,
+    last_wall: DateTime<Utc>,
+    last_offset: i32,
+}
+
+impl<C: Clock> Watchdog<C> {
+    pub fn new(clock: C) -> Self {
+        Self {
+            last_monotonic: clock.monotonic(),
+            last_wall: clock.now(),
+            last_offset: clock.utc_offset_seconds(),
+            clock,
+        }
+    }
+
+    /// Compare the clocks against the previous poll.
+    pub fn poll(&mut self) -> Vec<ClockChange> {
+        let monotonic = self.clock.monotonic();
+        let wall = self.clock.now();
+        let offset = self.clock.utc_offset_seconds();
+
+        let monotonic_elapsed = (monotonic - self.last_monotonic).as_millis() as i64;
+        let wall_elapsed = (wall - self.last_wall).num_milliseconds();
+        let drift = wall_elapsed - monotonic_elapsed;
+        let mut changes = Vec::new();
+        if drift >= SUSPEND_THRESHOLD_MS {
+            changes.push(ClockChange::Suspended { slept_ms: drift });
+        } else if drift.abs() >= DRIFT_TOLERANCE_MS {
+            changes.push(ClockChange::ClockJumped { shift_ms: drift });
+        }
+        if offset != self.last_offset {
+            changes.push(ClockChange::TimezoneChanged {
+                from_offset_seconds: self.last_offset,
+                to_offset_seconds: offset,
+            });
+        }
+
+        self.last_monotonic = monotonic;
+        self.last_wall = wall;
+        self.last_offset = offset;
+        changes
+    }
+}
+
+/// Poll every `interval` for the life of the process.
+pub fn run<C: Clock>(clock: C, interval: Duration, mut on_change: impl FnMut(ClockChange)) {
+    let mut watchdog = Watchdog::new(clock);
+    loop {
+        thread::sleep(interval);
+        for change in watchdog.poll() {
+            on_change(change);
+        }
+    }
+}
+
+/// Bring the saved session in line with a clock change; returns the state
+/// when it was modified.
+///
+/// A long suspend pauses a running session at its last saved remainders, so
+/// the sleep does not count as focus or break time. A clock jump shifts the
+/// saved wall-clock timestamps by the same amount so restart recovery does
+/// not misjudge the downtime.
+pub fn reconcile(
+    storage: &Storage,
+    change: &ClockChange,
+    clock: &dyn Clock,
+) -> Result<Option<ActiveSessionState>, AppError> {
+    session_state::update(storage, clock, |state| {
+        match change {
+            ClockChange::Suspended { slept_ms }
+                if *slept_ms >= PAUSE_AFTER_SLEEP_MS && state.phase.is_running() =>
+            {
+                state.phase = SessionPhase::Paused;
+                state.recovery_reason = Some(change.description(Some(state)));
+            }
+            ClockChange::ClockJumped { shift_ms } => state.shift_timestamps(*shift_ms),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{SimulatedClock, SystemClock};
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let counter = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "hyper-awareness-{name}-{}-{counter}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn watchdog_tells_suspends_clock_jumps_and_timezone_changes_apart() {
+        let clock = SimulatedClock::new(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        let mut watchdog = Watchdog::new(clock.clone());
+        let tick = Duration::from_secs(5);
+
+        clock.advance(tick);
+        clock.jump(chrono::Duration::milliseconds(500));
+        assert_eq!(watchdog.poll(), vec![]);
+
+        clock.advance(tick);
+        clock.suspend(Duration::from_secs(20 * 60));
+        assert_eq!(
+            watchdog.poll(),
+            vec![ClockChange::Suspended {
+                slept_ms: 20 * 60_000
+            }]
+        );
+
+        clock.advance(tick);
+        clock.jump(chrono::Duration::milliseconds(-90_000));
+        assert_eq!(
+            watchdog.poll(),
+            vec![ClockChange::ClockJumped { shift_ms: -90_000 }]
+        );
+
+        clock.advance(tick);
+        clock.jump(chrono::Duration::milliseconds(15_000));
+        clock.set_utc_offset(3600);
+        assert_eq!(
+            watchdog.poll(),
+            vec![
+                ClockChange::ClockJumped { shift_ms: 15_000 },
+                ClockChange::TimezoneChanged {
+                    from_offset_seconds: 0,
+                    to_offset_seconds: 3600
+                }
+            ]
+        );
+    }
+
+    fn saved_state(phase: SessionPhase) -> ActiveSessionState {
+        let mut state: ActiveSessionState = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "phase": "active",
+            "sessionGoal": "Synthetic goal",
+            "sessionStartedAt": 1_700_000_000_000i64,
+            "sessionDuration": 60,
+            "checkInInterval": 15,
+            "writeTime": 20,
+            "sessionTimeRemaining": 1800,
+            "checkInTimeRemaining": 300,
+            "writeTimeRemaining": 20,
+            "checkInsCompleted": 2,
+            "skippedCheckIns": 0,
+            "lastCheckInWasSkipped": false,
+            "focusShieldActive": true,
+            "focusShieldUntil": 1_700_000_600_000i64,
+            "recoveryReason": null,
+            "lastHeartbeatAt": 1_700_000_300_000i64,
+            "promptShownAt": 1_700_000_290_000i64,
+            "subtasks": [
+                { "id": 1, "text": "Synthetic step", "completedAt": 1_700_000_100_000i64 },
+                { "id": 2, "text": "Synthetic step", "completedAt": null }
+            ],
+            "pomodoro": {
+                "phase": "work",
+                "workMinutes": 25,
+                "shortBreakMinutes": 5,
+                "longBreakMinutes": 15,
+                "cyclesBeforeLongBreak": 4,
+                "autoStart": false,
+                "cyclesCompleted": 1,
+                "phaseTimeRemaining": 240,
+                "breakSeconds": 0
+            }
+        }))
+        .unwrap();
+        if phase == SessionPhase::Break {
+            state.pomodoro.as_mut().unwrap().phase = session_state::PomodoroPhase::ShortBreak;
+        }
+        state.phase = phase;
+        state
+    }
+
+    #[test]
+    fn a_clock_jump_shifts_every_saved_timestamp_in_any_phase() {
+        let dir = temporary_dir("watchdog-jump");
+        let storage = Storage::open(&dir).unwrap();
+        let jump = ClockChange::ClockJumped { shift_ms: -90_000 };
+
+        for phase in [
+            SessionPhase::Active,
+            SessionPhase::Writing,
+            SessionPhase::Break,
+            SessionPhase::Paused,
+            SessionPhase::Interrupted,
+        ] {
+            let saved = saved_state(phase.clone());
+            session_state::save_to_path(&storage.state_path(), &saved, &SystemClock).unwrap();
+            let shifted = reconcile(&storage, &jump, &SystemClock).unwrap().unwrap();
+            assert_eq!(shifted.phase, phase);
+            assert_eq!(shifted.session_started_at, Some(1_699_999_910_000));
+            assert_eq!(shifted.last_heartbeat_at, Some(1_700_000_210_000));
+            assert_eq!(shifted.focus_shield_until, Some(1_700_000_510_000));
+            assert_eq!(shifted.prompt_shown_at, Some(1_700_000_200_000));
+            let completed: Vec<_> = shifted.subtasks.iter().map(|s| s.completed_at).collect();
+            assert_eq!(completed, [Some(1_700_000_010_000), None]);
+            assert_eq!(session_state::load(&storage).unwrap(), Some(shifted));
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_long_sleep_pauses_every_running_phase_at_its_saved_remainders() {
+        let dir = temporary_dir("watchdog-sleep");
+        let storage = Storage::open(&dir).unwrap();
+        let short_nap = ClockChange::Suspended { slept_ms: 90_000 };
+        let sleep = ClockChange::Suspended {
+            slept_ms: 20 * 60_000,
+        };
+
+        for phase in [
+            SessionPhase::Active,
+            SessionPhase::Writing,
+            SessionPhase::Break,
+        ] {
+            let saved = saved_state(phase);
+            session_state::save_to_path(&storage.state_path(), &saved, &SystemClock).unwrap();
+            assert_eq!(reconcile(&storage, &short_nap, &SystemClock).unwrap(), None);
+
+            let paused = reconcile(&storage, &sleep, &SystemClock).unwrap().unwrap();
+            assert_eq!(paused.phase, SessionPhase::Paused);
+            assert_eq!(paused.session_time_remaining, saved.session_time_remaining);
+            assert_eq!(paused.write_time_remaining, saved.write_time_remaining);
+            assert_eq!(paused.pomodoro, saved.pomodoro);
+            assert_eq!(
+                sleep.description(Some(&paused)),
+                "Paused: computer was asleep for 20 min"
+            );
+            assert_eq!(session_state::load(&storage).unwrap(), Some(paused));
+            assert_eq!(reconcile(&storage, &sleep, &SystemClock).unwrap(), None);
+        }
+
+        let interrupted = saved_state(SessionPhase::Interrupted);
+        session_state::save_to_path(&storage.state_path(), &interrupted, &SystemClock).unwrap();
+        assert_eq!(reconcile(&storage, &sleep, &SystemClock).unwrap(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,534 @@
+//! Pure window placement geometry.
+//!
+//! Every input and output is in physical pixels of the shared desktop space,
+//! except the window size, which is logical so it can be scaled to whichever
+//! monitor the window lands on. Keeping this free of Tauri types lets the
+//! mixed-DPI and negative-origin layouts be tested without a display.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{error::AppError, storage};
+
+/// Logical padding kept between the window and the monitor edge.
+const EDGE_MARGIN: f64 = 10.0;
+/// Logical gap between the tray icon and the window.
+const TRAY_GAP: f64 = 5.0;
+/// Logical distance from the top of the monitor for menu-bar style placement.
+const TOP_OFFSET: f64 = 50.0;
+/// Logical distance from the right edge to the usual menu bar icon area.
+const TRAY_AREA_FROM_RIGHT: f64 = 250.0;
+/// Tray icon size used when the platform does not report its rectangle.
+pub const DEFAULT_TRAY_ICON_SIZE: f64 = 22.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowPlacement {
+    TrayAnchored,
+    Centered,
+    RightEdge,
+    RememberLast,
+}
+
+impl WindowPlacement {
+    /// Map the `window_position` setting onto a placement mode. Unknown values
+    /// fall back to the tray-anchored default ("auto").
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "centered" => Self::Centered,
+            "right-edge" => Self::RightEdge,
+            "remember-last" => Self::RememberLast,
+            _ => Self::TrayAnchored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl MonitorRect {
+    fn right(&self) -> f64 {
+        self.x as f64 + self.width as f64
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y as f64 + self.height as f64
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64 && x < self.right() && y >= self.y as f64 && y < self.bottom()
+    }
+
+    fn scaled(&self, logical: f64) -> f64 {
+        logical * self.scale_factor
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrayRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl TrayRect {
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogicalSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Where the user last left the window on one monitor layout. The position is
+/// physical; the size is logical so it survives a scale change on restore.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowBounds {
+    pub fn position(&self) -> PhysicalPoint {
+        PhysicalPoint {
+            x: self.x,
+            y: self.y,
+        }
+    }
+
+    pub fn size(&self) -> LogicalSize {
+        LogicalSize {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Remembered window bounds keyed by the monitor layout they were saved on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedWindowBounds {
+    layouts: BTreeMap<String, WindowBounds>,
+}
+
+impl SavedWindowBounds {
+    pub fn get(&self, monitors: &[MonitorRect]) -> Option<WindowBounds> {
+        self.layouts.get(&layout_key(monitors)).copied()
+    }
+
+    pub fn remember(&mut self, monitors: &[MonitorRect], bounds: WindowBounds) {
+        if !monitors.is_empty() {
+            self.layouts.insert(layout_key(monitors), bounds);
+        }
+    }
+}
+
+/// Stable identifier for a set of connected monitors, independent of the
+/// order the platform enumerates them in.
+pub fn layout_key(monitors: &[MonitorRect]) -> String {
+    let mut parts: Vec<String> = monitors
+        .iter()
+        .map(|m| {
+            format!(
+                "{},{},{}x{}@{}",
+                m.x, m.y, m.width, m.height, m.scale_factor
+            )
+        })
+        .collect();
+    parts.sort();
+    parts.join(";")
+}
+
+pub fn load_bounds_from_path(path: &Path) -> Result<SavedWindowBounds, AppError> {
+    if !path.exists() {
+        return Ok(SavedWindowBounds::default());
+    }
+    let data =
+        fs::read(path).map_err(|e| AppError::io("Failed to read saved window positions", e))?;
+    serde_json::from_slice(&data).map_err(AppError::settings_corrupt)
+}
+
+pub fn save_bounds_to_path(path: &Path, saved: &SavedWindowBounds) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(saved).map_err(AppError::settings_corrupt)?;
+    storage::write_atomically(path, &data)
+}
+
+/// Everything the placement calculation needs to know about the desktop.
+#[derive(Debug, Clone)]
+pub struct PlacementRequest<'a> {
+    pub placement: WindowPlacement,
+    pub monitors: &'a [MonitorRect],
+    /// The monitor the window currently occupies, if known.
+    pub current_monitor: Option<MonitorRect>,
+    pub tray: Option<TrayRect>,
+    pub window_size: LogicalSize,
+    /// Last position the user left the window at, for `RememberLast`.
+    pub remembered: Option<PhysicalPoint>,
+}
+
+/// Compute the physical top-left corner for the window, or `None` when no
+/// monitor is known at all.
+pub fn calculate_window_position(request: &PlacementRequest) -> Option<PhysicalPoint> {
+    if request.placement == WindowPlacement::RememberLast {
+        if let Some(point) = request.remembered {
+            let (x, y) = (point.x as f64, point.y as f64);
+            if let Some(monitor) = request.monitors.iter().find(|m| m.contains(x, y)) {
+                return Some(clamp_to_monitor(monitor, x, y, request.window_size));
+            }
+        }
+        // The remembered monitor is gone; behave like the default placement.
+        return calculate_window_position(&PlacementRequest {
+            placement: WindowPlacement::TrayAnchored,
+            remembered: None,
+            ..request.clone()
+        });
+    }
+
+    let monitor = target_monitor(request)?;
+    let width = monitor.scaled(request.window_size.width);
+    let height = monitor.scaled(request.window_size.height);
+
+    let (x, y) = match (request.placement, request.tray) {
+        (WindowPlacement::Centered, _) => (
+            monitor.x as f64 + (monitor.width as f64 - width) / 2.0,
+            monitor.y as f64 + (monitor.height as f64 - height) / 2.0,
+        ),
+        (WindowPlacement::RightEdge, _) => (
+            monitor.right() - width - monitor.scaled(EDGE_MARGIN * 2.0),
+            monitor.y as f64 + monitor.scaled(TOP_OFFSET),
+        ),
+        (_, Some(tray)) => {
+            let (center_x, center_y) = tray.center();
+            let gap = monitor.scaled(TRAY_GAP);
+            // Taskbars at the bottom of the screen open the window upwards.
+            let y = if center_y > monitor.y as f64 + monitor.height as f64 / 2.0 {
+                tray.y - height - gap
+            } else {
+                tray.y + tray.height + gap
+            };
+            (center_x - width / 2.0, y)
+        }
+        (_, None) => (
+            monitor.right() - monitor.scaled(TRAY_AREA_FROM_RIGHT) - width / 2.0,
+            monitor.y as f64 + monitor.scaled(TOP_OFFSET),
+        ),
+    };
+
+    Some(clamp_to_monitor(&monitor, x, y, request.window_size))
+}
+
+/// Prefer the monitor holding the tray icon, then the window's own monitor.
+fn target_monitor(request: &PlacementRequest) -> Option<MonitorRect> {
+    request
+        .tray
+        .and_then(|tray| {
+            let (x, y) = tray.center();
+            request.monitors.iter().find(|m| m.contains(x, y)).copied()
+        })
+        .or(request.current_monitor)
+        .or_else(|| request.monitors.first().copied())
+}
+
+fn clamp_to_monitor(monitor: &MonitorRect, x: f64, y: f64, size: LogicalSize) -> PhysicalPoint {
+    let margin = monitor.scaled(EDGE_MARGIN);
+    let width = monitor.scaled(size.width);
+    let height = monitor.scaled(size.height);
+
+    let clamp_axis = |value: f64, start: f64, end: f64, extent: f64| {
+        let min = start + margin;
+        let max = end - extent - margin;
+        if max < min {
+            // The window is larger than the monitor; pin it to the origin.
+            start
+        } else {
+            value.clamp(min, max)
+        }
+    };
+
+    PhysicalPoint {
+        x: clamp_axis(x, monitor.x as f64, monitor.right(), width).round() as i32,
+        y: clamp_axis(y, monitor.y as f64, monitor.bottom(), height).round() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: LogicalSize = LogicalSize {
+        width: 380.0,
+        height: 580.0,
+    };
+
+    const RETINA: MonitorRect = MonitorRect {
+        x: 0,
+        y: 0,
+        width: 2880,
+        height: 1800,
+        scale_factor: 2.0,
+    };
+
+    const FRACTIONAL: MonitorRect = MonitorRect {
+        x: 0,
+        y: 0,
+        width: 2560,
+        height: 1440,
+        scale_factor: 1.5,
+    };
+
+    const LEFT_EXTERNAL: MonitorRect = MonitorRect {
+        x: -1920,
+        y: -200,
+        width: 1920,
+        height: 1080,
+        scale_factor: 1.0,
+    };
+
+    const RIGHT_EXTERNAL: MonitorRect = MonitorRect {
+        x: 2880,
+        y: 0,
+        width: 3840,
+        height: 2160,
+        scale_factor: 1.5,
+    };
+
+    struct Case {
+        name: &'static str,
+        placement: WindowPlacement,
+        monitors: &'static [MonitorRect],
+        current_monitor: Option<MonitorRect>,
+        tray: Option<TrayRect>,
+        remembered: Option<PhysicalPoint>,
+        expected: Option<PhysicalPoint>,
+    }
+
+    fn tray(x: f64, y: f64, size: f64) -> Option<TrayRect> {
+        Some(TrayRect {
+            x,
+            y,
+            width: size,
+            height: size,
+        })
+    }
+
+    fn point(x: i32, y: i32) -> Option<PhysicalPoint> {
+        Some(PhysicalPoint { x, y })
+    }
+
+    #[test]
+    fn window_placement_matches_each_monitor_layout() {
+        let cases = [
+            Case {
+                name: "tray anchored on a retina menu bar",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[RETINA],
+                current_monitor: Some(RETINA),
+                tray: tray(2000.0, 0.0, 44.0),
+                remembered: None,
+                // Icon center 2022 minus half of 760 physical; below 44 + 10 gap.
+                expected: point(1642, 54),
+            },
+            Case {
+                name: "tray near the right edge is clamped inside the monitor",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[RETINA],
+                current_monitor: Some(RETINA),
+                tray: tray(2800.0, 0.0, 44.0),
+                remembered: None,
+                expected: point(2880 - 760 - 20, 54),
+            },
+            Case {
+                name: "fractional scale uses the full physical width",
+                placement: WindowPlacement::RightEdge,
+                monitors: &[FRACTIONAL],
+                current_monitor: Some(FRACTIONAL),
+                tray: None,
+                remembered: None,
+                expected: point(2560 - 570 - 30, 75),
+            },
+            Case {
+                name: "tray on a negative-origin monitor picks that monitor",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[RETINA, LEFT_EXTERNAL],
+                current_monitor: Some(RETINA),
+                tray: tray(-1900.0, -200.0, 22.0),
+                remembered: None,
+                expected: point(-1910, -200 + 22 + 5),
+            },
+            Case {
+                name: "tray on a mixed-dpi secondary monitor scales to that monitor",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[RETINA, RIGHT_EXTERNAL],
+                current_monitor: Some(RETINA),
+                tray: tray(5000.0, 0.0, 32.0),
+                remembered: None,
+                // 570 physical wide at 1.5x; the 7.5 physical gap rounds up.
+                expected: point(5016 - 285, 40),
+            },
+            Case {
+                name: "bottom taskbar tray opens the window upwards",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[LEFT_EXTERNAL],
+                current_monitor: None,
+                tray: tray(-400.0, 840.0, 24.0),
+                remembered: None,
+                expected: point(-578, 840 - 580 - 5),
+            },
+            Case {
+                name: "centered on the current negative-origin monitor",
+                placement: WindowPlacement::Centered,
+                monitors: &[RETINA, LEFT_EXTERNAL],
+                current_monitor: Some(LEFT_EXTERNAL),
+                tray: None,
+                remembered: None,
+                expected: point(-1920 + 770, -200 + 250),
+            },
+            Case {
+                name: "tray anchored without a tray rect uses the menu bar area",
+                placement: WindowPlacement::TrayAnchored,
+                monitors: &[RETINA],
+                current_monitor: Some(RETINA),
+                tray: None,
+                remembered: None,
+                expected: point(2880 - 500 - 380, 100),
+            },
+            Case {
+                name: "remember last restores a position on a connected monitor",
+                placement: WindowPlacement::RememberLast,
+                monitors: &[RETINA, LEFT_EXTERNAL],
+                current_monitor: Some(RETINA),
+                tray: tray(2000.0, 0.0, 44.0),
+                remembered: point(-1500, 100),
+                expected: point(-1500, 100),
+            },
+            Case {
+                name: "remember last falls back to the tray when its monitor is gone",
+                placement: WindowPlacement::RememberLast,
+                monitors: &[RETINA],
+                current_monitor: Some(RETINA),
+                tray: tray(2000.0, 0.0, 44.0),
+                remembered: point(-1500, 100),
+                expected: point(1642, 54),
+            },
+            Case {
+                name: "no monitors yields no position",
+                placement: WindowPlacement::Centered,
+                monitors: &[],
+                current_monitor: None,
+                tray: None,
+                remembered: None,
+                expected: None,
+            },
+        ];
+
+        for case in cases {
+            let request = PlacementRequest {
+                placement: case.placement,
+                monitors: case.monitors,
+                current_monitor: case.current_monitor,
+                tray: case.tray,
+                window_size: WINDOW,
+                remembered: case.remembered,
+            };
+            assert_eq!(
+                calculate_window_position(&request),
+                case.expected,
+                "{}",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn window_larger_than_monitor_is_pinned_to_its_origin() {
+        let small = MonitorRect {
+            x: -800,
+            y: 0,
+            width: 700,
+            height: 600,
+            scale_factor: 2.0,
+        };
+        let request = PlacementRequest {
+            placement: WindowPlacement::Centered,
+            monitors: &[small],
+            current_monitor: Some(small),
+            tray: None,
+            window_size: WINDOW,
+            remembered: None,
+        };
+
+        assert_eq!(
+            calculate_window_position(&request),
+            Some(PhysicalPoint { x: -800, y: 0 })
+        );
+    }
+
+    #[test]
+    fn saved_bounds_are_keyed_by_monitor_layout_regardless_of_order() {
+        let path = std::env::temp_dir().join(format!(
+            "hyper-awareness-window-bounds-{}.json",
+            std::process::id()
+        ));
+        let bounds = WindowBounds {
+            x: -1500,
+            y: 100,
+            width: 420.0,
+            height: 600.0,
+        };
+        let mut saved = load_bounds_from_path(&path).unwrap();
+        saved.remember(&[RETINA, LEFT_EXTERNAL], bounds);
+        save_bounds_to_path(&path, &saved).unwrap();
+        // The file is replaced through a renamed temporary file.
+        assert!(!path
+            .with_extension(format!("tmp-save-{}", std::process::id()))
+            .exists());
+
+        let restored = load_bounds_from_path(&path).unwrap();
+        assert_eq!(restored.get(&[LEFT_EXTERNAL, RETINA]), Some(bounds));
+        // Unplugging the external monitor is a different layout.
+        assert_eq!(restored.get(&[RETINA]), None);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn setting_values_map_to_placements() {
+        assert_eq!(
+            WindowPlacement::from_setting("auto"),
+            WindowPlacement::TrayAnchored
+        );
+        assert_eq!(
+            WindowPlacement::from_setting("right-edge"),
+            WindowPlacement::RightEdge
+        );
+        assert_eq!(
+            WindowPlacement::from_setting("centered"),
+            WindowPlacement::Centered
+        );
+        assert_eq!(
+            WindowPlacement::from_setting("remember-last"),
+            WindowPlacement::RememberLast
+        );
+        assert_eq!(
+            WindowPlacement::from_setting("unknown"),
+            WindowPlacement::TrayAnchored
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Replays a whole 12-hour session against a simulated clock: a save every
+//! minute, check-ins, a sleep, an NTP correction and an app restart. The
+//! session timer itself lives in the webview, so the loop here plays its part.
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hyper_awareness::{
+    clock::{Clock, SimulatedClock},
+    logs,
+    session_state::{self, ActiveSessionState, SessionPhase},
+    settings::Settings,
+    stats,
+    storage::Storage,
+    watchdog::{self, Watchdog},
+};
+use serde_json::json;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn temporary_storage(name: &str) -> (PathBuf, Storage) {
+    let root = std::env::temp_dir().join(format!("hyper-awareness-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let storage = Storage::open(root.join("data")).unwrap();
+    (root, storage)
+}
+
+fn twelve_hour_session() -> ActiveSessionState {
+    serde_json::from_value(json!({
+        "version": 1,
+        "phase": "active",
+        "sessionGoal": "Synthetic goal",
+        "sessionStartedAt": 1_741_000_000_000i64,
+        "sessionDuration": 720,
+        "checkInInterval": 20,
+        "writeTime": 20,
+        "sessionTimeRemaining": 720 * 60,
+        "checkInTimeRemaining": 20 * 60,
+        "writeTimeRemaining": 0,
+        "checkInsCompleted": 0,
+        "skippedCheckIns": 0,
+        "lastCheckInWasSkipped": false,
+        "focusShieldActive": false,
+        "focusShieldUntil": null,
+        "recoveryReason": null
+    }))
+    .unwrap()
+}
+
+#[test]
+fn twelve_hour_session_with_sleep_clock_jump_and_restart_replays_in_simulated_time() {
+    let (root, storage) = temporary_storage("session-replay");
+    let start: DateTime<Utc> = DateTime::parse_from_rfc3339("2025-03-03T08:00:00Z")
+        .unwrap()
+        .into();
+    let clock = SimulatedClock::new(start);
+    // Auto-resume is opt-in; the replay turns it on.
+    let settings = Settings {
+        auto_resume_minutes: 5,
+        ..Settings::default()
+    };
+    let mut watchdog = Watchdog::new(clock.clone());
+    let mut state = twelve_hour_session();
+    let mut pauses = 0;
+
+    for minute in 1.. {
+        clock.advance(MINUTE);
+        for change in watchdog.poll() {
+            if let Some(reconciled) = watchdog::reconcile(&storage, &change, &clock).unwrap() {
+                state = reconciled;
+            }
+        }
+        if state.phase == SessionPhase::Paused {
+            // The user comes back and resumes right away.
+            state.phase = SessionPhase::Active;
+            pauses += 1;
+        }
+
+        state.session_time_remaining -= 60;
+        state.check_in_time_remaining -= 60;
+        if state.check_in_time_remaining == 0 {
+            let entry = state.check_in_entry("On Task", None, clock.now());
+            logs::append_entry(&storage, &entry.to_string()).unwrap();
+            state.check_ins_completed += 1;
+            state.check_in_time_remaining = 20 * 60;
+        }
+        session_state::save(&storage, state.clone(), &clock).unwrap();
+        if state.session_time_remaining == 0 {
+            break;
+        }
+
+        match minute {
+            // The lid closes for 45 minutes.
+            150 => clock.suspend(45 * MINUTE),
+            // NTP pulls the wall clock back half a minute.
+            400 => clock.jump(chrono::Duration::seconds(-30)),
+            // The app dies and is restarted 3 minutes later.
+            600 => {
+                clock.advance(3 * MINUTE);
+                state = session_state::recover(&storage, (&settings).into(), &clock)
+                    .unwrap()
+                    .unwrap();
+                watchdog = Watchdog::new(clock.clone());
+            }
+            _ => {}
+        }
+    }
+
+    // Only the sleep paused the session, so the sleep was not counted as focus
+    // time; the restart resumed it automatically.
+    assert_eq!(pauses, 1);
+    assert_eq!(
+        clock.now() - start,
+        chrono::Duration::minutes(720 + 45 + 3) - chrono::Duration::seconds(30)
+    );
+
+    let journal = logs::read_entries_from_path(&storage.log_path()).unwrap();
+    let summary = stats::summarize(&journal, start, clock.now() + chrono::Duration::seconds(1));
+    assert_eq!(summary.total_check_ins, 36);
+    assert_eq!(summary.focused_minutes, 720);
+    let recoveries: Vec<_> = journal
+        .iter()
+        .filter(|entry| !entry.is_check_in())
+        .map(|entry| entry.details.as_ref().unwrap()["decision"].clone())
+        .collect();
+    assert_eq!(recoveries, vec![json!("resumed")]);
+
+    fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,142 @@
+//! Exercises the persistence layer through `Storage` over a temporary directory,
+//! the same way the app and the CLI reach it, without a Tauri runtime.
+
+use std::{fs, path::PathBuf};
+
+use chrono::{Duration, Utc};
+use hyper_awareness::{
+    clock::SystemClock,
+    logs,
+    session_state::{self, ActiveSessionState, RecoveryPolicy, SessionPhase},
+    settings::{self, Settings},
+    storage::Storage,
+};
+use serde_json::json;
+
+fn temporary_storage(name: &str) -> (PathBuf, Storage) {
+    let root = std::env::temp_dir().join(format!("hyper-awareness-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let storage = Storage::open(root.join("data")).unwrap();
+    (root, storage)
+}
+
+fn sample_state(phase: SessionPhase) -> ActiveSessionState {
+    serde_json::from_value(json!({
+        "version": 1,
+        "phase": phase,
+        "sessionGoal": "Synthetic goal",
+        "sessionStartedAt": 1_700_000_000_000i64,
+        "sessionDuration": 60,
+        "checkInInterval": 15,
+        "writeTime": 20,
+        "sessionTimeRemaining": 1800,
+        "checkInTimeRemaining": 300,
+        "writeTimeRemaining": 20,
+        "checkInsCompleted": 2,
+        "skippedCheckIns": 0,
+        "lastCheckInWasSkipped": false,
+        "focusShieldActive": false,
+        "focusShieldUntil": null,
+        "recoveryReason": null
+    }))
+    .unwrap()
+}
+
+#[test]
+fn settings_round_trip_and_default_when_missing() {
+    let (root, storage) = temporary_storage("storage-settings");
+    assert!(storage.root().is_dir());
+    assert_eq!(settings::load(&storage).unwrap(), Settings::default());
+
+    let custom = Settings {
+        session_duration: 90,
+        check_in_interval: 10,
+        write_time: 30,
+        window_position: "centered".to_string(),
+        ..Settings::default()
+    };
+    settings::save(&storage, &custom).unwrap();
+    assert_eq!(settings::load(&storage).unwrap(), custom);
+    assert!(storage.settings_path().starts_with(storage.root()));
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn session_state_saves_recovers_and_clears() {
+    let (root, storage) = temporary_storage("storage-session");
+    assert_eq!(session_state::load(&storage).unwrap(), None);
+
+    session_state::save(&storage, sample_state(SessionPhase::Active), &SystemClock).unwrap();
+    let saved = session_state::load(&storage).unwrap().unwrap();
+    assert!(saved.last_heartbeat_at.is_some());
+    assert_eq!(
+        saved,
+        ActiveSessionState {
+            last_heartbeat_at: saved.last_heartbeat_at,
+            ..sample_state(SessionPhase::Active)
+        }
+    );
+
+    // Restarting right away resumes when auto-resume is on; asking every
+    // time, the default, marks it interrupted. Both decisions are journaled.
+    let auto_resume = Settings {
+        auto_resume_minutes: 5,
+        ..Settings::default()
+    };
+    let resumed = session_state::recover(&storage, (&auto_resume).into(), &SystemClock)
+        .unwrap()
+        .unwrap();
+    assert_eq!(resumed.phase, SessionPhase::Active);
+    let recovered = session_state::recover(&storage, RecoveryPolicy::MANUAL, &SystemClock)
+        .unwrap()
+        .unwrap();
+    assert_eq!(recovered.phase, SessionPhase::Interrupted);
+    let decisions: Vec<_> = logs::read_entries_from_path(&storage.log_path())
+        .unwrap()
+        .into_iter()
+        .map(|entry| {
+            assert_eq!(
+                entry.record_type.as_deref(),
+                Some(logs::SESSION_RECOVERY_RECORD)
+            );
+            entry.details.unwrap()["decision"].clone()
+        })
+        .collect();
+    assert_eq!(decisions, vec![json!("resumed"), json!("interrupted")]);
+    assert!(logs::read_since(&storage, Utc::now() - Duration::days(1))
+        .unwrap()
+        .is_empty());
+
+    session_state::clear(&storage).unwrap();
+    assert_eq!(session_state::load(&storage).unwrap(), None);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn journal_appends_read_back_and_report_diagnostics() {
+    let (root, storage) = temporary_storage("storage-journal");
+    let state = sample_state(SessionPhase::Active);
+    let now = Utc::now();
+
+    for (offset, status) in [(2, "On Task"), (1, "Distracted")] {
+        let entry = state.check_in_entry(
+            status,
+            Some("Synthetic note"),
+            now - Duration::hours(offset),
+        );
+        logs::append_entry(&storage, &entry.to_string()).unwrap();
+    }
+
+    let recent = logs::read_since(&storage, now - Duration::minutes(90)).unwrap();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].status, "Distracted");
+
+    let diagnostics = logs::diagnostics(&storage).unwrap();
+    assert_eq!(diagnostics.valid_records, 2);
+    assert_eq!(diagnostics.malformed_records, 0);
+    assert!(!diagnostics.has_unterminated_tail);
+
+    fs::remove_dir_all(root).unwrap();
+}
@@ -28,6 +28,8 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
             this.isOpen = false;
             this.sessionStartTime = null;
             this.entries = [];
+            // Checklist of a finished session, shown above its check-ins
+            this.subtaskReport = null;
 
             // DOM element references (will be set after DOM loads)
             this.drawerEl = null;
@@ -81,13 +83,16 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
          */
         setSessionStartTime(startTime) {
             this.sessionStartTime = startTime;
+            this.subtaskReport = null;
             console.log('Session start time set:', this.sessionStartTime.toISOString());
         }
 
         /**
          * Open the review drawer
+         * @param {Object} [options]
+         * @param {boolean} [options.refresh=true] - Reload entries from the backend
          */
-        async open() {
+        async open({ refresh = true } = {}) {
             if (this.isOpen) return;
 
             this.isOpen = true;
@@ -96,7 +101,18 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
             this.triggerBtn.setAttribute('aria-pressed', 'true');
 
             // Refresh data when opening
-            await this.refresh();
+            if (refresh) await this.refresh();
+        }
+
+        /**
+         * Show the report of a finished session: its checklist and check-ins
+         * @param {Object} report - Session report from the backend
+         */
+        async showReport(report) {
+            this.entries = report.checkIns;
+            this.subtaskReport = report;
+            this.render();
+            await this.open({ refresh: false });
         }
 
         /**
@@ -148,7 +164,7 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
                 this.render();
             } catch (error) {
                 console.error('Failed to load session entries:', error);
-                this.showError(`Unable to load session log: ${error}`);
+                this.showError(`Unable to load session log: ${error?.message || error}`);
             }
         }
 
@@ -159,7 +175,9 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
             if (!this.entriesContainer) return;
 
             // Check if we have entries
-            if (!this.entries || this.entries.length === 0) {
+            const hasSubtasks = this.subtaskReport
+                && this.subtaskReport.finishedSubtasks.length + this.subtaskReport.openSubtasks.length > 0;
+            if ((!this.entries || this.entries.length === 0) && !hasSubtasks) {
                 this.showEmptyState('No check-ins yet. Stay mindful ✨');
                 return;
             }
@@ -171,6 +189,9 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
 
             // Build entries HTML using DocumentFragment for performance
             const fragment = document.createDocumentFragment();
+            if (hasSubtasks) {
+                fragment.appendChild(this.createSubtaskReportElement(this.subtaskReport));
+            }
 
             this.entries.forEach((entry) => {
                 const entryEl = this.createEntryElement(entry);
@@ -214,6 +235,39 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
             return div;
         }
 
+        /**
+         * Create the checklist block of a session report
+         * @param {Object} report - Session report from the backend
+         * @returns {HTMLElement}
+         */
+        createSubtaskReportElement(report) {
+            const div = document.createElement('div');
+            div.className = 'review-entry';
+
+            const timeStr = (completedAt) => new Date(completedAt).toLocaleTimeString('en-US', {
+                hour: 'numeric',
+                minute: '2-digit',
+                hour12: true,
+            });
+            const finished = report.finishedSubtasks.map((subtask) => `
+                <div class="entry-header">
+                    <span class="entry-status">✅ ${this.escapeHtml(subtask.text)}</span>
+                    <span class="entry-time">${timeStr(subtask.completedAt)}</span>
+                </div>`);
+            const open = report.openSubtasks.map((subtask) => `
+                <div class="entry-header">
+                    <span class="entry-status">⬜️ ${this.escapeHtml(subtask.text)}</span>
+                </div>`);
+            const total = report.finishedSubtasks.length + report.openSubtasks.length;
+
+            div.innerHTML = `
+                <div class="entry-note">Steps finished: ${report.finishedSubtasks.length}/${total}</div>
+                ${finished.join('')}${open.join('')}
+            `;
+
+            return div;
+        }
+
         /**
          * Update the summary pill at top
          */
@@ -229,11 +283,15 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
                     e.status === 'Other Distraction'
             ).length;
             const breakCount = this.entries.filter((e) => e.status === 'Taking a Break').length;
+            const ignoredCount = this.entries.filter(
+                (e) => e.outcome === 'skipped' || e.outcome === 'auto_submitted'
+            ).length;
 
             this.summaryEl.innerHTML = `
                 <span class="summary-stat">✅ On Task: ${onTaskCount}</span>
                 <span class="summary-stat">🚫 Distracted: ${distractionCount}</span>
                 <span class="summary-stat">☕️ Breaks: ${breakCount}</span>
+                <span class="summary-stat">⏭️ Ignored: ${ignoredCount}</span>
             `;
         }
 
@@ -293,6 +351,7 @@ import { waitForTauriBridge } from '../tauri-bridge.js';
         reset() {
             this.sessionStartTime = null;
             this.entries = [];
+            this.subtaskReport = null;
             this.close();
             this.showEmptyState('Session reset');
         }
@@ -49,6 +49,141 @@ export function applySettingsUpdate(currentSettings, payload, timers, isSessionR
     return { settings, remainders, deadlines };
 }
 
+// Pomodoro progress for a new session, or null when the mode is off. The
+// phase lengths are copied so a settings change applies from the next session.
+export function pomodoroFromSettings(loaded) {
+    if (!loaded?.pomodoro_enabled) return null;
+    return {
+        phase: 'work',
+        workMinutes: loaded.pomodoro_work_minutes,
+        shortBreakMinutes: loaded.pomodoro_short_break_minutes,
+        longBreakMinutes: loaded.pomodoro_long_break_minutes,
+        cyclesBeforeLongBreak: loaded.pomodoro_cycles_before_long_break,
+        autoStart: Boolean(loaded.pomodoro_auto_start),
+        cyclesCompleted: 0,
+        phaseTimeRemaining: loaded.pomodoro_work_minutes * 60,
+        breakSeconds: 0
+    };
+}
+
+export function isPomodoroBreak(progress) {
+    return progress?.phase === 'shortBreak' || progress?.phase === 'longBreak';
+}
+
+export function pomodoroPhaseSeconds(progress, phase = progress.phase) {
+    const minutes = {
+        work: progress.workMinutes,
+        shortBreak: progress.shortBreakMinutes,
+        longBreak: progress.longBreakMinutes
+    }[phase];
+    return minutes * 60;
+}
+
+// Progress after the current phase runs out: every work phase is followed by
+// a break, and every Nth break is a long one.
+export function nextPomodoroPhase(progress) {
+    if (progress.phase === 'work') {
+        const cyclesCompleted = progress.cyclesCompleted + 1;
+        const phase = cyclesCompleted % progress.cyclesBeforeLongBreak === 0
+            ? 'longBreak'
+            : 'shortBreak';
+        return {
+            ...progress,
+            phase,
+            cyclesCompleted,
+            phaseTimeRemaining: pomodoroPhaseSeconds(progress, phase)
+        };
+    }
+    return {
+        ...progress,
+        phase: 'work',
+        phaseTimeRemaining: pomodoroPhaseSeconds(progress, 'work'),
+        breakSeconds: progress.breakSeconds + pomodoroPhaseSeconds(progress)
+    };
+}
+
+// Mulberry32: seedable, with a 32-bit state that is saved with the session
+// so a resumed session keeps drawing the same schedule.
+export function nextRandom(state) {
+    const next = (state + 0x6D2B79F5) >>> 0;
+    let t = next;
+    t = Math.imul(t ^ (t >>> 15), t | 1);
+    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+    return { state: next, value: ((t ^ (t >>> 14)) >>> 0) / 4294967296 };
+}
+
+export function samplingFromSettings(loaded, seed) {
+    const distribution = loaded?.check_in_sampling;
+    if (distribution !== 'uniform' && distribution !== 'exponential') return null;
+    return {
+        distribution,
+        minGapSeconds: loaded.sampling_min_gap_minutes * 60,
+        rngState: seed >>> 0,
+        lastDelaySeconds: 0
+    };
+}
+
+// Draw the wait before the next check-in. Both distributions average
+// `meanSeconds` and never go below the minimum gap; exponential waits are
+// memoryless, so the time since the last prompt says nothing about the next,
+// and are capped at four times the mean.
+export function sampleCheckInDelay(sampling, meanSeconds) {
+    const { state, value } = nextRandom(sampling.rngState);
+    const gap = Math.min(sampling.minGapSeconds, meanSeconds);
+    const spread = meanSeconds - gap;
+    const delay = Math.round(sampling.distribution === 'exponential'
+        ? Math.min(gap - spread * Math.log(1 - value), 4 * meanSeconds)
+        : gap + value * 2 * spread);
+    return {
+        sampling: { ...sampling, rngState: state, lastDelaySeconds: delay },
+        delay
+    };
+}
+
+// Checklist edits return a new list. Ids stay with their items when the list
+// is reordered, so journal records can refer to them.
+export function addSubtask(subtasks, text) {
+    const trimmed = text.trim();
+    if (!trimmed) return subtasks;
+    const id = subtasks.reduce((max, subtask) => Math.max(max, subtask.id), 0) + 1;
+    return [...subtasks, { id, text: trimmed, completedAt: null }];
+}
+
+export function setSubtaskDone(subtasks, id, done, now = Date.now()) {
+    return subtasks.map((subtask) => subtask.id === id
+        ? { ...subtask, completedAt: done ? (subtask.completedAt ?? now) : null }
+        : subtask);
+}
+
+export function moveSubtask(subtasks, id, toIndex) {
+    const from = subtasks.findIndex((subtask) => subtask.id === id);
+    if (from < 0) return subtasks;
+    const moved = [...subtasks];
+    const [subtask] = moved.splice(from, 1);
+    moved.splice(Math.max(0, Math.min(toIndex, moved.length)), 0, subtask);
+    return moved;
+}
+
+// Tags typed as "client, urgent": trimmed, without blanks or repeats.
+export function parseTags(text) {
+    const tags = text.split(',').map((tag) => tag.trim()).filter(Boolean);
+    return [...new Set(tags)];
+}
+
+function recoveryStatusMessage(state) {
+    switch (state.phase) {
+        case 'interrupted':
+            return 'Session interrupted by restart - review and resume';
+        case 'active':
+        case 'writing':
+            return 'Session resumed after restart';
+        case 'break':
+            return 'Break resumed after restart';
+        default:
+            return 'Session paused - resume when ready';
+    }
+}
+
 export function recoveredSessionSnapshot(state, now = Date.now()) {
     const settings = {
         sessionDuration: state.sessionDuration,
@@ -69,8 +204,16 @@ export function recoveredSessionSnapshot(state, now = Date.now()) {
         focusShieldUntil: focusShieldActive ? state.focusShieldUntil : null,
         sessionStartedAt: state.sessionStartedAt,
         sessionGoal: state.sessionGoal || '',
-        statusMessage: state.phase === 'interrupted'
-            ? 'Session interrupted by restart - review and resume'
-            : 'Session paused - resume when ready'
+        pomodoro: state.pomodoro ?? null,
+        templateId: state.templateId ?? null,
+        adaptive: state.adaptive ?? null,
+        sampling: state.sampling ?? null,
+        subtasks: state.subtasks ?? [],
+        project: state.project ?? null,
+        tags: state.tags ?? [],
+        // Restart recovery leaves a session running only when its policy
+        // resumed it automatically.
+        autoResume: ['active', 'writing', 'break'].includes(state.phase),
+        statusMessage: recoveryStatusMessage(state)
     };
 }
@@ -1,9 +1,19 @@
 import { waitForTauriBridge } from './tauri-bridge.js';
 import {
     DEFAULT_TIMER_SETTINGS,
+    addSubtask,
     applySettingsUpdate,
     captureTimerRemainders,
+    isPomodoroBreak,
+    moveSubtask,
+    nextPomodoroPhase,
+    parseTags,
+    pomodoroFromSettings,
+    pomodoroPhaseSeconds,
     recoveredSessionSnapshot,
+    sampleCheckInDelay,
+    samplingFromSettings,
+    setSubtaskDone,
     resumeTimerDeadlines
 } from './js/timer-state.mjs';
 import './js/sessionReview.js';
@@ -41,6 +51,32 @@ let sessionPersistenceQueue = Promise.resolve();
 let lastSessionPersistedAt = 0;
 let skippedCheckIns = 0;
 let lastCheckInWasSkipped = false;
+// Pomodoro progress of the current session (null outside Pomodoro mode), and
+// the progress a new session starts from.
+let pomodoro = null;
+let pomodoroTemplate = null;
+let phaseEndTimestamp = null;
+// The session template the current session started from, and the user's own
+// timer settings to return to when it ends.
+let activeTemplate = null;
+let settingsBeforeTemplate = null;
+// Adaptive check-in interval of the current session (null when the interval
+// is fixed), and the bounds a new session starts with.
+let adaptive = null;
+let adaptiveBounds = null;
+// Randomized check-in schedule of the current session (null when check-ins
+// are periodic), and the settings a new session draws from.
+let sampling = null;
+// When the pending check-in prompt appeared, for its response latency.
+let promptShownAt = null;
+// The session goal's checklist, in display order.
+let subtasks = [];
+// A finished session waiting for its outcome rating: how it ended and its
+// last state.
+let pendingOutcome = null;
+// Numbers goal searches so a slow reply cannot replace a newer one.
+let goalSearchSequence = 0;
+let samplingSettings = null;
 
 const TICK_RATE_MS = 1000;
 const SLEEP_THRESHOLD_MS = 60_000;
@@ -99,12 +135,24 @@ function cacheDom() {
     dom.diagnosticsBtn = document.getElementById('diagnosticsBtn');
     dom.testBtn = document.getElementById('testBtn');
     dom.calendarBtn = document.getElementById('calendarBtn');
+    dom.templateBtn = document.getElementById('templateBtn');
     dom.sessionGoal = document.getElementById('sessionGoal');
+    dom.goalSuggestions = document.getElementById('goalSuggestions');
+    dom.sessionProject = document.getElementById('sessionProject');
+    dom.sessionTags = document.getElementById('sessionTags');
+    dom.subtaskList = document.getElementById('subtaskList');
+    dom.subtaskInput = document.getElementById('subtaskInput');
     dom.mainScreen = document.getElementById('mainScreen');
     dom.checkInScreen = document.getElementById('checkInScreen');
     dom.checkInGoalText = document.getElementById('checkInGoalText');
     dom.checkInNotes = document.getElementById('checkInNotes');
     dom.checkInCountdown = document.getElementById('checkInCountdown');
+    dom.outcomeScreen = document.getElementById('outcomeScreen');
+    dom.outcomeGoalText = document.getElementById('outcomeGoalText');
+    dom.outcomeFocusScore = document.getElementById('outcomeFocusScore');
+    dom.outcomeFocusScoreValue = document.getElementById('outcomeFocusScoreValue');
+    dom.outcomeReflection = document.getElementById('outcomeReflection');
+    dom.outcomeSkipBtn = document.getElementById('outcomeSkipBtn');
     dom.focusShieldBtn = document.getElementById('focusShieldBtn');
     dom.focusShieldBanner = document.getElementById('focusShieldBanner');
     dom.focusShieldText = document.getElementById('focusShieldText');
@@ -132,6 +180,9 @@ async function loadSettings() {
             checkInInterval: loaded.check_in_interval || settings.checkInInterval,
             writeTime: loaded.write_time || settings.writeTime
         };
+        pomodoroTemplate = pomodoroFromSettings(loaded);
+        adaptiveBounds = adaptiveBoundsFromSettings(loaded);
+        samplingSettings = loaded;
         console.log('Settings loaded:', settings);
 
         if (!isSessionRunning && !isWriting) {
@@ -143,6 +194,26 @@ async function loadSettings() {
     }
 }
 
+function adaptiveBoundsFromSettings(loaded) {
+    return loaded?.adaptive_check_ins
+        ? { min: loaded.adaptive_min_interval, max: loaded.adaptive_max_interval }
+        : null;
+}
+
+// Minutes between check-ins for the current session.
+function checkInIntervalMinutes() {
+    return adaptive?.interval ?? settings.checkInInterval;
+}
+
+// Seconds until the next check-in, drawn at random in sampling mode.
+function nextCheckInDelaySeconds() {
+    const meanSeconds = checkInIntervalMinutes() * 60;
+    if (!sampling) return meanSeconds;
+    const drawn = sampleCheckInDelay(sampling, meanSeconds);
+    sampling = drawn.sampling;
+    return drawn.delay;
+}
+
 function captureRemainingTimes(now = Date.now()) {
     const remainders = captureTimerRemainders({
         sessionTimeRemaining,
@@ -155,12 +226,58 @@ function captureRemainingTimes(now = Date.now()) {
     sessionTimeRemaining = remainders.sessionTimeRemaining;
     checkInTimeRemaining = remainders.checkInTimeRemaining;
     writeTimeRemaining = remainders.writeTimeRemaining;
+    if (pomodoro && phaseEndTimestamp) {
+        pomodoro.phaseTimeRemaining = Math.max(0, Math.ceil((phaseEndTimestamp - now) / 1000));
+    }
+}
+
+// Start the current Pomodoro phase's countdown; check-ins stay suspended for
+// the rest of a break.
+function resumePomodoroPhase(now) {
+    if (!pomodoro) return;
+    phaseEndTimestamp = now + pomodoro.phaseTimeRemaining * 1000;
+    if (isPomodoroBreak(pomodoro)) checkInEndTimestamp = null;
+}
+
+async function finishPomodoroPhase(now) {
+    const finished = pomodoro.phase;
+    const seconds = pomodoroPhaseSeconds(pomodoro);
+    captureRemainingTimes(now);
+    phaseEndTimestamp = null;
+    pomodoro = nextPomodoroPhase(pomodoro);
+    const onBreak = isPomodoroBreak(pomodoro);
+    if (onBreak) {
+        checkInEndTimestamp = null;
+    } else {
+        // Coming back from a break starts a full check-in interval.
+        checkInTimeRemaining = nextCheckInDelaySeconds();
+        checkInEndTimestamp = now + checkInTimeRemaining * 1000;
+    }
+
+    try {
+        await invoke('log_pomodoro_phase', { state: activeSessionSnapshot(), finished, seconds });
+    } catch (error) {
+        console.error('Failed to log Pomodoro phase:', error);
+    }
+
+    if (pomodoro.autoStart) {
+        resumePomodoroPhase(now);
+        statusOverride = onBreak ? 'Break time' : 'Back to work';
+        persistActiveSession();
+        updateDisplay();
+    } else {
+        statusOverride = onBreak ? 'Work phase done - start your break' : 'Break over - resume when ready';
+        pauseSession({ reason: 'pomodoro' });
+        if (onBreak && dom.startBtn) dom.startBtn.textContent = 'Start Break';
+    }
 }
 
 function activeSessionSnapshot() {
     return {
         version: 1,
-        phase: isWriting ? 'writing' : (isSessionRunning ? 'active' : 'paused'),
+        phase: isWriting
+            ? 'writing'
+            : (isSessionRunning ? (isPomodoroBreak(pomodoro) ? 'break' : 'active') : 'paused'),
         sessionGoal: dom.sessionGoal?.value || '',
         sessionStartedAt,
         sessionDuration: settings.sessionDuration,
@@ -174,7 +291,18 @@ function activeSessionSnapshot() {
         lastCheckInWasSkipped,
         focusShieldActive,
         focusShieldUntil,
-        recoveryReason: null
+        recoveryReason: null,
+        templateId: activeTemplate?.id ?? null,
+        adaptive,
+        sampling,
+        promptShownAt: isWriting ? promptShownAt : null,
+        subtasks,
+        project: dom.sessionProject?.value || null,
+        tags: parseTags(dom.sessionTags?.value || ''),
+        pomodoro: pomodoro && {
+            ...pomodoro,
+            phaseTimeRemaining: Math.max(0, Math.round(pomodoro.phaseTimeRemaining))
+        }
     };
 }
 
@@ -201,12 +329,57 @@ function clearActiveSession() {
         .catch((error) => console.error('Failed to clear active session state:', error));
 }
 
-async function recoverActiveSession() {
+async function offerOrphanedSessionRecovery() {
+    try {
+        const orphan = await invoke('clean_up_session_state_files');
+        if (!orphan) return;
+        const savedAt = new Date(orphan.writtenAt).toLocaleString();
+        const restore = confirm(`A newer snapshot of "${orphan.state.sessionGoal}" from ${savedAt} was not saved completely before the app closed. Restore it?`);
+        await invoke('resolve_orphaned_session_state', { fileName: orphan.fileName, restore });
+    } catch (error) {
+        console.error('Failed to clean up session state files:', error);
+    }
+}
+
+function minutesAgo(timestamp) {
+    return Math.max(0, Math.round((Date.now() - new Date(timestamp).getTime()) / 60000));
+}
+
+// Offer to roll the saved session back to an earlier snapshot. Returns true
+// when one was restored.
+async function offerSessionRollback() {
+    if (isSessionRunning || isWriting) return false;
+    try {
+        const snapshots = await invoke('list_session_snapshots');
+        if (snapshots.length === 0) return false;
+        const choices = snapshots
+            .map((snapshot, index) => `${index + 1}. ${minutesAgo(snapshot.savedAt)} min ago: ${Math.round(snapshot.sessionTimeRemaining / 60)} min left, ${snapshot.checkInsCompleted} check-in(s)`)
+            .join('\n');
+        const answer = prompt(`Restore session state from an earlier snapshot?\n${choices}\n\nEnter a number, or cancel to keep the current state.`);
+        const snapshot = snapshots[Number.parseInt(answer, 10) - 1];
+        if (!snapshot) return false;
+        await invoke('restore_session_snapshot', { id: snapshot.id });
+        return true;
+    } catch (error) {
+        console.error('Failed to restore session snapshot:', error);
+        alert(error?.message || 'Could not restore the session snapshot.');
+        return false;
+    }
+}
+
+async function recoverActiveSession(allowRollback = true) {
+    await offerOrphanedSessionRecovery();
     try {
         const state = await invoke('recover_active_session');
         if (!state) return false;
 
         const recovered = recoveredSessionSnapshot(state);
+        if (recovered.templateId) {
+            const templates = await invoke('list_session_templates').catch(() => []);
+            activeTemplate = templates.find((template) => template.id === recovered.templateId)
+                || { id: recovered.templateId };
+            settingsBeforeTemplate = settings;
+        }
         settings = recovered.settings;
         sessionTimeRemaining = recovered.sessionTimeRemaining;
         checkInTimeRemaining = recovered.checkInTimeRemaining;
@@ -217,6 +390,14 @@ async function recoverActiveSession() {
         focusShieldActive = recovered.focusShieldActive;
         focusShieldUntil = recovered.focusShieldUntil;
         sessionStartedAt = recovered.sessionStartedAt;
+        pomodoro = recovered.pomodoro;
+        adaptive = recovered.adaptive;
+        sampling = recovered.sampling;
+        subtasks = recovered.subtasks;
+        renderSubtasks();
+        await loadProjects(recovered.project);
+        if (dom.sessionTags) dom.sessionTags.value = recovered.tags.join(', ');
+        phaseEndTimestamp = null;
         isSessionRunning = false;
         isWriting = false;
         sessionEndTimestamp = null;
@@ -233,9 +414,11 @@ async function recoverActiveSession() {
             dom.startBtn.style.color = 'black';
         }
         statusOverride = recovered.statusMessage;
+        if (recovered.autoResume) await startSession({ autoHide: false });
         return true;
     } catch (error) {
         console.error('Failed to recover active session:', error);
+        if (allowRollback && await offerSessionRollback()) return recoverActiveSession(false);
         statusOverride = 'Saved session needs attention';
         return null;
     }
@@ -272,10 +455,14 @@ function shouldDeferCheckIn(now) {
 
 function deferCheckIn(now) {
     const base = focusShieldUntil || now;
-    checkInEndTimestamp = base + settings.checkInInterval * 60 * 1000;
+    checkInEndTimestamp = base + nextCheckInDelaySeconds() * 1000;
     captureRemainingTimes(now);
     statusOverride = 'Check-in postponed';
     updateDisplay();
+    invoke('log_deferred_prompt', {
+        state: activeSessionSnapshot(),
+        deferredUntil: checkInEndTimestamp
+    }).catch((error) => console.error('Failed to log deferred check-in:', error));
 }
 
 function handleSystemSleep(deltaMs) {
@@ -291,6 +478,37 @@ function handleSystemSleep(deltaMs) {
     // For shorter gaps, just continue - the timestamp-based timers will catch up
 }
 
+// Sleep and clock changes detected by the backend watchdog, which keeps
+// running while this webview is suspended.
+function handleClockChange({ change, state, message }) {
+    if (!change) return;
+    if (change.kind === 'suspended' && state?.phase === 'paused') {
+        pauseSession({ reason: 'sleep' });
+        // The backend paused at the last saved remainders, before the sleep.
+        sessionTimeRemaining = state.sessionTimeRemaining;
+        checkInTimeRemaining = state.checkInTimeRemaining;
+        writeTimeRemaining = state.writeTimeRemaining;
+        if (pomodoro && state.pomodoro) pomodoro.phaseTimeRemaining = state.pomodoro.phaseTimeRemaining;
+        persistActiveSession();
+    } else if (change.kind === 'clockJumped') {
+        // Deadlines are wall-clock timestamps; move them with the clock.
+        if (sessionEndTimestamp) sessionEndTimestamp += change.shiftMs;
+        if (checkInEndTimestamp) checkInEndTimestamp += change.shiftMs;
+        if (writeEndTimestamp) writeEndTimestamp += change.shiftMs;
+        if (focusShieldUntil) focusShieldUntil += change.shiftMs;
+        if (phaseEndTimestamp) phaseEndTimestamp += change.shiftMs;
+        if (lastTickTimestamp) lastTickTimestamp += change.shiftMs;
+        // Saved timestamps too, or the next save would undo the backend's shift.
+        if (sessionStartedAt) sessionStartedAt += change.shiftMs;
+        if (promptShownAt) promptShownAt += change.shiftMs;
+        for (const subtask of subtasks) {
+            if (subtask.completedAt != null) subtask.completedAt += change.shiftMs;
+        }
+    }
+    if (message) statusOverride = message;
+    updateDisplay();
+}
+
 async function tick() {
     const now = Date.now();
 
@@ -343,6 +561,14 @@ async function tick() {
             }
         }
 
+        if (pomodoro && phaseEndTimestamp) {
+            pomodoro.phaseTimeRemaining = Math.max(0, Math.ceil((phaseEndTimestamp - now) / 1000));
+            if (pomodoro.phaseTimeRemaining <= 0) {
+                await finishPomodoroPhase(now);
+                return;
+            }
+        }
+
         if (checkInEndTimestamp) {
             checkInTimeRemaining = Math.max(0, Math.ceil((checkInEndTimestamp - now) / 1000));
             if (checkInTimeRemaining <= 0) {
@@ -401,11 +627,29 @@ async function updateDisplay() {
         if (timerEl) timerEl.textContent = `${writeTimeRemaining}`;
         if (statusEl) statusEl.textContent = "Write what you're doing";
         trayText = `✍️ ${writeTimeRemaining}s`;
+    } else if (isPomodoroBreak(pomodoro)) {
+        if (timerLabelEl) timerLabelEl.textContent = pomodoro.phase === 'longBreak' ? 'LONG BREAK' : 'BREAK';
+        if (timerEl) timerEl.textContent = formatTime(pomodoro.phaseTimeRemaining);
+
+        let statusText = isSessionRunning ? 'Check-ins paused for your break' : 'Break paused';
+        if (statusOverride) {
+            statusText = statusOverride;
+            statusOverride = null;
+        }
+        if (statusEl) statusEl.textContent = statusText;
+
+        trayText = `☕ ${formatTime(pomodoro.phaseTimeRemaining)}`;
     } else {
-        if (timerLabelEl) timerLabelEl.textContent = 'NEXT CHECK-IN';
-        if (timerEl) timerEl.textContent = formatTime(checkInTimeRemaining);
+        // Sampled check-ins only work if their time is not known in advance,
+        // so show the session time instead.
+        const shownTime = sampling ? sessionTimeRemaining : checkInTimeRemaining;
+        if (timerLabelEl) timerLabelEl.textContent = sampling ? 'SESSION LEFT' : 'NEXT CHECK-IN';
+        if (timerEl) timerEl.textContent = formatTime(shownTime);
 
         let statusText = isSessionRunning ? 'Session active' : 'Ready';
+        if (pomodoro && isSessionRunning) {
+            statusText = `Pomodoro ${pomodoro.cyclesCompleted + 1} • ${formatTime(pomodoro.phaseTimeRemaining)} left`;
+        }
         if (focusShieldActive && focusShieldUntil) {
             const minutesLeft = Math.max(1, Math.ceil((focusShieldUntil - Date.now()) / 60000));
             statusText = `Focus Shield • ${minutesLeft} min`;
@@ -416,7 +660,7 @@ async function updateDisplay() {
         }
         if (statusEl) statusEl.textContent = statusText;
 
-        trayText = formatTime(checkInTimeRemaining);
+        trayText = formatTime(shownTime);
     }
 
     const elapsedSeconds = Math.max(0, (settings.sessionDuration * 60) - sessionTimeRemaining);
@@ -459,11 +703,21 @@ async function startSession({ autoHide = true } = {}) {
         if (sessionTimeRemaining <= 0 || sessionTimeRemaining > settings.sessionDuration * 60) {
             sessionTimeRemaining = settings.sessionDuration * 60;
         }
-        if (checkInTimeRemaining <= 0 || checkInTimeRemaining > settings.checkInInterval * 60) {
-            checkInTimeRemaining = settings.checkInInterval * 60;
+        if (checkInTimeRemaining <= 0 || (!sampling && checkInTimeRemaining > checkInIntervalMinutes() * 60)) {
+            checkInTimeRemaining = nextCheckInDelaySeconds();
         }
         if (!sessionStartedAt) {
             sessionStartedAt = now;
+            pomodoro = pomodoroTemplate && { ...pomodoroTemplate };
+            adaptive = adaptiveBounds && {
+                minInterval: adaptiveBounds.min,
+                maxInterval: adaptiveBounds.max,
+                interval: Math.min(Math.max(settings.checkInInterval, adaptiveBounds.min), adaptiveBounds.max),
+                onTaskStreak: 0,
+                reason: null
+            };
+            sampling = samplingFromSettings(samplingSettings, crypto.getRandomValues(new Uint32Array(1))[0]);
+            checkInTimeRemaining = nextCheckInDelaySeconds();
             if (window.sessionReview) {
                 window.sessionReview.setSessionStartTime(new Date(sessionStartedAt));
             }
@@ -473,6 +727,7 @@ async function startSession({ autoHide = true } = {}) {
     const deadlines = resumeTimerDeadlines({ sessionTimeRemaining, checkInTimeRemaining }, now);
     sessionEndTimestamp = deadlines.sessionEndTimestamp;
     checkInEndTimestamp = deadlines.checkInEndTimestamp;
+    resumePomodoroPhase(now);
     isSessionRunning = true;
 
     if (dom.startBtn) {
@@ -497,6 +752,7 @@ async function startSession({ autoHide = true } = {}) {
     isSessionRunning = false;
     sessionEndTimestamp = null;
     checkInEndTimestamp = null;
+    phaseEndTimestamp = null;
 
     if (dom.startBtn) {
         dom.startBtn.textContent = 'Resume Focus';
@@ -511,6 +767,9 @@ async function startSession({ autoHide = true } = {}) {
 }
 
 function resetSession({ clearPersistedState = true } = {}) {
+    if (settingsBeforeTemplate) settings = settingsBeforeTemplate;
+    settingsBeforeTemplate = null;
+    activeTemplate = null;
     isSessionRunning = false;
     isWriting = false;
     checkInsCompleted = 0;
@@ -525,6 +784,12 @@ function resetSession({ clearPersistedState = true } = {}) {
     focusShieldActive = false;
     focusShieldUntil = null;
     sessionStartedAt = null;
+    pomodoro = null;
+    phaseEndTimestamp = null;
+    adaptive = null;
+    sampling = null;
+    subtasks = [];
+    renderSubtasks();
     isUsingCalendarEvent = false;
     lastSessionPersistedAt = 0;
 
@@ -550,6 +815,7 @@ function resetSession({ clearPersistedState = true } = {}) {
 
 async function triggerCheckIn({ forced = false } = {}) {
     console.log('🔔 triggerCheckIn: Starting check-in #' + (checkInsCompleted + 1));
+    if (!forced && isPomodoroBreak(pomodoro)) return;
     const now = Date.now();
     if (!forced && shouldDeferCheckIn(now)) {
         console.log('🛡️ Check-in deferred due to Focus Shield');
@@ -558,6 +824,8 @@ async function triggerCheckIn({ forced = false } = {}) {
     }
 
     captureRemainingTimes(now);
+    // A check-in takes over the window from an unanswered outcome prompt.
+    if (pendingOutcome) submitSessionOutcome(null);
 
     // Set isWriting BEFORE pausing to prevent stopTickingIfIdle from killing the interval
     isWriting = true;
@@ -578,6 +846,7 @@ async function triggerCheckIn({ forced = false } = {}) {
     }
 
     showCheckInScreen();
+    promptShownAt = Date.now();
 
     // isWriting already set to true above (before pauseSession)
     writeTimeRemaining = settings.writeTime;
@@ -593,6 +862,12 @@ async function triggerCheckIn({ forced = false } = {}) {
 
 function showCheckInScreen() {
     const goalText = (dom.sessionGoal?.value || '').trim() || '(No specific goal)';
+    // A template may narrow the answers offered; Skip is always available.
+    const statuses = activeTemplate?.statuses || [];
+    dom.checkInScreen?.querySelectorAll('button[data-status]').forEach((button) => {
+        const status = button.getAttribute('data-status');
+        button.hidden = statuses.length > 0 && status !== 'Skip' && !statuses.includes(status);
+    });
     if (dom.checkInGoalText) dom.checkInGoalText.textContent = `"${goalText}"`;
     if (dom.checkInNotes) dom.checkInNotes.value = '';
 
@@ -620,7 +895,10 @@ function handleCheckInTimeout() {
 
 async function handleCheckInResponse(status, options = {}) {
     if (!isWriting) return;
-    if (!options.auto) lastCheckInWasSkipped = false;
+    if (!options.auto) {
+        lastCheckInWasSkipped = status === 'Skip';
+        if (lastCheckInWasSkipped) skippedCheckIns++;
+    }
 
     const logEntry = {
         timestamp: new Date().toISOString(),
@@ -632,14 +910,35 @@ async function handleCheckInResponse(status, options = {}) {
         write_time_setting: settings.writeTime,
         check_in_number: checkInsCompleted,
         auto_submitted: !!options.auto,
-        focus_shield_active: focusShieldActive
+        focus_shield_active: focusShieldActive,
+        template_id: activeTemplate?.id ?? null,
+        interval_adjustment: null,
+        sampled_delay_seconds: sampling?.lastDelaySeconds ?? null,
+        project: dom.sessionProject?.value || null,
+        tags: parseTags(dom.sessionTags?.value || ''),
+        prompt_outcome: options.auto ? 'auto_submitted' : (status === 'Skip' ? 'skipped' : 'answered'),
+        response_latency_ms: promptShownAt ? Math.max(0, Date.now() - promptShownAt) : null
     };
+    promptShownAt = null;
+
+    if (adaptive) {
+        try {
+            const [next, adjustment] = await invoke('adapt_check_in_interval', { adaptive, status });
+            adaptive = next;
+            logEntry.interval_adjustment = adjustment;
+        } catch (error) {
+            console.error('Failed to adapt the check-in interval:', error);
+        }
+    }
 
     try {
         await invoke('log_check_in', { logLine: JSON.stringify(logEntry) });
         window.dispatchEvent(new CustomEvent('ft:checkin-created'));
     } catch (error) {
         console.error('Failed to log check-in:', error);
+        if (error?.code === 'disk_full' || error?.code === 'permission_denied') {
+            alert(`This check-in could not be saved: ${error.message}`);
+        }
     }
 
     // Hide window immediately after response
@@ -648,7 +947,7 @@ async function handleCheckInResponse(status, options = {}) {
     endWriteTime({ auto: options.auto, status });
 }
 
-async function endWriteTime({ auto = false, status } = {}) {
+async function endWriteTime({ auto = false, status, checkInDelaySeconds } = {}) {
     console.log('endWriteTime called, isSessionRunning before:', isSessionRunning);
     isWriting = false;
     writeEndTimestamp = null;
@@ -657,7 +956,7 @@ async function endWriteTime({ auto = false, status } = {}) {
 
     // CRITICAL: Reset check-in timer for the NEXT check-in
     // This needs to happen BEFORE resuming the session
-    checkInTimeRemaining = settings.checkInInterval * 60;
+    checkInTimeRemaining = checkInDelaySeconds ?? nextCheckInDelaySeconds();
     const now = Date.now();
     const deadlines = resumeTimerDeadlines({ sessionTimeRemaining, checkInTimeRemaining }, now);
     checkInEndTimestamp = deadlines.checkInEndTimestamp;
@@ -665,9 +964,10 @@ async function endWriteTime({ auto = false, status } = {}) {
     // CRITICAL: Re-establish session timer
     // The session is still running in the background, we just need to resume ticking
     sessionEndTimestamp = deadlines.sessionEndTimestamp;
+    resumePomodoroPhase(now);
 
     if (auto) statusOverride = 'Skipped';
-    else if (status) statusOverride = `Logged: ${status}`;
+    else if (status) statusOverride = adaptive ? `Logged: ${status} • next in ${adaptive.interval}m` : `Logged: ${status}`;
     else statusOverride = 'Session active';
 
     // Ensure the main screen is visible
@@ -683,19 +983,191 @@ async function endWriteTime({ auto = false, status } = {}) {
     startTicking();
 
     console.log('endWriteTime done, isSessionRunning after:', isSessionRunning, 'checkInEndTimestamp:', new Date(checkInEndTimestamp).toLocaleTimeString(), 'sessionEndTimestamp:', new Date(sessionEndTimestamp).toLocaleTimeString());
-} function endSession() {
+}
+
+// Fill the project picker with the active projects, keeping `selected` even
+// when it has been archived since.
+async function loadProjects(selected = dom.sessionProject?.value || '') {
+    if (!dom.sessionProject) return;
+    try {
+        const projects = await invoke('list_projects');
+        dom.sessionProject.innerHTML = '<option value="">No project</option>';
+        projects
+            .filter((project) => !project.archived || project.id === selected)
+            .forEach((project) => {
+                const option = document.createElement('option');
+                option.value = project.id;
+                option.textContent = `● ${project.name}`;
+                option.style.color = project.color;
+                dom.sessionProject.appendChild(option);
+            });
+        dom.sessionProject.value = selected ?? '';
+    } catch (error) {
+        console.error('Failed to load projects:', error);
+    }
+}
+
+const GOAL_SUGGESTION_LIMIT = 8;
+
+async function updateGoalSuggestions(query) {
+    if (!dom.goalSuggestions) return;
+    const sequence = ++goalSearchSequence;
+    try {
+        const goals = await invoke('search_goals', { query, limit: GOAL_SUGGESTION_LIMIT });
+        if (sequence !== goalSearchSequence) return;
+        dom.goalSuggestions.innerHTML = '';
+        goals.forEach((record) => {
+            const option = document.createElement('option');
+            option.value = record.goal;
+            dom.goalSuggestions.appendChild(option);
+        });
+    } catch (error) {
+        console.error('Failed to search goal history:', error);
+    }
+}
+
+function renderSubtasks() {
+    if (!dom.subtaskList) return;
+    dom.subtaskList.innerHTML = '';
+    subtasks.forEach((subtask, index) => {
+        const item = document.createElement('li');
+        item.classList.toggle('done', subtask.completedAt != null);
+
+        const checkbox = document.createElement('input');
+        checkbox.type = 'checkbox';
+        checkbox.checked = subtask.completedAt != null;
+        checkbox.addEventListener('change', () => checkSubtask(subtask.id, checkbox.checked));
+
+        const text = document.createElement('span');
+        text.textContent = subtask.text;
+
+        const up = document.createElement('button');
+        up.textContent = '▲';
+        up.disabled = index === 0;
+        up.addEventListener('click', () => reorderSubtask(subtask.id, index - 1));
+        const down = document.createElement('button');
+        down.textContent = '▼';
+        down.disabled = index === subtasks.length - 1;
+        down.addEventListener('click', () => reorderSubtask(subtask.id, index + 1));
+
+        item.append(checkbox, text, up, down);
+        dom.subtaskList.appendChild(item);
+    });
+}
+
+function persistSubtasks() {
+    renderSubtasks();
+    persistActiveSession();
+}
+
+function reorderSubtask(id, toIndex) {
+    subtasks = moveSubtask(subtasks, id, toIndex);
+    persistSubtasks();
+}
+
+async function checkSubtask(id, done) {
+    subtasks = setSubtaskDone(subtasks, id, done);
+    persistSubtasks();
+    // Journal only while a session is underway; a checklist prepared before
+    // the start has no session to belong to yet.
+    if (!done || !sessionStartedAt) return;
+    try {
+        await invoke('log_subtask_completed', { state: activeSessionSnapshot(), id });
+    } catch (error) {
+        console.error('Failed to log subtask completion:', error);
+    }
+}
+
+async function reportFinishedSession(state) {
+    try {
+        const report = await invoke('session_report', { state });
+        const total = report.finishedSubtasks.length + report.openSubtasks.length;
+        if (total > 0) {
+            statusOverride = `Cycle done: ${report.finishedSubtasks.length}/${total} steps finished`;
+            updateDisplay();
+        }
+        window.sessionReview?.showReport(report);
+    } catch (error) {
+        console.error('Failed to build the session report:', error);
+    }
+}
+
+async function promptSessionOutcome(ended, state) {
+    // Only one outcome prompt at a time; an unanswered one is dropped.
+    pendingOutcome = { ended, state };
+    const goalText = state.sessionGoal.trim() || '(No specific goal)';
+    if (dom.outcomeGoalText) dom.outcomeGoalText.textContent = `"${goalText}"`;
+    if (dom.outcomeFocusScore) dom.outcomeFocusScore.value = '7';
+    if (dom.outcomeFocusScoreValue) dom.outcomeFocusScoreValue.textContent = '7';
+    if (dom.outcomeReflection) dom.outcomeReflection.value = '';
+
+    dom.mainScreen?.classList.add('hidden');
+    dom.outcomeScreen?.classList.add('active');
+    try {
+        await invoke('position_window_centered');
+        await appWindow.show();
+        await appWindow.setFocus();
+    } catch (error) {
+        console.error('Failed to show the session outcome prompt:', error);
+    }
+}
+
+function hideOutcomeScreen() {
+    dom.outcomeScreen?.classList.remove('active');
+    if (!isWriting) dom.mainScreen?.classList.remove('hidden');
+}
+
+// Journal the pending outcome with `rating`, or drop it when skipped.
+async function submitSessionOutcome(rating) {
+    const pending = pendingOutcome;
+    pendingOutcome = null;
+    hideOutcomeScreen();
+    if (!pending || !rating) return;
+
+    const outcome = {
+        ended: pending.ended,
+        rating,
+        focus_score: Number(dom.outcomeFocusScore?.value || 7),
+        reflection: (dom.outcomeReflection?.value || '').trim()
+    };
+    try {
+        await invoke('log_session_outcome', { state: pending.state, outcome });
+        statusOverride = 'Session outcome saved';
+        updateDisplay();
+    } catch (error) {
+        console.error('Failed to log the session outcome:', error);
+        alert(`The session outcome could not be saved: ${error?.message || error}`);
+    }
+}
+
+// End the session before its timer ran out, asking how it went.
+function endSessionEarly(ended) {
+    const state = sessionStartedAt ? activeSessionSnapshot() : null;
+    resetSession();
+    if (state) promptSessionOutcome(ended, state);
+}
+
+function endSession() {
+    // Report on the finished cycle before its state is reset.
+    const finished = activeSessionSnapshot();
+    reportFinishedSession(finished);
+    promptSessionOutcome('completed', finished);
+
     // Reset session timers but keep running (continuous mode)
     checkInsCompleted = 0;
     skippedCheckIns = 0;
     lastCheckInWasSkipped = false;
     sessionTimeRemaining = settings.sessionDuration * 60;
-    checkInTimeRemaining = settings.checkInInterval * 60;
+    checkInTimeRemaining = nextCheckInDelaySeconds();
 
     const now = Date.now();
     sessionStartedAt = now;
+    // Open steps carry over into the next cycle.
+    subtasks = subtasks.filter((subtask) => subtask.completedAt == null);
+    renderSubtasks();
     const deadlines = resumeTimerDeadlines({ sessionTimeRemaining, checkInTimeRemaining }, now);
     sessionEndTimestamp = deadlines.sessionEndTimestamp;
-    checkInEndTimestamp = deadlines.checkInEndTimestamp;
+    checkInEndTimestamp = isPomodoroBreak(pomodoro) ? null : deadlines.checkInEndTimestamp;
 
     statusOverride = 'New cycle started';
     console.log('Session cycle complete, starting new cycle automatically');
@@ -717,7 +1189,7 @@ function extendFocusShield() {
 
     focusShieldActive = true;
     if (isSessionRunning && checkInEndTimestamp) {
-        const earliest = focusShieldUntil + settings.checkInInterval * 60 * 1000;
+        const earliest = focusShieldUntil + checkInIntervalMinutes() * 60 * 1000;
         if (checkInEndTimestamp < earliest) {
             checkInEndTimestamp = earliest;
         }
@@ -741,17 +1213,31 @@ function cancelFocusShield() {
 
 async function showPersistenceDiagnostics() {
     try {
-        const diagnostics = await invoke('get_persistence_diagnostics');
-        const journalStatus = diagnostics.malformedRecords === 0 && !diagnostics.hasUnterminatedTail
-            ? 'Activity journal: healthy'
-            : `Activity journal: ${diagnostics.malformedRecords} recoverable malformed record(s)`;
-        const tailStatus = diagnostics.hasUnterminatedTail
-            ? '\nThe final record is incomplete. New check-ins will restore a safe line boundary.'
-            : '';
-        alert(`${journalStatus}\nValid activity records: ${diagnostics.validRecords}${tailStatus}`);
+        let report = await invoke('get_persistence_diagnostics');
+        const { journal } = report;
+        const lines = [
+            `Valid activity records: ${journal.validRecords}`,
+            `Session state: ${report.sessionState.status}`,
+            `Settings: ${report.settings.status}`,
+        ];
+        if (report.issues.length === 0) {
+            alert(`Local data: healthy\n${lines.join('\n')}`);
+            if (await offerSessionRollback()) await recoverActiveSession(false);
+            return;
+        }
+        alert(`Local data: ${report.issues.length} issue(s)\n${lines.join('\n')}\n\n${report.issues.map(issue => `• ${issue.summary}`).join('\n')}`);
+
+        const actions = report.issues
+            .filter(issue => issue.repair && confirm(`${issue.summary}\n\nRepair: ${issue.repairDescription}?`))
+            .map(issue => issue.repair);
+        if (actions.length === 0) return;
+        report = await invoke('repair_persistence', { actions });
+        alert(report.issues.length === 0
+            ? 'Repairs applied. Local data is healthy.'
+            : `Repairs applied. Remaining issues:\n${report.issues.map(issue => `• ${issue.summary}`).join('\n')}`);
     } catch (error) {
-        console.error('Failed to load persistence diagnostics:', error);
-        alert('Could not inspect local data status. Your activity content was not displayed.');
+        console.error('Failed to inspect or repair local data:', error);
+        alert(error?.message || 'Could not inspect local data status. Your activity content was not displayed.');
     }
 }
 
@@ -765,6 +1251,12 @@ async function useCalendarEvent(silent = false) {
     try {
         const result = await invoke('get_current_event');
         if (result) {
+            // A tagged event sets up its template's timers; the event title
+            // stays the goal.
+            const template = sessionStartedAt
+                ? null
+                : await invoke('template_for_calendar_event', { title: result }).catch(() => null);
+            if (template) applyTemplate(template);
             if (dom.sessionGoal) dom.sessionGoal.value = result;
             isUsingCalendarEvent = true;
             updateCalendarButtonState();
@@ -773,11 +1265,49 @@ async function useCalendarEvent(silent = false) {
             alert('No calendar event found for the current time.');
         }
     } catch (error) {
-        if (!silent) alert(error || 'Failed to access calendar.');
+        if (!silent) alert(error?.message || 'Failed to access calendar.');
         console.error('Calendar access failed:', error);
     }
 }
 
+// Use a template's timers and check-in answers for the next session.
+function applyTemplate(template) {
+    settingsBeforeTemplate = settingsBeforeTemplate || settings;
+    settings = {
+        sessionDuration: template.session_duration,
+        checkInInterval: template.check_in_interval,
+        writeTime: template.write_time
+    };
+    activeTemplate = template;
+    sessionTimeRemaining = settings.sessionDuration * 60;
+    checkInTimeRemaining = settings.checkInInterval * 60;
+    statusOverride = `Template: ${template.id}`;
+    updateDisplay();
+}
+
+async function chooseTemplate() {
+    if (sessionStartedAt) {
+        alert('End the current session before starting one from a template.');
+        return;
+    }
+    try {
+        const templates = await invoke('list_session_templates');
+        if (templates.length === 0) {
+            alert('No session templates yet. Add them to session_templates.json in the data folder.');
+            return;
+        }
+        const choices = templates
+            .map((template, index) => `${index + 1}. ${template.id} (${template.session_duration}m, check in every ${template.check_in_interval}m)`)
+            .join('\n');
+        const choice = prompt(`Start a session from a template:\n\n${choices}\n\nEnter a number:`);
+        const template = templates[parseInt(choice, 10) - 1];
+        if (template) await invoke('start_session_from_template', { id: template.id });
+    } catch (error) {
+        console.error('Failed to start from a template:', error);
+        alert(error?.message || 'Could not start from the template.');
+    }
+}
+
 function updateCalendarButtonState() {
     const btn = dom.calendarBtn;
     if (!btn) return;
@@ -861,7 +1391,7 @@ window.addEventListener('DOMContentLoaded', async () => {
     });
 
     if (dom.startBtn) dom.startBtn.addEventListener('click', toggleSession);
-    if (dom.resetBtn) dom.resetBtn.addEventListener('click', resetSession);
+    if (dom.resetBtn) dom.resetBtn.addEventListener('click', () => endSessionEarly('reset'));
     if (dom.settingsBtn) dom.settingsBtn.addEventListener('click', openSettings);
     if (dom.diagnosticsBtn) dom.diagnosticsBtn.addEventListener('click', showPersistenceDiagnostics);
     if (dom.testBtn) dom.testBtn.addEventListener('click', testCheckIn);
@@ -879,6 +1409,7 @@ window.addEventListener('DOMContentLoaded', async () => {
         });
     }
 
+    if (dom.templateBtn) dom.templateBtn.addEventListener('click', chooseTemplate);
     if (dom.focusShieldBtn) dom.focusShieldBtn.addEventListener('click', extendFocusShield);
     if (dom.focusShieldCancelBtn) dom.focusShieldCancelBtn.addEventListener('click', cancelFocusShield);
 
@@ -904,18 +1435,41 @@ window.addEventListener('DOMContentLoaded', async () => {
         });
     }
 
-    document.querySelectorAll('.check-in-buttons button').forEach((button) => {
+    document.querySelectorAll('#checkInScreen .check-in-buttons button').forEach((button) => {
         button.addEventListener('click', () => {
             handleCheckInResponse(button.getAttribute('data-status'));
         });
     });
 
+    document.querySelectorAll('#outcomeScreen button[data-rating]').forEach((button) => {
+        button.addEventListener('click', () => submitSessionOutcome(button.getAttribute('data-rating')));
+    });
+    if (dom.outcomeSkipBtn) dom.outcomeSkipBtn.addEventListener('click', () => submitSessionOutcome(null));
+    if (dom.outcomeFocusScore) {
+        dom.outcomeFocusScore.addEventListener('input', () => {
+            if (dom.outcomeFocusScoreValue) dom.outcomeFocusScoreValue.textContent = dom.outcomeFocusScore.value;
+        });
+    }
+
     if (dom.checkInNotes) {
         dom.checkInNotes.addEventListener('keypress', (event) => {
             if (event.key === 'Enter') handleCheckInResponse('On Task');
         });
     }
 
+    // A running session picks up project and tag changes from the next save.
+    dom.sessionProject?.addEventListener('change', persistActiveSession);
+    dom.sessionTags?.addEventListener('change', persistActiveSession);
+
+    if (dom.subtaskInput) {
+        dom.subtaskInput.addEventListener('keypress', (event) => {
+            if (event.key !== 'Enter') return;
+            subtasks = addSubtask(subtasks, dom.subtaskInput.value);
+            dom.subtaskInput.value = '';
+            persistSubtasks();
+        });
+    }
+
     if (dom.sessionGoal) {
         dom.sessionGoal.addEventListener('keypress', (event) => {
             if (event.key === 'Enter' && !isSessionRunning) toggleSession();
@@ -926,10 +1480,13 @@ window.addEventListener('DOMContentLoaded', async () => {
                 updateCalendarButtonState();
                 stopCalendarAutoRefresh();
             }
+            updateGoalSuggestions(dom.sessionGoal.value);
         });
+        dom.sessionGoal.addEventListener('focus', () => updateGoalSuggestions(dom.sessionGoal.value));
     }
 
     await loadSettings();
+    await loadProjects();
     const recoveredSession = await recoverActiveSession();
     if (recoveredSession === false) {
         resetSession({ clearPersistedState: false });
@@ -966,18 +1523,99 @@ window.addEventListener('DOMContentLoaded', async () => {
                 isSessionRunning,
                 now
             );
+            adaptiveBounds = adaptiveBoundsFromSettings(event.payload);
+            samplingSettings = event.payload;
+            if (activeTemplate) {
+                // The template's timers apply until the session ends.
+                settingsBeforeTemplate = updated.settings;
+                pomodoroTemplate = pomodoroFromSettings(event.payload);
+                return;
+            }
             settings = updated.settings;
+            pomodoroTemplate = pomodoroFromSettings(event.payload);
             sessionTimeRemaining = updated.remainders.sessionTimeRemaining;
             checkInTimeRemaining = updated.remainders.checkInTimeRemaining;
             writeTimeRemaining = updated.remainders.writeTimeRemaining;
             if (isSessionRunning) {
                 sessionEndTimestamp = updated.deadlines.sessionEndTimestamp;
-                checkInEndTimestamp = updated.deadlines.checkInEndTimestamp;
+                if (!isPomodoroBreak(pomodoro)) checkInEndTimestamp = updated.deadlines.checkInEndTimestamp;
             }
             persistActiveSession();
             updateDisplay();
         }
     });
 
-    window.addEventListener('beforeunload', () => unlisten());
+    // Requests from the local control socket (see src-tauri/src/control.rs).
+    const unlistenControl = await listen('control-command', (event) => {
+        handleControlCommand(event?.payload || {}).catch((error) =>
+            console.error('Control command failed:', error)
+        );
+    });
+
+    const unlistenClock = await listen('clock-change', (event) => handleClockChange(event?.payload || {}));
+
+    window.addEventListener('beforeunload', () => {
+        unlisten();
+        unlistenControl();
+        unlistenClock();
+    });
 });
+
+async function handleControlCommand({ command, goal, status, template, answered }) {
+    switch (command) {
+        case 'start':
+            // A template or goal starts a new session; never apply one to the
+            // session that is running or paused.
+            if ((template || goal) && sessionStartedAt) {
+                statusOverride = template
+                    ? 'Finish the current session before starting a template'
+                    : 'Finish the current session before starting a new goal';
+                updateDisplay();
+                break;
+            }
+            if (template) applyTemplate(template);
+            if (goal && dom.sessionGoal) dom.sessionGoal.value = goal;
+            if (!isSessionRunning && !isWriting) await startSession({ autoHide: false });
+            break;
+        case 'resume':
+            if (!isSessionRunning && !isWriting) await startSession({ autoHide: false });
+            break;
+        case 'pause':
+            pauseSession({ reason: 'user' });
+            break;
+        case 'end':
+            endSessionEarly('ended_early');
+            break;
+        case 'checkInLogged':
+            // The backend already journaled this check-in and computed where
+            // it leaves the session; only adopt that and advance the timers.
+            if (answered) {
+                checkInsCompleted = answered.checkInsCompleted;
+                skippedCheckIns = answered.skippedCheckIns;
+                lastCheckInWasSkipped = answered.lastCheckInWasSkipped;
+                adaptive = answered.adaptive;
+                sampling = answered.sampling;
+            }
+            window.dispatchEvent(new CustomEvent('ft:checkin-created'));
+            if (isWriting) {
+                promptShownAt = null;
+                await hideMainWindow('control check-in');
+                await endWriteTime({ status, checkInDelaySeconds: answered?.checkInTimeRemaining });
+            } else {
+                const now = Date.now();
+                if (isSessionRunning) captureRemainingTimes(now);
+                checkInTimeRemaining = answered?.checkInTimeRemaining ?? nextCheckInDelaySeconds();
+                if (isSessionRunning) {
+                    const deadlines = resumeTimerDeadlines({ sessionTimeRemaining, checkInTimeRemaining }, now);
+                    sessionEndTimestamp = deadlines.sessionEndTimestamp;
+                    checkInEndTimestamp = deadlines.checkInEndTimestamp;
+                }
+                statusOverride = `Logged: ${status}`;
+                persistActiveSession();
+                updateDisplay();
+            }
+            break;
+        default:
+            console.warn('Unknown control command:', command);
+    }
+}
@@ -2,9 +2,18 @@ import test from 'node:test';
 import assert from 'node:assert/strict';
 import {
     DEFAULT_TIMER_SETTINGS,
+    addSubtask,
     applySettingsUpdate,
     captureTimerRemainders,
+    isPomodoroBreak,
+    moveSubtask,
+    parseTags,
+    nextPomodoroPhase,
+    pomodoroFromSettings,
     recoveredSessionSnapshot,
+    sampleCheckInDelay,
+    samplingFromSettings,
+    setSubtaskDone,
     resumeTimerDeadlines
 } from '../src/js/timer-state.mjs';
 
@@ -82,7 +91,114 @@ test('restart recovery presents an explicit interruption without resetting saved
     }, 4_000_000);
 
     assert.equal(recovered.statusMessage, 'Session interrupted by restart - review and resume');
+    assert.equal(recovered.autoResume, false);
     assert.equal(recovered.sessionTimeRemaining, 42_000);
     assert.equal(recovered.checkInTimeRemaining, 1_200);
     assert.equal(recovered.checkInsCompleted, 3);
 });
+
+test('restart recovery resumes a session the policy left running', () => {
+    const recovered = recoveredSessionSnapshot({
+        phase: 'active',
+        sessionGoal: 'Synthetic test goal',
+        sessionStartedAt: 3_000_000,
+        sessionDuration: 720,
+        checkInInterval: 20,
+        writeTime: 20,
+        sessionTimeRemaining: 42_000,
+        checkInTimeRemaining: 1_200,
+        writeTimeRemaining: 0,
+        checkInsCompleted: 3,
+        skippedCheckIns: 1,
+        lastCheckInWasSkipped: true,
+        focusShieldActive: false,
+        focusShieldUntil: null,
+        recoveryReason: 'Resumed automatically after 2 min of downtime',
+        templateId: 'code-review'
+    }, 4_000_000);
+
+    assert.equal(recovered.autoResume, true);
+    assert.equal(recovered.statusMessage, 'Session resumed after restart');
+    assert.equal(recovered.templateId, 'code-review');
+    assert.equal(recovered.sessionTimeRemaining, 42_000);
+});
+
+test('pomodoro alternates work and breaks with a long break every N cycles', () => {
+    assert.equal(pomodoroFromSettings({ pomodoro_enabled: false }), null);
+    let progress = pomodoroFromSettings({
+        pomodoro_enabled: true,
+        pomodoro_work_minutes: 25,
+        pomodoro_short_break_minutes: 5,
+        pomodoro_long_break_minutes: 15,
+        pomodoro_cycles_before_long_break: 2,
+        pomodoro_auto_start: true
+    });
+    assert.equal(progress.phaseTimeRemaining, 25 * 60);
+
+    const phases = [];
+    for (let i = 0; i < 6; i++) {
+        progress = nextPomodoroPhase(progress);
+        phases.push(progress.phase);
+    }
+
+    assert.deepEqual(phases, ['shortBreak', 'work', 'longBreak', 'work', 'shortBreak', 'work']);
+    assert.equal(progress.cyclesCompleted, 3);
+    assert.equal(progress.breakSeconds, (5 + 15 + 5) * 60);
+    assert.equal(isPomodoroBreak(progress), false);
+    assert.equal(isPomodoroBreak(nextPomodoroPhase(progress)), true);
+});
+
+test('sampled check-in delays keep the minimum gap, average the interval and replay from saved state', () => {
+    assert.equal(samplingFromSettings({ check_in_sampling: 'off' }, 1), null);
+    for (const distribution of ['uniform', 'exponential']) {
+        let sampling = samplingFromSettings(
+            { check_in_sampling: distribution, sampling_min_gap_minutes: 5 },
+            42
+        );
+        const delays = [];
+        let resumed = null;
+        for (let i = 0; i < 2_000; i++) {
+            const drawn = sampleCheckInDelay(sampling, 20 * 60);
+            sampling = drawn.sampling;
+            delays.push(drawn.delay);
+            if (i === 999) resumed = JSON.parse(JSON.stringify(sampling));
+        }
+
+        assert.ok(Math.min(...delays) >= 5 * 60, distribution);
+        assert.ok(Math.max(...delays) <= 4 * 20 * 60, distribution);
+        const mean = delays.reduce((sum, delay) => sum + delay, 0) / delays.length;
+        assert.ok(Math.abs(mean - 20 * 60) < 90, `${distribution} mean ${mean}`);
+        assert.equal(sampleCheckInDelay(resumed, 20 * 60).delay, delays[1_000]);
+    }
+});
+
+test('subtask checklist items are added, checked and reordered with stable ids', () => {
+    let subtasks = [];
+    for (const text of ['Collect merged PRs', '  ', 'Draft highlights', 'Send for review']) {
+        subtasks = addSubtask(subtasks, text);
+    }
+    assert.deepEqual(subtasks.map((subtask) => subtask.id), [1, 2, 3]);
+
+    subtasks = setSubtaskDone(subtasks, 2, true, 1_000);
+    subtasks = setSubtaskDone(subtasks, 2, true, 2_000);
+    assert.equal(subtasks[1].completedAt, 1_000);
+
+    subtasks = moveSubtask(subtasks, 3, 0);
+    assert.deepEqual(subtasks.map((subtask) => subtask.id), [3, 1, 2]);
+    subtasks = moveSubtask(subtasks, 3, 10);
+    assert.deepEqual(subtasks.map((subtask) => subtask.id), [1, 2, 3]);
+    assert.equal(addSubtask(subtasks, 'Publish').at(-1).id, 4);
+
+    subtasks = setSubtaskDone(subtasks, 2, false);
+    assert.equal(subtasks[1].completedAt, null);
+    assert.deepEqual(recoveredSessionSnapshot({ phase: 'paused', subtasks }).subtasks, subtasks);
+});
+
+test('tags are split on commas and older saved sessions have no project', () => {
+    assert.deepEqual(parseTags(' client,urgent ,, client,Client '), ['client', 'urgent', 'Client']);
+    assert.deepEqual(parseTags(''), []);
+
+    const recovered = recoveredSessionSnapshot({ phase: 'paused' });
+    assert.equal(recovered.project, null);
+    assert.deepEqual(recovered.tags, []);
+});